@@ -1,8 +1,11 @@
+mod retrieval;
+
 use itertools::Itertools;
 use crate::errors::AppResult;
 use rig::completion::Prompt;
 use rig::providers::openai::Client;
 use crate::storage::file_contents::FileContentRecords;
+pub use retrieval::RetrievalOptions;
 
 pub struct OpenAiAgent(Client);
 
@@ -59,7 +62,11 @@ impl OpenAiAgent {
 
     /// https://dashscope.aliyuncs.com/compatible-mode/v1
     pub async fn query_context(&self, context: &FileContentRecords) -> AppResult<String> {
-        let s = context.0.iter().map(|v|v.text.clone()).join("\n");
+        // 带上页码或时间，回答时可以引用来源页或视频中的时间点
+        let s = context.0.iter().map(|v| match v.location() {
+            Some(location) => format!("{} ({})", v.text, location),
+            None => v.text.clone(),
+        }).join("\n");
         let comedian_agent = self.0
             .agent("deepseek-r1")
             .build();
@@ -0,0 +1,82 @@
+//! 检索配置：按文本块检索，交给模型的是去重后的上级文本（页或章节中相邻的文本块）或文本块前后相邻的文本块组成的段落，
+//! 总长度不超过 token 预算
+
+use crate::errors::{AidenErrors, AppResult};
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+
+/// 当前生效的检索配置，启动时从设置表加载
+static OPTIONS: LazyLock<RwLock<RetrievalOptions>> = LazyLock::new(Default::default);
+
+/// 上下文 token 预算的范围
+const CONTEXT_TOKENS_RANGE: std::ops::RangeInclusive<usize> = 256..=32768;
+/// 前后相邻文本块数的上限
+const MAX_WINDOW: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetrievalOptions {
+    /// 交给模型的上下文 token 数上限
+    pub context_tokens: usize,
+    /// 每个检索结果前后各取的相邻文本块数，为 0 时使用上级文本
+    pub window: usize,
+}
+
+impl Default for RetrievalOptions {
+    fn default() -> Self {
+        Self {
+            context_tokens: 2048,
+            window: 0,
+        }
+    }
+}
+
+impl RetrievalOptions {
+    /// 在设置表中的键
+    pub const SETTINGS_KEY: &'static str = "retrieval_options";
+
+    /// 当前生效的检索配置
+    pub fn current() -> Self {
+        *OPTIONS.read().unwrap()
+    }
+
+    /// 替换当前生效的检索配置，之后的查询使用新的配置
+    pub fn apply(self) {
+        *OPTIONS.write().unwrap() = self;
+    }
+
+    /// 检查 token 预算和相邻文本块数，保存前调用
+    pub fn check(&self) -> AppResult<()> {
+        if !CONTEXT_TOKENS_RANGE.contains(&self.context_tokens) {
+            return Err(AidenErrors::String(format!(
+                "上下文 token 数 {} 超出范围 {}-{}",
+                self.context_tokens,
+                CONTEXT_TOKENS_RANGE.start(),
+                CONTEXT_TOKENS_RANGE.end()
+            )));
+        }
+        if self.window > MAX_WINDOW {
+            return Err(AidenErrors::String(format!("相邻文本块数 {} 超过上限 {}", self.window, MAX_WINDOW)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let options: RetrievalOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, RetrievalOptions::default());
+        options.check().unwrap();
+
+        let options: RetrievalOptions = serde_json::from_str(r#"{"context_tokens": 100}"#).unwrap();
+        assert!(options.check().is_err());
+        let options: RetrievalOptions = serde_json::from_str(r#"{"window": 2}"#).unwrap();
+        assert_eq!(options.context_tokens, 2048);
+        options.check().unwrap();
+        assert!(RetrievalOptions { window: 20, ..options }.check().is_err());
+    }
+}
@@ -1,7 +1,8 @@
-use crate::embed::AidenTextEmbedder;
+use crate::embed::{AidenTextEmbedder, Embedded};
 use crate::storage::file_contents::{FileContentRecordFields, FileContentsRepo};
-use crate::storage::files::FilesRepo;
-use embed_anything::embeddings::embed::EmbedData;
+use crate::storage::file_parents::{FileParentRecordFields, FileParentsRepo};
+use crate::storage::files::{FilesRepo, SyncStatus};
+use crate::storage::skipped::SkippedRepo;
 use flume::{Receiver, Sender};
 use std::path::Path;
 use std::time::Duration;
@@ -9,8 +10,9 @@ use tokio::time::sleep;
 
 #[derive(Clone)]
 pub struct EmbedManager {
-    tx: Sender<(String, Vec<EmbedData>)>,
-    rx: Option<Receiver<(String, Vec<EmbedData>)>>,
+    /// (同步路径, 向量化结果)
+    tx: Sender<(String, Embedded)>,
+    rx: Option<Receiver<(String, Embedded)>>,
 }
 
 impl EmbedManager {
@@ -25,9 +27,9 @@ impl EmbedManager {
                     } else {
                         for file in fr {
                             let _ = repo2.update_progress_and_sync_time(&file.file_path, 1).await;
-                            let data = embedder.embedding(Path::new(&file.file_path)).await;
+                            let embedded = embedder.embedding(Path::new(&file.file_path)).await;
                             let _ = repo2.update_progress_and_sync_time(&file.file_path, 50).await;
-                            let _ = tx.send_async((file.file_path, data)).await;
+                            let _ = tx.send_async((file.file_path, embedded)).await;
                         }
                     }
                 } else {
@@ -37,19 +39,41 @@ impl EmbedManager {
         });
     }
 
-    pub fn start_write_embedding(&mut self, files: FilesRepo, repo: FileContentsRepo) {
+    pub fn start_write_embedding(&mut self, files: FilesRepo, repo: FileContentsRepo, parents: FileParentsRepo, skipped: SkippedRepo) {
         let rx = self.rx.take().unwrap();
         tauri::async_runtime::spawn(async move {
-            while let Ok((file_path, data)) = rx.recv_async().await {
-                if !data.is_empty() {
-                    if let Err(e) = repo.insert_data(FileContentRecordFields::new(file_path.clone(), data)).await {
-                        log::error!("Failed to insert data, {}: {}", file_path, e);
+            while let Ok((file_path, mut embedded)) = rx.recv_async().await {
+                if let Err(e) = skipped.save(&file_path, &embedded.skipped).await {
+                    log::error!("Failed to save skipped files, {}: {}", file_path, e);
+                }
+                // 目录中只有部分文件需要密码或被跳过时其余文件照常保存，需要密码的文件在界面上逐个输入
+                let status = if !embedded.data.is_empty() || embedded.skipped.is_empty() {
+                    SyncStatus::Normal
+                } else if embedded.locked() {
+                    SyncStatus::NeedsPassword
+                } else {
+                    SyncStatus::Skipped
+                };
+                if !embedded.data.is_empty() {
+                    // 上级文本从文本块元数据中取出，单独保存
+                    let parent_records = FileParentRecordFields::new(file_path.clone(), &mut embedded.data);
+                    if let Err(e) = parents.save(parent_records).await {
+                        log::error!("Failed to save parents, {}: {}", file_path, e);
                     }
-                    if let Err(e) = files.update_progress_and_sync_time(&file_path, 100).await {
-                        log::error!("Failed to update progress, {}: {}", file_path, e);
+                    if let Err(e) = repo.insert_data(FileContentRecordFields::new(file_path.clone(), embedded.data)).await {
+                        log::error!("Failed to insert data, {}: {}", file_path, e);
                     }
-                } else {
+                } else if status == SyncStatus::Normal {
                     log::warn!("Data is empty, {}.", file_path);
+                    continue;
+                }
+                // 加密文件和全部被跳过的同步项单独标记，在界面上显示原因
+                let updated = match status {
+                    SyncStatus::Normal => files.update_progress_and_sync_time(&file_path, 100).await,
+                    _ => files.update_status_and_sync_time(&file_path, status).await,
+                };
+                if let Err(e) = updated {
+                    log::error!("Failed to update progress, {}: {}", file_path, e);
                 }
             }
         });
@@ -2,7 +2,7 @@ pub mod job;
 pub mod text_loader;
 pub mod statistical;
 
-use crate::errors::AppResult;
+use crate::errors::{AidenErrors, AppResult};
 use candle_nn::VarBuilder;
 use embed_anything::config::TextEmbedConfig;
 use embed_anything::embeddings::embed::{EmbedData, Embedder, TextEmbedder};
@@ -10,13 +10,13 @@ use embed_anything::embeddings::local::bert::BertEmbedder;
 use embed_anything::embeddings::local::pooling::Pooling;
 use embed_anything::embeddings::select_device;
 use embed_anything::models::bert::{BertModel, Config, DTYPE};
-use embed_anything::text_loader::SplittingStrategy;
 use std::fs;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
 use crate::extract::embed_file;
+use crate::storage::skipped::{OCR_UNAVAILABLE, PASSWORD_REQUIRED};
 
 #[derive(Clone)]
 pub struct AidenTextEmbedder(Arc<Embedder>);
@@ -59,15 +59,15 @@ impl AidenTextEmbedder {
         Ok(aiden_embedder)
     }
 
+    /// 文本块大小、重叠和切分策略按文件类型配置，见 `ChunkingProfiles`
     pub fn config(&self) -> TextEmbedConfig {
         TextEmbedConfig::default()
-            .with_chunk_size(256, Some(0.3))
             .with_batch_size(32)
             .with_buffer_size(32)
-            .with_splitting_strategy(SplittingStrategy::Semantic)
             .with_semantic_encoder(self.0.clone())
     }
-    pub async fn embedding<P: AsRef<Path>>(&self, path: P) -> Vec<EmbedData> {
+    /// 向量化文件或目录
+    pub async fn embedding<P: AsRef<Path>>(&self, path: P) -> Embedded {
         let mut files = Vec::new();
         let path = path.as_ref();
         if path.is_dir() {
@@ -82,21 +82,50 @@ impl AidenTextEmbedder {
             handles.push(tauri::async_runtime::spawn(async move { self_clone.embedding_file(file).await }));
         }
 
-        let mut data = Vec::with_capacity(handles.len());
+        let mut embedded = Embedded::default();
         for x in handles {
-            if let Some(s) = x.await.ok().flatten() {
-                data.extend(s);
+            match x.await {
+                Ok(Ok(Some(s))) => embedded.data.extend(s),
+                Ok(Err(e)) => match e.downcast_ref::<AidenErrors>() {
+                    Some(AidenErrors::PasswordRequired(file)) => {
+                        log::warn!("Password required, {}.", file);
+                        embedded.skipped.push((file.clone(), PASSWORD_REQUIRED.to_string()));
+                    }
+                    Some(AidenErrors::LimitExceeded(file, reason)) => {
+                        log::warn!("Skip {}: {}", file, reason);
+                        embedded.skipped.push((file.clone(), reason.clone()));
+                    }
+                    Some(AidenErrors::OcrUnavailable(file)) => {
+                        log::warn!("OCR unavailable, {}.", file);
+                        embedded.skipped.push((file.clone(), OCR_UNAVAILABLE.to_string()));
+                    }
+                    Some(AidenErrors::UnsupportedFileType(file)) => log::debug!("Unsupported file, {}.", file),
+                    _ => log::warn!("Failed to embed file: {}", e),
+                },
+                _ => {}
             }
         }
 
-        data
+        embedded
     }
 
-    pub async fn embedding_file<P: AsRef<Path>>(&self, path: P) -> Option<Vec<EmbedData>> {
-        embed_file(path, &self.0, Some(&self.config()), None::<fn(Vec<EmbedData>)>)
-            .await
-            .ok()
-            .flatten()
+    pub async fn embedding_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<Option<Vec<EmbedData>>> {
+        embed_file(path, &self.0, Some(&self.config()), None::<fn(Vec<EmbedData>)>).await
+    }
+}
+
+/// 一个同步项（文件或目录）的向量化结果
+#[derive(Default)]
+pub struct Embedded {
+    pub data: Vec<EmbedData>,
+    /// 超出提取限制、缺少密码或缺少 OCR 模型而跳过的文件 (文件路径, 原因)
+    pub skipped: Vec<(String, String)>,
+}
+
+impl Embedded {
+    /// 是否有文件因加密缺少密码而未能读取
+    pub fn locked(&self) -> bool {
+        self.skipped.iter().any(|(_, reason)| reason == PASSWORD_REQUIRED)
     }
 }
 
@@ -108,14 +137,45 @@ impl Deref for AidenTextEmbedder {
     }
 }
 
+/// 版本控制、依赖和构建输出目录，只在代码仓库中跳过，其他目录中同名的目录（如资料中的 build、dist）照常索引
+static IGNORED_DIRS: &[&str] = &[".git", ".svn", ".hg", ".idea", ".vscode", "node_modules", "target", "__pycache__", ".venv", "venv", "dist", "build"];
+/// 代码仓库根目录的标志：版本控制目录或项目清单文件
+static REPOSITORY_MARKERS: &[&str] = &[
+    ".git",
+    ".svn",
+    ".hg",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "CMakeLists.txt",
+];
+
+fn is_repository(dir: &Path) -> bool {
+    REPOSITORY_MARKERS.iter().any(|marker| dir.join(marker).exists())
+}
+
 fn get_files_in_dir<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
+    // 只从同步的目录向下查找仓库标志，同步目录的上级（如家目录中的 package.json）不影响索引
+    collect_files(path.as_ref(), false)
+}
+
+fn collect_files(dir: &Path, in_repository: bool) -> Vec<PathBuf> {
+    let in_repository = in_repository || is_repository(dir);
     let mut files = Vec::new();
-    if let Ok(entries) = fs::read_dir(path) {
+    if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_dir() {
+                if in_repository && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| IGNORED_DIRS.contains(&n)) {
+                    continue;
+                }
                 // 递归获取子目录中的文件
-                files.extend(get_files_in_dir(path));
+                files.extend(collect_files(&path, in_repository));
             } else if path.is_file() {
                 // 如果是文件，添加到结果中
                 files.push(path);
@@ -132,6 +192,34 @@ mod tests {
     use std::path::Path;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_get_files_in_dir() {
+        let dir = tempdir().unwrap();
+        let write = |path: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        };
+        write("docs/build/manual.md");
+        write("repo/Cargo.toml");
+        write("repo/src/main.rs");
+        write("repo/target/debug/build.rs");
+        write("repo/docs/dist/guide.md");
+
+        let mut files = get_files_in_dir(dir.path())
+            .into_iter()
+            .map(|f| f.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        files.sort();
+        // 代码仓库之外的 build 目录照常索引
+        let expected = ["docs/build/manual.md", "repo/Cargo.toml", "repo/src/main.rs"];
+        assert_eq!(files, expected.iter().map(PathBuf::from).collect::<Vec<_>>());
+
+        // 同步目录之上的仓库标志不影响索引
+        let docs = dir.path().join("repo").join("docs");
+        assert_eq!(get_files_in_dir(&docs), vec![docs.join("dist").join("guide.md")]);
+    }
+
     #[tokio::test]
     async fn test_embedding_file() {
         let dir = tempdir().unwrap();
@@ -1,11 +1,18 @@
-use crate::embed::text_loader::TOKENIZER;
+use crate::errors::AppResult;
 use candle::{Device, Tensor};
 use embed_anything::embeddings::{embed::Embedder, select_device};
-use itertools::{enumerate, Itertools};
+use itertools::enumerate;
+use std::ops::Range;
 use std::{cmp::max, sync::Arc};
-use text_splitter::{ChunkConfig, TextSplitter};
 use tokenizers::Tokenizer;
 
+/// 计算语义相似度的片段大小（token）
+const SPLIT_TOKENS: usize = 64;
+/// 句末标点，包括中文全角标点和英文标点，英文句号单独处理
+const SENTENCE_TERMINATORS: &[char] = &['。', '！', '？', '；', '…', '．', '｡', '!', '?', ';'];
+/// 句末标点后属于同一句的右引号和右括号
+const CLOSING_PUNCTUATION: &[char] = &['”', '’', '"', '\'', '」', '』', '）', ')', '】', '》', ']'];
+
 pub struct StatisticalChunker {
     pub encoder: Arc<Embedder>,
     pub device: Device,
@@ -46,89 +53,70 @@ impl StatisticalChunker {
         }
     }
 
+    /// 按句子切分文本，相邻的句子合并为不超过 chunk_size 个 token 的片段，超过 chunk_size 的单个句子独占一个片段。
+    /// 支持中文和英文的句末标点，见 `split_sentences`
     pub fn split_into_sentences(&self, text: &str, chunk_size: usize) -> Option<Vec<String>> {
-        let mut chunk = Vec::new();
-        let mut chunks = Vec::new();
-
-        if text.is_empty() {
+        if text.trim().is_empty() {
             return None;
         }
-        if text.len() < chunk_size {
-            chunks.push(text.to_owned());
-            return Some(chunks);
-        }
-
-        let sentences: Vec<&str> = text.split_terminator(&['.', ';'][..]).collect();
-
-        for sentence in sentences {
-            let sentence_with_period = format!("{}.", sentence);
-
-            let words: Vec<String> = sentence_with_period.split_whitespace().map(|word| word.to_owned()).collect();
-
-            chunk.extend(words);
-
-            if chunk.len() >= chunk_size {
-                chunks.push(chunk.join(" "));
-                chunk.clear();
-            }
-        }
-        if !chunk.is_empty() {
-            chunks.push(chunk.join(" "));
-        }
-
-        Some(chunks)
+        let ranges = group_sentences(text, &split_sentences(text), &self.tokenizer, chunk_size);
+        Some(ranges.into_iter().map(|(start, end)| text[start..end].to_string()).collect())
     }
 
-    pub async fn chunk(&self, text: &str, batch_size: usize) -> Vec<String> {
-        let splitter = TextSplitter::new(ChunkConfig::new(500).with_sizer(TOKENIZER.clone()));
-        let splits = splitter.chunks(text).collect::<Vec<_>>();
+    /// 语义切分：按句子切分为片段并计算每个片段的向量，在与前文语义相似度低的位置切开，合并为不超过
+    /// max_split_tokens 的文本块。文本块是原文的连续片段，并带有其中片段向量的平均值，
+    /// 编码器与向量化文本块的模型相同时可以直接使用，不必重新计算
+    pub async fn chunk(&self, text: &str, batch_size: usize) -> AppResult<Vec<SemanticChunk>> {
+        let ranges = group_sentences(text, &split_sentences(text), &self.tokenizer, SPLIT_TOKENS);
+        if ranges.is_empty() {
+            return Ok(Vec::new());
+        }
+        let splits = ranges.iter().map(|(start, end)| text[*start..*end].to_string()).collect::<Vec<_>>();
         if self.verbose {
             for split in splits.iter() {
                 println!("-----Split---\n{}", split);
             }
         }
-        let mut chunks: Vec<String> = Vec::new();
-        let mut last_chunk: String = String::new();
-
-        for i in &(0..splits.len()).chunks(batch_size) {
-            let indices = i.collect::<Vec<_>>();
-            let mut batch_splits = indices.into_iter().map(|idx| splits[idx].to_string()).collect::<Vec<_>>();
-
-            if !last_chunk.is_empty() {
-                batch_splits = vec![last_chunk.clone()].into_iter().chain(batch_splits).collect::<Vec<_>>();
-            }
-
-            let encoded_splits = self.encoder.embed(&batch_splits, Some(16)).await.unwrap();
-            let encoded_splits = encoded_splits.into_iter().map(|x| x.to_dense().unwrap()).collect::<Vec<_>>();
-
-            let similarities = self._calculate_similarity_scores(&encoded_splits);
-            let calculated_threshold = self._find_optimal_threshold(&batch_splits, &similarities);
-
-            let split_indices = self._find_split_indices(&similarities, calculated_threshold);
-            let doc_chunks: Vec<String> = self._split_documents(batch_splits, split_indices);
-
-            if doc_chunks.len() > 1 {
-                //add doc chunks to chunks
-                chunks.extend(doc_chunks[..doc_chunks.len() - 1].iter().cloned());
-                // last chunk is last element of doc_chunks
-                last_chunk = doc_chunks.last().unwrap().to_string();
-            } else {
-                last_chunk.clone_from(&doc_chunks[0]);
-            }
-        }
-        if !last_chunk.is_empty() {
-            chunks.push(last_chunk);
-        }
 
+        let encoded_splits = self
+            .encoder
+            .embed(&splits, Some(batch_size))
+            .await?
+            .into_iter()
+            .map(|encoding| encoding.to_dense())
+            .collect::<Result<Vec<_>, _>>()?;
+        let token_counts = splits.iter().map(|split| self.count_tokens(split)).collect::<Vec<_>>();
+
+        // 相似度的中位数和标准差至少需要两个值
+        let split_indices = if splits.len() > 2 {
+            let similarities = self._calculate_similarity_scores(&encoded_splits)?;
+            let calculated_threshold = self._find_optimal_threshold(&token_counts, &similarities);
+            self._find_split_indices(&similarities, calculated_threshold)
+        } else {
+            Vec::new()
+        };
+
+        let chunks = self
+            ._split_documents(&token_counts, &split_indices)
+            .into_iter()
+            .map(|docs| SemanticChunk {
+                text: text[ranges[docs.start].0..ranges[docs.end - 1].1].to_string(),
+                embedding: mean_embedding(&encoded_splits[docs]),
+            })
+            .collect::<Vec<_>>();
         if self.verbose {
             for chunk in chunks.iter() {
-                println!("-----Chunk---\n{}", chunk);
+                println!("-----Chunk---\n{}", chunk.text);
             }
         }
-        chunks
+        Ok(chunks)
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer.encode(text, false).map(|e| e.len()).unwrap_or(text.len())
     }
 
-    fn _calculate_similarity_scores(&self, encoded_splits: &[Vec<f32>]) -> Vec<f32> {
+    fn _calculate_similarity_scores(&self, encoded_splits: &[Vec<f32>]) -> AppResult<Vec<f32>> {
         let embed_dim = encoded_splits[0].len();
         let mut raw_similarities: Vec<f32> = Vec::new();
 
@@ -136,38 +124,30 @@ impl StatisticalChunker {
             encoded_splits.iter().flatten().copied().collect::<Vec<_>>(),
             (encoded_splits.len(), embed_dim),
             &self.device,
-        )
-        .unwrap();
+        )?;
 
         for i in 1..encoded_splits.len() {
             let window_start = max(0, i as isize - self.window_size as isize) as usize;
-            let indexes = Tensor::arange(window_start as i64, i as i64, &self.device).unwrap();
-            let encoded_splits_window = encoded_splits_tensor.index_select(&indexes, 0).unwrap();
+            let indexes = Tensor::arange(window_start as i64, i as i64, &self.device)?;
+            let encoded_splits_window = encoded_splits_tensor.index_select(&indexes, 0)?;
 
-            let cumulative_context = encoded_splits_window.mean_keepdim(0).unwrap();
-            let cumulative_context_norm = cumulative_context.sqr().unwrap().get(0).unwrap().sum(0).unwrap().sqrt().unwrap();
-            let encoded_splits_tensor_norm = encoded_splits_tensor.get(i).unwrap().sqr().unwrap().sum(0).unwrap().sqrt().unwrap();
-            let norm = (encoded_splits_tensor_norm * cumulative_context_norm).unwrap();
+            let cumulative_context = encoded_splits_window.mean_keepdim(0)?;
+            let cumulative_context_norm = cumulative_context.sqr()?.get(0)?.sum(0)?.sqrt()?;
+            let encoded_splits_tensor_norm = encoded_splits_tensor.get(i)?.sqr()?.sum(0)?.sqrt()?;
+            let norm = (encoded_splits_tensor_norm * cumulative_context_norm)?;
             let curr_sim_score = encoded_splits_tensor
-                .get(i)
-                .unwrap()
-                .reshape((1, embed_dim))
-                .unwrap()
-                .matmul(&cumulative_context.transpose(0, 1).unwrap())
-                .unwrap()
-                .squeeze(1)
-                .unwrap();
-
-            let curr_sim_score_scaled = curr_sim_score.broadcast_div(&norm).unwrap().get(0).unwrap().to_vec0::<f32>().unwrap();
+                .get(i)?
+                .reshape((1, embed_dim))?
+                .matmul(&cumulative_context.transpose(0, 1)?)?
+                .squeeze(1)?;
+
+            let curr_sim_score_scaled = curr_sim_score.broadcast_div(&norm)?.get(0)?.to_vec0::<f32>()?;
             raw_similarities.push(curr_sim_score_scaled);
         }
-        raw_similarities
+        Ok(raw_similarities)
     }
 
-    fn _find_optimal_threshold(&self, batch_splits: &[String], similarities: &Vec<f32>) -> f32 {
-        let tokens = self.tokenizer.encode_batch(batch_splits.to_vec(), true).unwrap();
-        let token_counts = tokens.iter().map(|tokens| tokens.get_ids().len()).collect::<Vec<_>>();
-
+    fn _find_optimal_threshold(&self, token_counts: &[usize], similarities: &Vec<f32>) -> f32 {
         let cumulative_token_counts = std::iter::once(&0)
             .chain(token_counts.iter())
             .scan(0, |state, &x| {
@@ -189,7 +169,7 @@ impl StatisticalChunker {
         let mut calculated_threshold = 0.0;
 
         while low <= high {
-            println!("Iteration: {}", iteration);
+            log::debug!("Iteration: {}", iteration);
             calculated_threshold = (low + high) / 2.0;
             let split_indices = self._find_split_indices(similarities, calculated_threshold);
             let split_token_counts: Vec<usize> = [0]
@@ -201,7 +181,8 @@ impl StatisticalChunker {
 
             median_tokens = statistical::median(&split_token_counts);
 
-            if self.min_split_tokens - self.split_token_tolerance <= median_tokens && median_tokens <= self.max_split_tokens + self.split_token_tolerance
+            if self.min_split_tokens.saturating_sub(self.split_token_tolerance) <= median_tokens
+                && median_tokens <= self.max_split_tokens + self.split_token_tolerance
             {
                 break;
             } else if median_tokens < self.min_split_tokens {
@@ -223,42 +204,190 @@ impl StatisticalChunker {
         split_indices
     }
 
-    fn _split_documents(&self, docs: Vec<String>, split_indices: Vec<usize>) -> Vec<String> {
-        let tokens = self.tokenizer.encode_batch(docs.to_vec(), true).unwrap();
-        let token_counts = tokens.iter().map(|tokens| tokens.get_ids().len()).collect::<Vec<_>>();
-
-        let mut chunks: Vec<String> = Vec::new();
-        let mut current_split = Vec::new();
+    /// 按切分位置合并片段，返回每个文本块包含的片段区间。文本块不超过 max_split_tokens（单个片段超过时除外），
+    /// 不足 min_split_tokens 时不在切分位置切开
+    fn _split_documents(&self, token_counts: &[usize], split_indices: &[usize]) -> Vec<Range<usize>> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
         let mut current_tokens_count = 0;
 
-        for (doc_idx, doc) in enumerate(docs) {
-            let doc_token_count = token_counts[doc_idx];
-            if split_indices.contains(&(doc_idx + 1))
-                && self.min_split_tokens <= current_tokens_count + doc_token_count
-                && current_tokens_count + doc_token_count <= self.max_split_tokens
-            {
-                current_split.push(doc);
-
-                chunks.push(current_split.join("\n"));
-                current_split = Vec::new();
+        for (doc_idx, doc_token_count) in enumerate(token_counts) {
+            // 超过上限时先切开，不足下限的文本块同样保留，不丢弃文本
+            if doc_idx > start && current_tokens_count + doc_token_count > self.max_split_tokens {
+                chunks.push(start..doc_idx);
+                start = doc_idx;
                 current_tokens_count = 0;
-                continue;
             }
-            if current_tokens_count + doc_token_count > self.max_split_tokens {
-                if current_tokens_count >= self.min_split_tokens {
-                    chunks.push(current_split.join("\n"));
-                }
-                current_split = Vec::new();
+            current_tokens_count += doc_token_count;
+            if split_indices.contains(&(doc_idx + 1)) && current_tokens_count >= self.min_split_tokens {
+                chunks.push(start..doc_idx + 1);
+                start = doc_idx + 1;
                 current_tokens_count = 0;
             }
-            current_split.push(doc);
-            current_tokens_count += doc_token_count;
         }
-
-        if !current_split.is_empty() {
-            chunks.push(current_split.join("\n"));
+        if start < token_counts.len() {
+            chunks.push(start..token_counts.len());
         }
 
         chunks
     }
 }
+
+/// 语义切分出的文本块
+#[derive(Debug, Clone)]
+pub struct SemanticChunk {
+    pub text: String,
+    /// 文本块中各片段向量的平均值
+    pub embedding: Vec<f32>,
+}
+
+/// 向量的平均值，缩放到各向量长度的平均值，与模型输出的向量（如已归一化的向量）保持同样的尺度
+fn mean_embedding(embeddings: &[Vec<f32>]) -> Vec<f32> {
+    let norm = |vector: &[f32]| vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let mut mean = vec![0.0; embeddings.first().map(Vec::len).unwrap_or_default()];
+    for embedding in embeddings {
+        mean.iter_mut().zip(embedding).for_each(|(m, x)| *m += x);
+    }
+    let target = embeddings.iter().map(|e| norm(e)).sum::<f32>() / embeddings.len().max(1) as f32;
+    let current = norm(&mean);
+    if current > 0.0 {
+        mean.iter_mut().for_each(|m| *m *= target / current);
+    }
+    mean
+}
+
+/// 切分句子，返回每个句子在文本中的字节区间（已去掉首尾空白）。
+/// 句子在句末标点（`。！？；…` 及英文的 `!?;`）或空行处结束，英文句号后需要有空白，避免拆开 3.14、example.com。
+/// 连续的句末标点（如 `？！`、`……`）和紧随其后的右引号、右括号属于同一句
+pub fn split_sentences(text: &str) -> Vec<(usize, usize)> {
+    let mut sentences = Vec::new();
+    let mut push = |start: usize, end: usize| {
+        let sentence = &text[start..end];
+        let trimmed = sentence.trim_start();
+        let start = start + sentence.len() - trimmed.len();
+        let end = start + trimmed.trim_end().len();
+        if end > start {
+            sentences.push((start, end));
+        }
+    };
+
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let is_end = match c {
+            '.' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            '\n' => chars.peek().is_some_and(|(_, next)| *next == '\n'),
+            c => SENTENCE_TERMINATORS.contains(&c),
+        };
+        if !is_end {
+            continue;
+        }
+        let mut end = idx + c.len_utf8();
+        while let Some((next_idx, next)) = chars.peek().copied() {
+            if next == '.' || SENTENCE_TERMINATORS.contains(&next) || CLOSING_PUNCTUATION.contains(&next) {
+                end = next_idx + next.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        push(start, end);
+        start = end;
+    }
+    push(start, text.len());
+    sentences
+}
+
+/// 把相邻的句子合并为不超过 chunk_size 个 token 的片段，返回每个片段在文本中的字节区间
+fn group_sentences(text: &str, sentences: &[(usize, usize)], tokenizer: &Tokenizer, chunk_size: usize) -> Vec<(usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut chunk: Option<(usize, usize)> = None;
+    let mut chunk_tokens = 0;
+    for (start, end) in sentences {
+        let sentence = &text[*start..*end];
+        let tokens = tokenizer.encode(sentence, false).map(|e| e.len()).unwrap_or(sentence.len());
+        chunk = match chunk {
+            Some((chunk_start, chunk_end)) if chunk_tokens + tokens > chunk_size => {
+                chunks.push((chunk_start, chunk_end));
+                chunk_tokens = 0;
+                Some((*start, *end))
+            }
+            Some((chunk_start, _)) => Some((chunk_start, *end)),
+            None => Some((*start, *end)),
+        };
+        chunk_tokens += tokens;
+    }
+    chunks.extend(chunk);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::text_loader::TOKENIZER;
+
+    const MIXED: &str = "烧结炉的温度由三个传感器测量。若读数偏差超过 5%，系统会报警！Check the sensor wiring first. \
+                         Then restart the controller; the value should be 3.14 after calibration?“为什么要校准？”\
+                         因为传感器会漂移……\n\n第二段：更换滤网。See example.com for details";
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = split_sentences(MIXED)
+            .into_iter()
+            .map(|(start, end)| &MIXED[start..end])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sentences,
+            vec![
+                "烧结炉的温度由三个传感器测量。",
+                "若读数偏差超过 5%，系统会报警！",
+                "Check the sensor wiring first.",
+                "Then restart the controller;",
+                "the value should be 3.14 after calibration?",
+                "“为什么要校准？”",
+                "因为传感器会漂移……",
+                "第二段：更换滤网。",
+                "See example.com for details",
+            ]
+        );
+        assert!(split_sentences(" \n\n ").is_empty());
+    }
+
+    #[test]
+    fn test_group_sentences() {
+        let sentences = split_sentences(MIXED);
+        let count = |text: &str| TOKENIZER.encode(text, false).unwrap().len();
+
+        let group = |chunk_size: usize| {
+            group_sentences(MIXED, &sentences, &TOKENIZER, chunk_size)
+                .into_iter()
+                .map(|(start, end)| &MIXED[start..end])
+                .collect::<Vec<_>>()
+        };
+
+        let chunks = group(24);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            // 只有单个句子超过上限时才会超出
+            assert!(count(chunk) <= 24 || split_sentences(chunk).len() == 1, "{}", chunk);
+        }
+        // 片段按原文顺序连接后与原文一致（忽略空白）
+        let strip = |text: &str| text.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        assert_eq!(strip(&chunks.concat()), strip(MIXED));
+        // 上限足够大时整段为一个片段，句子之间保留原有的空白
+        assert_eq!(group(1000), vec![MIXED]);
+    }
+
+    #[test]
+    fn test_mean_embedding() {
+        let unit = |x: f32, y: f32| {
+            let norm = (x * x + y * y).sqrt();
+            vec![x / norm, y / norm]
+        };
+        // 归一化向量的平均值仍是单位向量，方向在两者之间
+        let mean = mean_embedding(&[unit(1.0, 0.0), unit(0.0, 1.0)]);
+        assert!((mean[0] - mean[1]).abs() < 1e-6);
+        assert!((mean[0] * mean[0] + mean[1] * mean[1] - 1.0).abs() < 1e-6);
+        assert_eq!(mean_embedding(&[vec![3.0, 4.0]]), vec![3.0, 4.0]);
+    }
+}
@@ -1,14 +1,10 @@
-use crate::embed::statistical::StatisticalChunker;
+use crate::embed::statistical::{SemanticChunk, StatisticalChunker};
+use crate::errors::AppResult;
+use crate::extract::{extract_text, markdown_blocks, MarkdownBlock};
 use anyhow::Error;
 use chrono::{DateTime, Local};
 use embed_anything::embeddings::embed::Embedder;
 use embed_anything::embeddings::select_device;
-use embed_anything::file_processor::docx_processor::DocxProcessor;
-use embed_anything::file_processor::markdown_processor::MarkdownProcessor;
-use embed_anything::file_processor::pdf_processor::PdfProcessor;
-use embed_anything::file_processor::txt_processor::TxtProcessor;
-use rayon::iter::ParallelBridge;
-use rayon::iter::ParallelIterator;
 use std::collections::HashMap;
 use std::fs;
 use std::sync::{Arc, LazyLock};
@@ -17,81 +13,180 @@ use tokenizers::Tokenizer;
 
 const TOKENIZER_JSON: &[u8] = include_bytes!("../../assets/tokenizers/chinese-roberta-wwm-ext-tokenizer.json");
 pub static TOKENIZER: LazyLock<Tokenizer> = LazyLock::new(|| Tokenizer::from_bytes(TOKENIZER_JSON).unwrap());
+const BERT_BASE_CHINESE_TOKENIZER_JSON: &[u8] = include_bytes!("../../assets/tokenizers/bert-base-chinese-tokenizer.json");
+pub static BERT_BASE_CHINESE_TOKENIZER: LazyLock<Tokenizer> = LazyLock::new(|| Tokenizer::from_bytes(BERT_BASE_CHINESE_TOKENIZER_JSON).unwrap());
 
 #[derive(Debug)]
 pub struct TextLoader {
     pub splitter: TextSplitter<Tokenizer>,
+    pub chunk_size: usize,
+    /// 计算 token 数的分词器
+    pub tokenizer: &'static Tokenizer,
 }
 impl TextLoader {
     pub fn new(chunk_size: usize, overlap_ratio: f32) -> Self {
+        Self::with_tokenizer(chunk_size, overlap_ratio, &TOKENIZER)
+    }
+
+    pub fn with_tokenizer(chunk_size: usize, overlap_ratio: f32, tokenizer: &'static Tokenizer) -> Self {
+        // 重叠必须小于文本块大小
+        let overlap = ((chunk_size as f32 * overlap_ratio) as usize).min(chunk_size.saturating_sub(1));
         Self {
-            splitter: TextSplitter::new(
-                ChunkConfig::new(chunk_size)
-                    .with_overlap(chunk_size * overlap_ratio as usize)
-                    .unwrap()
-                    .with_sizer(TOKENIZER.clone()),
-            ),
+            splitter: TextSplitter::new(ChunkConfig::new(chunk_size).with_overlap(overlap).unwrap().with_sizer(tokenizer.clone())),
+            chunk_size,
+            tokenizer,
         }
     }
-    pub fn split_into_chunks(
-        &self,
-        text: &str,
-        splitting_strategy: embed_anything::text_loader::SplittingStrategy,
-        embedder: Arc<Embedder>,
-    ) -> Option<Vec<String>> {
+    /// 按句子切分文本，单个换行视为空格
+    pub fn split_into_chunks(&self, text: &str) -> Option<Vec<String>> {
+        if text.is_empty() {
+            return None;
+        }
+
+        // 保持文本块的顺序，用于定位文本块所在的页码
+        Some(self.splitter.chunks(&join_lines(text)).map(|chunk| chunk.to_string()).collect())
+    }
+
+    /// 按语义切分文本，单个换行视为空格，文本块不超过 chunk_size。见 `StatisticalChunker::chunk`
+    pub async fn split_semantic(&self, text: &str, encoder: Arc<Embedder>, batch_size: usize) -> AppResult<Vec<SemanticChunk>> {
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let chunker = StatisticalChunker {
+            encoder,
+            device: select_device(),
+            threshold_adjustment: 0.01,
+            dynamic_threshold: true,
+            window_size: 5,
+            min_split_tokens: self.chunk_size / 4,
+            max_split_tokens: self.chunk_size,
+            split_token_tolerance: 10,
+            tokenizer: self.tokenizer.clone(),
+            verbose: false,
+        };
+        chunker.chunk(&join_lines(text), batch_size).await
+    }
+
+    /// 按行切分文本，每个块由若干完整的行组成，用于表格类数据，保证一行数据不会被拆到两个块中。
+    /// 单行超过 chunk_size 时独占一个块。
+    pub fn split_into_rows(&self, text: &str) -> Option<Vec<String>> {
         if text.is_empty() {
             return None;
         }
 
-        // Remove single newlines but keep double newlines
-        let cleaned_text = text
-            .replace("\n\n", "{{DOUBLE_NEWLINE}}")
-            .replace("\n", " ")
-            .replace("{{DOUBLE_NEWLINE}}", "\n\n");
-        let chunks: Vec<String> = match splitting_strategy {
-            embed_anything::text_loader::SplittingStrategy::Sentence => {
-                self.splitter.chunks(&cleaned_text).par_bridge().map(|chunk| chunk.to_string()).collect()
+        let mut chunks = Vec::new();
+        let mut chunk: Vec<&str> = Vec::new();
+        let mut chunk_tokens = 0;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let tokens = self.count_tokens(line);
+            if !chunk.is_empty() && chunk_tokens + tokens > self.chunk_size {
+                chunks.push(chunk.join("\n"));
+                chunk.clear();
+                chunk_tokens = 0;
             }
-            embed_anything::text_loader::SplittingStrategy::Semantic => {
-                let chunker = StatisticalChunker {
-                    encoder: embedder,
-                    device: select_device(),
-                    threshold_adjustment: 0.01,
-                    dynamic_threshold: true,
-                    window_size: 5,
-                    min_split_tokens: 100,
-                    max_split_tokens: 512,
-                    split_token_tolerance: 10,
-                    tokenizer: TOKENIZER.clone(),
-                    verbose: false,
-                };
-
-                tokio::task::block_in_place(|| {
-                    tokio::runtime::Runtime::new()
-                        .unwrap()
-                        .block_on(async { chunker.chunk(&cleaned_text, 64).await })
-                })
+            chunk.push(line);
+            chunk_tokens += tokens;
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk.join("\n"));
+        }
+
+        Some(chunks)
+    }
+
+    /// 切分 markdown，段落、代码块和表格作为整块依次组成文本块，保留原文的换行和缩进，标题行由章节标题代替。
+    /// 超过 chunk_size 的块才会被拆开：段落按句子切分，代码块按行切分并补全围栏，表格按行切分并重复表头。
+    pub fn split_markdown(&self, text: &str) -> Option<Vec<String>> {
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        let mut chunks = Vec::new();
+        let mut chunk: Vec<String> = Vec::new();
+        let mut chunk_tokens = 0;
+        for block in markdown_blocks(text) {
+            let rendered = block.render();
+            let pieces = if self.count_tokens(&rendered) > self.chunk_size {
+                self.split_markdown_block(block)
+            } else {
+                vec![rendered]
+            };
+            for piece in pieces {
+                let tokens = self.count_tokens(&piece);
+                if !chunk.is_empty() && chunk_tokens + tokens > self.chunk_size {
+                    chunks.push(chunk.join("\n\n"));
+                    chunk.clear();
+                    chunk_tokens = 0;
+                }
+                chunk.push(piece);
+                chunk_tokens += tokens;
             }
-        };
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk.join("\n\n"));
+        }
 
         Some(chunks)
     }
 
-    pub fn extract_text<T: AsRef<std::path::Path>>(file: &T, use_ocr: bool) -> Result<String, Error> {
-        if !file.as_ref().exists() {
-            return Err(embed_anything::text_loader::FileLoadingError::FileNotFound(file.as_ref().to_str().unwrap().to_string()).into());
+    /// 拆开超过 chunk_size 的 markdown 块
+    fn split_markdown_block(&self, block: MarkdownBlock) -> Vec<String> {
+        match block {
+            MarkdownBlock::Text(text) => self.splitter.chunks(&text).map(|chunk| chunk.to_string()).collect(),
+            MarkdownBlock::Code { fence, marker, body } => self
+                .split_into_lines(&body)
+                .into_iter()
+                .map(|(lines, _, _)| format!("{}\n{}\n{}", fence, lines, marker))
+                .collect(),
+            MarkdownBlock::Table { header, rows } => self
+                .split_into_rows(&rows.join("\n"))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|rows| format!("{}\n{}", header, rows))
+                .collect(),
         }
-        let file_extension = file.as_ref().extension().unwrap();
-        match file_extension.to_str().unwrap() {
-            "pdf" => PdfProcessor::extract_text(file, use_ocr),
-            "md" => MarkdownProcessor::extract_text(file),
-            "txt" => TxtProcessor::extract_text(file),
-            "docx" => DocxProcessor::extract_text(file),
-            _ => Err(embed_anything::text_loader::FileLoadingError::UnsupportedFileType(
-                file.as_ref().extension().unwrap().to_str().unwrap().to_string(),
-            )
-            .into()),
+    }
+
+    /// 按行切分源代码，保留缩进和空行，单行超过 chunk_size 时独占一个块。
+    /// 返回每个块及其在文本中的起止行号（从 0 开始，包含结束行）。
+    pub fn split_into_lines(&self, text: &str) -> Vec<(String, usize, usize)> {
+        let lines = text.lines().collect::<Vec<_>>();
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        let mut chunk_tokens = 0;
+        for (idx, line) in lines.iter().enumerate() {
+            let tokens = self.count_tokens(line);
+            if idx > start && chunk_tokens + tokens > self.chunk_size {
+                ranges.push((start, idx - 1));
+                start = idx;
+                chunk_tokens = 0;
+            }
+            chunk_tokens += tokens;
+        }
+        if start < lines.len() {
+            ranges.push((start, lines.len() - 1));
         }
+
+        ranges
+            .into_iter()
+            .filter_map(|(start, end)| {
+                // 去掉块首尾的空行
+                let first = (start..=end).find(|i| !lines[*i].trim().is_empty())?;
+                let last = (first..=end).rev().find(|i| !lines[*i].trim().is_empty())?;
+                Some((lines[first..=last].join("\n"), first, last))
+            })
+            .collect()
+    }
+
+    /// 文本的 token 数，分词失败时按字节数估算
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer.encode(text, false).map(|e| e.len()).unwrap_or(text.len())
+    }
+
+    /// 使用注册的提取器提取文本，见 `crate::extract::register_extractor`
+    pub async fn extract_text<T: AsRef<std::path::Path>>(file: &T) -> Result<String, Error> {
+        Ok(extract_text(file).await?)
     }
 
     pub fn get_metadata<T: AsRef<std::path::Path>>(file: T) -> Result<HashMap<String, String>, Error> {
@@ -104,3 +199,74 @@ impl TextLoader {
         Ok(metadata_map)
     }
 }
+
+/// Remove single newlines but keep double newlines.
+/// 替换前后长度相同，文本块在结果中的字节位置与原文一致
+pub fn join_lines(text: &str) -> String {
+    text.replace("\n\n", "{{DOUBLE_NEWLINE}}")
+        .replace("\n", " ")
+        .replace("{{DOUBLE_NEWLINE}}", "\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlap() {
+        let text = (1..=20).map(|i| format!("第{}号烧结炉的温度曲线正常。", i)).collect::<Vec<_>>().concat();
+        let total = |loader: TextLoader| loader.splitter.chunks(&text).map(|c| c.chars().count()).sum::<usize>();
+        // 没有空白可去掉，不重叠时各块的长度之和等于原文长度，重叠时更长。重叠按整句计算，需要放得下一句
+        assert_eq!(total(TextLoader::new(64, 0.0)), text.chars().count());
+        assert!(total(TextLoader::new(64, 0.3)) > text.chars().count());
+        assert!(total(TextLoader::with_tokenizer(64, 0.3, &BERT_BASE_CHINESE_TOKENIZER)) > text.chars().count());
+    }
+
+    #[test]
+    fn test_split_into_rows() {
+        let loader = TextLoader::new(24, 0.0);
+        let rows = (1..=6)
+            .map(|i| format!("Sheet: Params | Row: {} | Name = Furnace {} | Max Temp = {}", i, i, i * 100))
+            .collect::<Vec<_>>();
+
+        let chunks = loader.split_into_rows(&rows.join("\n")).unwrap();
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.iter().flat_map(|c| c.lines()).collect::<Vec<_>>(), rows);
+    }
+
+    #[test]
+    fn test_split_into_lines() {
+        let loader = TextLoader::new(16, 0.0);
+        let code = "fn main() {\n    let total = compute_total(1, 2, 3);\n\n    println!(\"{}\", total);\n    save(total);\n}\n";
+
+        let chunks = loader.split_into_lines(code);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].1, 0);
+        for (chunk, start, end) in &chunks {
+            let lines = code.lines().collect::<Vec<_>>();
+            assert_eq!(chunk, &lines[*start..=*end].join("\n"));
+        }
+        assert_eq!(chunks.last().unwrap().2, 5);
+    }
+
+    #[test]
+    fn test_split_markdown() {
+        let loader = TextLoader::new(40, 0.0);
+        let rows = (1..=8).map(|i| format!("| E0{} | 第 {} 号传感器断开 |", i, i)).collect::<Vec<_>>();
+        let text = format!(
+            "## 安装\n运行安装程序，\n然后重启。\n\n```bash\nsetup.exe --silent\n\n./check.sh\n```\n\n| 错误码 | 说明 |\n|---|---|\n{}\n",
+            rows.join("\n")
+        );
+
+        let chunks = loader.split_markdown(&text).unwrap();
+        // 标题行去掉，段落保留换行，短代码块与段落组成一块
+        assert_eq!(chunks[0], "运行安装程序，\n然后重启。\n\n```bash\nsetup.exe --silent\n\n./check.sh\n```");
+        // 过长的表格按行切分，每块都带表头
+        assert!(chunks.len() > 2);
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("| 错误码 | 说明 |\n|---|---|\n| E0"));
+        }
+        let split_rows = chunks[1..].iter().flat_map(|c| c.lines().skip(2)).collect::<Vec<_>>();
+        assert_eq!(split_rows, rows);
+    }
+}
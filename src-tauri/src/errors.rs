@@ -20,6 +20,18 @@ pub enum AidenErrors {
     #[error("{0}")]
     String(String),
 
+    #[error("不支持的文件类型: {0}")]
+    UnsupportedFileType(String),
+
+    #[error("文件已加密，需要密码: {0}")]
+    PasswordRequired(String),
+
+    #[error("超出提取限制，已跳过 {0}: {1}")]
+    LimitExceeded(String, String),
+
+    #[error("缺少 OCR 模型，已跳过: {0}")]
+    OcrUnavailable(String),
+
     #[error("{0}")]
     TaskJoinError(#[from] JoinError),
 
@@ -27,7 +39,25 @@ pub enum AidenErrors {
     LoPdfError(#[from] lopdf::Error),
 
     #[error("{0}")]
-    DocxError(#[from] docx_rs::ReaderError),
+    ZipError(#[from] zip::result::ZipError),
+
+    #[error("{0}")]
+    XmlError(#[from] roxmltree::Error),
+
+    #[error("{0}")]
+    YamlError(#[from] serde_yaml::Error),
+
+    #[error("{0}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("{0}")]
+    SpreadsheetError(#[from] calamine::Error),
+
+    #[error("{0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("{0}")]
+    MailError(#[from] mailparse::MailParseError),
 
     #[error("{0}")]
     PromptError(#[from] rig::completion::PromptError),
@@ -46,6 +76,9 @@ pub enum AidenErrors {
 
     #[error("{0}")]
     LancedbError(#[from] lancedb::Error),
+
+    #[error("{0}")]
+    KeyringError(#[from] keyring::Error),
 }
 
 impl From<AidenErrors> for InvokeError {
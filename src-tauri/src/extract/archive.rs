@@ -0,0 +1,264 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::detect::{detect, is_tar, read_header, FileType};
+use crate::extract::registry::has_extension_extractor;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use tempfile::{tempdir, TempDir};
+use zip::ZipArchive;
+
+/// 解压后的总大小上限，防止压缩炸弹
+const MAX_TOTAL_SIZE: u64 = 512 * 1024 * 1024;
+/// 压缩包中的文件数量上限
+const MAX_ENTRIES: usize = 10_000;
+/// 压缩包嵌套层数上限，最外层为 1
+const MAX_DEPTH: usize = 3;
+
+/// 压缩包中的一个文件，已解压到临时目录
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    /// 虚拟路径，如 `/data/bundle.zip!/docs/intro.md`，嵌套的压缩包依次以 `!/` 连接
+    pub virtual_path: String,
+    /// 压缩包内的路径，如 `docs/intro.md`、`inner.zip!/a.md`
+    pub name: String,
+    /// 解压后的临时文件
+    pub path: PathBuf,
+}
+
+/// 解压结果，临时目录在该值释放时删除
+pub struct ExpandedArchive {
+    _dir: TempDir,
+    pub members: Vec<ArchiveMember>,
+}
+
+pub struct ArchiveProcessor;
+
+impl ArchiveProcessor {
+    /// Expands a zip or tar(.gz) archive into a temporary directory.
+    ///
+    /// Only members that can be extracted are written out; nested archives are expanded in place
+    /// up to `MAX_DEPTH` levels. Expansion fails once the total uncompressed size exceeds
+    /// `MAX_TOTAL_SIZE` or the archives contain more than `MAX_ENTRIES` files.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the archive.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the expanded members if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub fn expand<T: AsRef<Path>>(path: T) -> AppResult<ExpandedArchive> {
+        let dir = tempdir()?;
+        let mut budget = Budget {
+            remaining: MAX_TOTAL_SIZE,
+            entries: 0,
+        };
+        let mut members = Vec::new();
+        let prefix = format!("{}!/", path.as_ref().display());
+        expand_into(path.as_ref(), dir.path(), "", &prefix, 1, &mut budget, &mut members)?;
+        Ok(ExpandedArchive { _dir: dir, members })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        // 没有可识别的扩展名时按文件头判断
+        let header = read_header(path).ok()?;
+        if header.starts_with(b"PK\x03\x04") {
+            Some(ArchiveKind::Zip)
+        } else if header.starts_with(b"\x1F\x8B") {
+            Some(ArchiveKind::TarGz)
+        } else if is_tar(&header) {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+struct Budget {
+    remaining: u64,
+    entries: usize,
+}
+
+impl Budget {
+    /// 写出一个文件，实际写入的字节数超过剩余额度时报错（不信任压缩包中声明的大小）
+    fn write<R: Read>(&mut self, reader: R, target: &Path) -> AppResult<()> {
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            return Err(AidenErrors::Str("压缩包中的文件数量超过限制"));
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(target)?;
+        let written = std::io::copy(&mut reader.take(self.remaining + 1), &mut file)?;
+        if written > self.remaining {
+            return Err(AidenErrors::Str("压缩包解压后的大小超过限制"));
+        }
+        self.remaining -= written;
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn expand_into(
+    archive: &Path,
+    dir: &Path,
+    name_prefix: &str,
+    virtual_prefix: &str,
+    depth: usize,
+    budget: &mut Budget,
+    members: &mut Vec<ArchiveMember>,
+) -> AppResult<()> {
+    let kind = archive_kind(archive).ok_or(AidenErrors::Str("不支持的压缩包格式"))?;
+    let mut written = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+            for i in 0..zip.len() {
+                let entry = zip.by_index(i)?;
+                // enclosed_name 会拒绝绝对路径和 `..`，防止写到临时目录之外
+                let Some(name) = entry.enclosed_name().filter(|_| entry.is_file()) else {
+                    continue;
+                };
+                if !should_extract(&name) {
+                    continue;
+                }
+                budget.write(entry, &dir.join(&name))?;
+                written.push(name);
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let file = BufReader::new(File::open(archive)?);
+            let reader: Box<dyn Read> = match kind {
+                ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+                _ => Box::new(file),
+            };
+            let mut tar = tar::Archive::new(reader);
+            for entry in tar.entries()? {
+                let entry = entry?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry.path()?.into_owned();
+                if !name.components().all(|c| matches!(c, Component::Normal(_))) || !should_extract(&name) {
+                    continue;
+                }
+                budget.write(entry, &dir.join(&name))?;
+                written.push(name);
+            }
+        }
+    }
+
+    for name in written {
+        let path = dir.join(&name);
+        let name = name.to_string_lossy().replace('\\', "/");
+        if detect(&path).ok() != Some(FileType::Archive) {
+            members.push(ArchiveMember {
+                virtual_path: format!("{}{}", virtual_prefix, name),
+                name: format!("{}{}", name_prefix, name),
+                path,
+            });
+            continue;
+        }
+        if depth >= MAX_DEPTH {
+            log::warn!("Skip nested archive {}{}: too deep.", virtual_prefix, name);
+            continue;
+        }
+        // 嵌套的压缩包解压到以其文件名命名的子目录中
+        let nested_dir = dir.join(format!("{}!", name));
+        expand_into(
+            &path,
+            &nested_dir,
+            &format!("{}{}!/", name_prefix, name),
+            &format!("{}{}!/", virtual_prefix, name),
+            depth + 1,
+            budget,
+            members,
+        )?;
+    }
+    Ok(())
+}
+
+/// 只解压能够提取文本的文件（包括注册的自定义格式）和嵌套的压缩包
+fn should_extract(name: &Path) -> bool {
+    FileType::from_extension(&name).is_some() || has_extension_extractor(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_expand_nested() {
+        let dir = tempdir().unwrap();
+        let inner = dir.path().join("inner.tar.gz");
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&inner).unwrap(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, "notes/a.txt", &b"hello"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let bundle = dir.path().join("bundle.zip");
+        let inner_bytes = std::fs::read(&inner).unwrap();
+        write_zip(
+            &bundle,
+            &[
+                ("docs/intro.md", b"# Intro"),
+                ("tool.exe", b"MZ\x90\x00"),
+                ("../evil.txt", b"evil"),
+                ("inner.tar.gz", &inner_bytes),
+            ],
+        );
+
+        let archive = ArchiveProcessor::expand(&bundle).unwrap();
+        let names = archive.members.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["docs/intro.md", "inner.tar.gz!/notes/a.txt"]);
+        assert_eq!(archive.members[0].virtual_path, format!("{}!/docs/intro.md", bundle.display()));
+        assert_eq!(std::fs::read_to_string(&archive.members[1].path).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_budget_limit() {
+        let dir = tempdir().unwrap();
+        let mut budget = Budget { remaining: 8, entries: 0 };
+        budget.write(&b"12345"[..], &dir.path().join("a.txt")).unwrap();
+        assert_eq!(budget.remaining, 3);
+        // 超出剩余额度时即使压缩包声明的大小很小也会失败
+        assert!(budget.write(&b"12345"[..], &dir.path().join("b.txt")).is_err());
+    }
+}
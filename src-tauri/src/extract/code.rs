@@ -0,0 +1,874 @@
+use crate::errors::AppResult;
+use crate::extract::Section;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 不超过该行数（不含空行）的片段并入下一个片段，避免产生只有一行声明的文本块
+const MIN_SECTION_LINES: usize = 1;
+
+/// 定义前可能出现的修饰符
+static MODIFIERS: &[&str] = &[
+    "pub",
+    "export",
+    "default",
+    "async",
+    "unsafe",
+    "extern",
+    "const",
+    "static",
+    "public",
+    "private",
+    "protected",
+    "internal",
+    "abstract",
+    "final",
+    "sealed",
+    "open",
+    "override",
+    "inline",
+    "virtual",
+    "data",
+    "partial",
+    "readonly",
+    "declare",
+    "suspend",
+    "fileprivate",
+    "mutating",
+    "case",
+];
+
+/// 以这些关键字开头的行不是函数定义
+static CONTROL_KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "catch",
+    "try",
+    "return",
+    "new",
+    "throw",
+    "using",
+    "lock",
+    "synchronized",
+    "sizeof",
+    "typeof",
+    "await",
+    "yield",
+    "delete",
+    "goto",
+];
+
+pub(crate) struct Language {
+    pub name: &'static str,
+    /// 按缩进划分代码块（Python）
+    indent: bool,
+    /// 限定名分隔符，如 `TextLoader::new`、`Foo.bar`
+    separator: &'static str,
+    /// 内部成员需要单独切分的定义，如 impl、class
+    containers: &'static [&'static str],
+    functions: &'static [&'static str],
+    others: &'static [&'static str],
+    /// 是否识别 `Type name(args) {` 形式的 C 风格函数
+    c_style: bool,
+    /// `'` 是否表示字符串（Rust 中 `'` 也用于生命周期）
+    single_quote_strings: bool,
+}
+
+static RUST: Language = Language {
+    name: "rust",
+    indent: false,
+    separator: "::",
+    containers: &["impl", "trait", "mod"],
+    functions: &["fn"],
+    others: &["struct", "enum", "union", "type", "macro_rules!"],
+    c_style: false,
+    single_quote_strings: false,
+};
+
+static PYTHON: Language = Language {
+    name: "python",
+    indent: true,
+    separator: ".",
+    containers: &["class"],
+    functions: &["def"],
+    others: &[],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+static JAVASCRIPT: Language = Language {
+    name: "javascript",
+    indent: false,
+    separator: ".",
+    containers: &["class"],
+    functions: &["function", "function*"],
+    others: &[],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static TYPESCRIPT: Language = Language {
+    name: "typescript",
+    indent: false,
+    separator: ".",
+    containers: &["class", "namespace"],
+    functions: &["function", "function*"],
+    others: &["interface", "enum", "type"],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static JAVA: Language = Language {
+    name: "java",
+    indent: false,
+    separator: ".",
+    containers: &["class", "interface", "enum", "record", "@interface"],
+    functions: &[],
+    others: &[],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static CSHARP: Language = Language {
+    name: "csharp",
+    indent: false,
+    separator: ".",
+    containers: &["namespace", "class", "interface", "struct", "record"],
+    functions: &[],
+    others: &["enum"],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static C: Language = Language {
+    name: "c",
+    indent: false,
+    separator: "::",
+    containers: &[],
+    functions: &[],
+    others: &["struct", "enum", "union"],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static CPP: Language = Language {
+    name: "cpp",
+    indent: false,
+    separator: "::",
+    containers: &["namespace", "class", "struct"],
+    functions: &[],
+    others: &["enum", "union"],
+    c_style: true,
+    single_quote_strings: true,
+};
+
+static GO: Language = Language {
+    name: "go",
+    indent: false,
+    separator: ".",
+    containers: &[],
+    functions: &["func"],
+    others: &["type"],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+static KOTLIN: Language = Language {
+    name: "kotlin",
+    indent: false,
+    separator: ".",
+    containers: &["class", "interface", "object"],
+    functions: &["fun"],
+    others: &[],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+static SWIFT: Language = Language {
+    name: "swift",
+    indent: false,
+    separator: ".",
+    containers: &["class", "struct", "extension", "protocol", "enum", "actor"],
+    functions: &["func", "init"],
+    others: &[],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+static SCALA: Language = Language {
+    name: "scala",
+    indent: false,
+    separator: ".",
+    containers: &["class", "object", "trait"],
+    functions: &["def"],
+    others: &[],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+static PHP: Language = Language {
+    name: "php",
+    indent: false,
+    separator: "::",
+    containers: &["class", "interface", "trait"],
+    functions: &["function"],
+    others: &[],
+    c_style: false,
+    single_quote_strings: true,
+};
+
+/// 根据扩展名识别源代码语言
+pub(crate) fn language(extension: &str) -> Option<&'static Language> {
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "rs" => &RUST,
+        "py" | "pyi" => &PYTHON,
+        "js" | "jsx" | "mjs" | "cjs" => &JAVASCRIPT,
+        "ts" | "tsx" | "mts" | "cts" => &TYPESCRIPT,
+        "java" => &JAVA,
+        "cs" => &CSHARP,
+        "c" | "h" => &C,
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => &CPP,
+        "go" => &GO,
+        "kt" | "kts" => &KOTLIN,
+        "swift" => &SWIFT,
+        "scala" => &SCALA,
+        "php" => &PHP,
+        _ => return None,
+    };
+    Some(language)
+}
+
+pub struct CodeProcessor;
+
+impl CodeProcessor {
+    /// Extracts text from a source code file, unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the source file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<Path>>(path: T) -> AppResult<String> {
+        let bytes = std::fs::read(path)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Splits a source code file on function/class/impl boundaries.
+    ///
+    /// Every top-level definition becomes a `Section`; members of containers (impl, trait, class, ...)
+    /// get their own sections named with the qualified symbol, e.g. `TextLoader::new`. Doc comments,
+    /// attributes and decorators stay with the definition they annotate. Each section records
+    /// `language`, `symbol`, `start_line` and `end_line` (1-based, inclusive) as metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the source file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        let language = path.as_ref().extension().and_then(|ext| ext.to_str()).and_then(language).unwrap_or(&C);
+        let text = Self::extract_text(path).await?;
+        Ok(split_code(&text, language))
+    }
+}
+
+/// 一个定义的起始行及其限定名
+struct Boundary {
+    line: usize,
+    symbol: String,
+}
+
+pub(crate) fn split_code(text: &str, language: &Language) -> Vec<Section> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let boundaries = if language.indent {
+        indent_boundaries(&lines, language)
+    } else {
+        brace_boundaries(&lines, language)
+    };
+
+    // (起始行, 符号)，注释、属性和装饰器归属于其后的定义
+    let mut starts: Vec<(usize, Option<String>)> = vec![(0, None)];
+    for boundary in boundaries {
+        let mut start = boundary.line;
+        let floor = starts.last().map(|(s, _)| *s + 1).unwrap_or(0);
+        while start > floor && is_annotation(lines[start - 1], language) {
+            start -= 1;
+        }
+        if starts.last().is_some_and(|(s, _)| *s == start) {
+            starts.pop();
+        }
+        starts.push((start, Some(boundary.symbol)));
+    }
+
+    let mut ranges = Vec::new();
+    for (idx, (start, symbol)) in starts.iter().enumerate() {
+        let end = starts.get(idx + 1).map(|(s, _)| *s).unwrap_or(lines.len());
+        if lines[*start..end].iter().any(|l| !l.trim().is_empty()) {
+            ranges.push((*start, end, symbol.clone()));
+        }
+    }
+
+    // 过短的片段（如 `impl Foo {`、单行常量）并入下一个片段
+    let mut merged: Vec<(usize, usize, Option<String>)> = Vec::new();
+    let mut carry: Option<(usize, Option<String>)> = None;
+    let count = ranges.len();
+    for (idx, (start, end, symbol)) in ranges.into_iter().enumerate() {
+        let (start, symbol) = match carry.take() {
+            Some((carry_start, carry_symbol)) => (carry_start, symbol.or(carry_symbol)),
+            None => (start, symbol),
+        };
+        let content_lines = lines[start..end].iter().filter(|l| !l.trim().is_empty()).count();
+        if content_lines <= MIN_SECTION_LINES && idx + 1 < count {
+            carry = Some((start, symbol));
+            continue;
+        }
+        merged.push((start, end, symbol));
+    }
+
+    merged
+        .into_iter()
+        .map(|(start, end, symbol)| {
+            // 去掉首尾空行，行号与之对应
+            let first = (start..end).find(|i| !lines[*i].trim().is_empty()).unwrap_or(start);
+            let last = (start..end).rev().find(|i| !lines[*i].trim().is_empty()).unwrap_or(first);
+            let mut metadata = HashMap::from([
+                ("language".to_string(), language.name.to_string()),
+                ("start_line".to_string(), (first + 1).to_string()),
+                ("end_line".to_string(), (last + 1).to_string()),
+            ]);
+            if let Some(symbol) = &symbol {
+                metadata.insert("symbol".to_string(), symbol.clone());
+            }
+            Section {
+                title: symbol,
+                text: lines[first..=last].join("\n"),
+                metadata,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// 文档注释、属性、注解和装饰器
+fn is_annotation(line: &str, language: &Language) -> bool {
+    let line = line.trim_start();
+    if language.indent {
+        return line.starts_with('@') || line.starts_with('#');
+    }
+    line.starts_with("//")
+        || line.starts_with("/*")
+        || line.starts_with('*')
+        || line.starts_with("#[")
+        || line.starts_with("#![")
+        || (line.starts_with('@') && !line.starts_with("@interface"))
+        || (line.starts_with('[') && line.ends_with(']'))
+}
+
+/// 花括号语言：只有所在的代码块全部是容器（impl、class 等）时，定义才作为切分点
+fn brace_boundaries(lines: &[&str], language: &Language) -> Vec<Boundary> {
+    let mut boundaries = Vec::new();
+    // 已打开的代码块，容器记录其名称
+    let mut blocks: Vec<Option<String>> = Vec::new();
+    // 已识别定义但其 `{` 还未出现
+    let mut pending: Option<Option<String>> = None;
+    let mut scanner = Scanner::default();
+
+    for (idx, line) in lines.iter().enumerate() {
+        let eligible = !scanner.in_comment && scanner.string.is_none() && blocks.iter().all(Option::is_some);
+        if eligible {
+            let next = lines.get(idx + 1).map(|l| l.trim()).unwrap_or_default();
+            if let Some(definition) = parse_definition(line.trim(), next, !blocks.is_empty(), language) {
+                let parents = blocks.iter().flatten().cloned().collect::<Vec<_>>();
+                let symbol = if parents.is_empty() {
+                    definition.name.clone()
+                } else {
+                    format!("{}{}{}", parents.join(language.separator), language.separator, definition.name)
+                };
+                pending = Some(definition.container.then_some(definition.name));
+                boundaries.push(Boundary { line: idx, symbol });
+            }
+        }
+
+        for token in scanner.scan(line, language) {
+            match token {
+                Token::Open => blocks.push(pending.take().flatten()),
+                Token::Close => {
+                    blocks.pop();
+                }
+                // 没有代码块的定义，如 `struct Foo;`
+                Token::Semicolon => pending = None,
+            }
+        }
+    }
+    boundaries
+}
+
+/// 缩进语言：类中的方法和顶层定义作为切分点，函数内部的嵌套定义不切分
+fn indent_boundaries(lines: &[&str], language: &Language) -> Vec<Boundary> {
+    let mut boundaries = Vec::new();
+    // (缩进, 容器名称)
+    let mut blocks: Vec<(usize, Option<String>)> = Vec::new();
+    let mut triple_quote: Option<&str> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(quote) = triple_quote {
+            if trimmed.contains(quote) {
+                triple_quote = None;
+            }
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        while blocks.last().is_some_and(|(i, _)| *i >= indent) {
+            blocks.pop();
+        }
+
+        if blocks.iter().all(|(_, c)| c.is_some()) {
+            if let Some(definition) = parse_definition(trimmed, "", !blocks.is_empty(), language) {
+                let parents = blocks.iter().filter_map(|(_, c)| c.clone()).collect::<Vec<_>>();
+                let symbol = if parents.is_empty() {
+                    definition.name.clone()
+                } else {
+                    format!("{}{}{}", parents.join(language.separator), language.separator, definition.name)
+                };
+                boundaries.push(Boundary { line: idx, symbol });
+                blocks.push((indent, definition.container.then_some(definition.name)));
+                continue;
+            }
+        }
+        if trimmed.ends_with(':') {
+            blocks.push((indent, None));
+        }
+
+        for quote in ["\"\"\"", "'''"] {
+            if trimmed.matches(quote).count() % 2 == 1 {
+                triple_quote = Some(quote);
+                break;
+            }
+        }
+    }
+    boundaries
+}
+
+struct Definition {
+    name: String,
+    container: bool,
+}
+
+/// 识别一行是否为定义，`next` 为下一行，用于识别 `{` 单独成行的写法
+fn parse_definition(line: &str, next: &str, nested: bool, language: &Language) -> Option<Definition> {
+    if line.is_empty() || is_annotation(line, language) {
+        return None;
+    }
+
+    let mut words = line.split_whitespace().peekable();
+    // JS/TS 中赋值为函数的常量：`const foo = async (a) => {`
+    if !language.indent && language.c_style && language.single_quote_strings {
+        if let Some(name) = arrow_function(line) {
+            return Some(Definition { name, container: false });
+        }
+    }
+    while let Some(word) = words.peek() {
+        let base = word.split('(').next().unwrap_or_default();
+        if MODIFIERS.contains(&base) || word.starts_with('"') {
+            words.next();
+        } else {
+            break;
+        }
+    }
+    let keyword = words.next()?;
+    let rest = words.collect::<Vec<_>>().join(" ");
+
+    if language.containers.contains(&keyword) {
+        let name = if keyword == "impl" { impl_target(&rest) } else { identifier(&rest) };
+        return name.map(|name| Definition { name, container: true });
+    }
+    // impl、trait 中的关联类型不单独切分
+    if keyword == "type" && nested {
+        return None;
+    }
+    if language.functions.contains(&keyword) || language.others.contains(&keyword) {
+        let name = if language.name == "go" && keyword == "func" {
+            go_function(&rest)
+        } else {
+            identifier(&rest)
+        };
+        // Swift 的 `init(` 没有名称
+        let name = name.or_else(|| (keyword == "init").then(|| "init".to_string()));
+        return name.map(|name| Definition { name, container: false });
+    }
+    if keyword.starts_with("init(") && language.functions.contains(&"init") {
+        return Some(Definition {
+            name: "init".to_string(),
+            container: false,
+        });
+    }
+
+    if language.c_style {
+        return c_style_function(line, next).map(|name| Definition { name, container: false });
+    }
+    None
+}
+
+/// 开头的标识符，跳过泛型参数
+fn identifier(text: &str) -> Option<String> {
+    let text = text.trim_start();
+    let text = if text.starts_with('<') { skip_generics(text) } else { text };
+    let name = text
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect::<String>();
+    (!name.is_empty()).then_some(name)
+}
+
+fn skip_generics(text: &str) -> &str {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[idx + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// `impl<T> Trait for Foo<T>` 取实现的类型 `Foo`
+fn impl_target(rest: &str) -> Option<String> {
+    let rest = if rest.starts_with('<') { skip_generics(rest) } else { rest };
+    let rest = rest.split(['{']).next().unwrap_or_default();
+    let rest = rest.split(" where").next().unwrap_or_default();
+    let target = rest.rsplit(" for ").next().unwrap_or(rest).trim().trim_start_matches(['&', '*']);
+    let target = target.trim_start_matches("dyn ").trim_start_matches("mut ");
+    let name = target
+        .split(['<', ' '])
+        .next()
+        .unwrap_or_default()
+        .rsplit("::")
+        .next()
+        .unwrap_or_default();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Go 方法 `func (s *Server) Start(` 取 `Server.Start`
+fn go_function(rest: &str) -> Option<String> {
+    let rest = rest.trim_start();
+    if let Some(receiver) = rest.strip_prefix('(') {
+        let (receiver, rest) = receiver.split_once(')')?;
+        let receiver_type = receiver.split_whitespace().last()?.trim_start_matches('*');
+        let receiver_type = receiver_type.split('[').next().unwrap_or(receiver_type);
+        return identifier(rest).map(|name| format!("{}.{}", receiver_type, name));
+    }
+    identifier(rest)
+}
+
+fn arrow_function(line: &str) -> Option<String> {
+    let line = line.trim_start_matches("export ").trim_start_matches("default ");
+    let rest = ["const ", "let ", "var "].iter().find_map(|kw| line.strip_prefix(kw))?;
+    let name = identifier(rest)?;
+    let (_, value) = rest.split_once('=')?;
+    let value = value.trim_start();
+    let is_function = value.starts_with("function") || (value.contains("=>") && (value.starts_with('(') || value.starts_with("async")));
+    is_function.then_some(name)
+}
+
+/// C 风格的函数或方法定义：`int main(int argc) {`、`public void run() throws Exception`
+fn c_style_function(line: &str, next: &str) -> Option<String> {
+    let first = line.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default();
+    if CONTROL_KEYWORDS.contains(&first) || line.contains("=>") || line.ends_with(';') || line.ends_with(',') {
+        return None;
+    }
+    // 定义必须打开代码块，或者参数列表、throws 子句延续到下一行
+    let opens_block = line.ends_with('{') || (line.contains(')') && next.starts_with('{'));
+    let continues = line.ends_with('(') || (line.ends_with(')') && next.starts_with("throws"));
+    if !opens_block && !continues {
+        return None;
+    }
+
+    let paren = line.find('(')?;
+    let before = &line[..paren];
+    if before.contains('=') || before.trim_end().ends_with('.') {
+        return None;
+    }
+    let name = before
+        .trim_end()
+        .rsplit(|c: char| !c.is_alphanumeric() && c != '_' && c != '~' && c != ':')
+        .next()
+        .unwrap_or_default();
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let name = name.trim_start_matches('~');
+    if name.is_empty() || name.chars().next().is_some_and(|c| c.is_ascii_digit()) || CONTROL_KEYWORDS.contains(&name) {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+enum Token {
+    Open,
+    Close,
+    Semicolon,
+}
+
+/// 跳过字符串和注释，逐行找出花括号和分号
+#[derive(Default)]
+struct Scanner {
+    in_comment: bool,
+    /// 跨行字符串的结束标记，如 `"`、`"#`、`` ` ``
+    string: Option<String>,
+}
+
+impl Scanner {
+    fn scan(&mut self, line: &str, language: &Language) -> Vec<Token> {
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if self.in_comment {
+                if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    self.in_comment = false;
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            if let Some(end) = &self.string {
+                if chars[i] == '\\' && end.len() == 1 {
+                    i += 2;
+                    continue;
+                }
+                let end_chars = end.chars().collect::<Vec<_>>();
+                if chars[i..].starts_with(&end_chars) {
+                    i += end_chars.len();
+                    self.string = None;
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+
+            match chars[i] {
+                '/' if chars.get(i + 1) == Some(&'/') => break,
+                '/' if chars.get(i + 1) == Some(&'*') => {
+                    self.in_comment = true;
+                    i += 1;
+                }
+                '"' | '`' => self.string = Some(chars[i].to_string()),
+                '\'' if language.single_quote_strings => self.string = Some("'".to_string()),
+                '\'' => {
+                    // Rust 字符字面量，其余情况为生命周期
+                    if chars.get(i + 1) == Some(&'\\') {
+                        i += chars[i + 2..].iter().position(|c| *c == '\'').map(|p| p + 2).unwrap_or(1);
+                    } else if chars.get(i + 2) == Some(&'\'') {
+                        i += 2;
+                    }
+                }
+                'r' if !language.single_quote_strings && (i == 0 || !is_ident(chars[i - 1]) || starts_byte_string(&chars, i)) => {
+                    // Rust 原始字符串 r#"..."#
+                    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        self.string = Some(format!("\"{}", "#".repeat(hashes)));
+                        i += 1 + hashes;
+                    }
+                }
+                '{' => tokens.push(Token::Open),
+                '}' => tokens.push(Token::Close),
+                ';' => tokens.push(Token::Semicolon),
+                _ => {}
+            }
+            i += 1;
+        }
+        tokens
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `br#"..."#` 中 `r` 前面的 `b`
+fn starts_byte_string(chars: &[char], i: usize) -> bool {
+    i > 0 && chars[i - 1] == 'b' && (i == 1 || !is_ident(chars[i - 2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r##"use std::sync::Arc;
+
+/// 文本加载器
+#[derive(Debug)]
+pub struct TextLoader {
+    pub chunk_size: usize,
+}
+
+impl TextLoader {
+    pub fn new(chunk_size: usize) -> Self {
+        let raw = r#"{ not a block"#;
+        let c = '{';
+        Self { chunk_size }
+    }
+
+    /// 按行切分
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        // {
+        text.lines().collect()
+    }
+}
+
+fn main() {
+    let loader = TextLoader::new(1);
+    println!("{:?}", loader.split("a"));
+}
+"##;
+
+    const PYTHON_SOURCE: &str = r#"import os
+
+
+class Loader:
+    """Loads files.
+
+    def not_a_function():
+    """
+
+    def __init__(self, path):
+        self.path = path
+
+    @property
+    def name(self):
+        def inner():
+            return 1
+        return os.path.basename(self.path)
+
+
+async def main():
+    loader = Loader("a")
+    print(loader.name)
+"#;
+
+    const GO_SOURCE: &str = r#"package main
+
+import "fmt"
+
+type Server struct {
+	addr string
+}
+
+func (s *Server) Start() error {
+	fmt.Println("start {")
+	return nil
+}
+
+func main() {
+	s := &Server{addr: ":80"}
+	s.Start()
+}
+"#;
+
+    const JAVA_SOURCE: &str = r#"package demo;
+
+public class Greeter {
+    private final String name;
+
+    public Greeter(String name) {
+        this.name = name;
+    }
+
+    @Override
+    public String toString()
+    {
+        if (name == null) {
+            return "";
+        }
+        return "Hello " + name;
+    }
+}
+"#;
+
+    fn symbols(sections: &[Section]) -> Vec<(Option<&str>, &str, &str)> {
+        sections
+            .iter()
+            .map(|s| (s.title.as_deref(), s.metadata["start_line"].as_str(), s.metadata["end_line"].as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_rust() {
+        let sections = split_code(RUST_SOURCE, &RUST);
+        assert_eq!(
+            symbols(&sections),
+            vec![
+                (Some("TextLoader"), "1", "7"),
+                (Some("TextLoader::new"), "9", "14"),
+                (Some("TextLoader::split"), "16", "21"),
+                (Some("main"), "23", "26"),
+            ]
+        );
+        assert!(sections[0].text.starts_with("use std::sync::Arc;\n\n/// 文本加载器\n#[derive(Debug)]"));
+        assert!(sections[1].text.starts_with("impl TextLoader {\n    pub fn new"));
+        assert!(sections[2].text.starts_with("    /// 按行切分"));
+        assert_eq!(sections[3].metadata["language"], "rust");
+    }
+
+    #[test]
+    fn test_split_python() {
+        let sections = split_code(PYTHON_SOURCE, &PYTHON);
+        assert_eq!(
+            symbols(&sections),
+            vec![
+                (Some("Loader"), "1", "8"),
+                (Some("Loader.__init__"), "10", "11"),
+                (Some("Loader.name"), "13", "17"),
+                (Some("main"), "20", "22"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_go() {
+        let sections = split_code(GO_SOURCE, &GO);
+        assert_eq!(
+            symbols(&sections),
+            vec![
+                (None, "1", "3"),
+                (Some("Server"), "5", "7"),
+                (Some("Server.Start"), "9", "12"),
+                (Some("main"), "14", "17")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_java() {
+        let sections = split_code(JAVA_SOURCE, &JAVA);
+        assert_eq!(
+            symbols(&sections),
+            vec![
+                (Some("Greeter"), "1", "4"),
+                (Some("Greeter.Greeter"), "6", "8"),
+                (Some("Greeter.toString"), "10", "18")
+            ]
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::code;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const RTF_MAGIC: &[u8] = b"{\\rtf";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const OLE_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const GZIP_MAGIC: &[u8] = b"\x1F\x8B";
+const IMAGE_MAGICS: &[&[u8]] = &[b"\x89PNG\r\n\x1A\n", b"\xFF\xD8\xFF", b"II*\x00", b"MM\x00*"];
+/// 读取文件头的长度，PDF 头允许出现在前 1024 字节内
+const HEADER_LEN: usize = 8192;
+
+/// 文件类型，决定使用哪个提取器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    Pdf,
+    Markdown,
+    Text,
+    Docx,
+    Doc,
+    Rtf,
+    /// odt、odp
+    OpenDocument,
+    Pptx,
+    Html,
+    Epub,
+    /// eml、mbox
+    Email,
+    /// xlsx、xls、xlsb、ods
+    Spreadsheet,
+    Csv,
+    Code,
+    /// zip、tar、tar.gz
+    Archive,
+    /// png、jpg、tiff，使用 OCR 提取文本
+    Image,
+    /// Jupyter ipynb
+    Notebook,
+    /// tex、ltx
+    Latex,
+    /// srt、vtt
+    Subtitle,
+    /// json、yaml、toml、xml，展开为带路径的语句
+    Structured,
+}
+
+impl FileType {
+    /// 按扩展名识别，不区分大小写
+    pub fn from_extension<T: AsRef<Path>>(file: &T) -> Option<Self> {
+        let path = file.as_ref();
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        let file_type = match ext.as_str() {
+            "pdf" => FileType::Pdf,
+            "md" | "markdown" => FileType::Markdown,
+            "txt" => FileType::Text,
+            "docx" => FileType::Docx,
+            "doc" => FileType::Doc,
+            "rtf" => FileType::Rtf,
+            "odt" | "odp" => FileType::OpenDocument,
+            "pptx" => FileType::Pptx,
+            "html" | "htm" | "xhtml" => FileType::Html,
+            "epub" => FileType::Epub,
+            "eml" | "mbox" => FileType::Email,
+            "xlsx" | "xls" | "xlsm" | "xlsb" | "ods" => FileType::Spreadsheet,
+            "csv" => FileType::Csv,
+            "zip" | "tar" | "tgz" => FileType::Archive,
+            "png" | "jpg" | "jpeg" | "tif" | "tiff" => FileType::Image,
+            "ipynb" => FileType::Notebook,
+            "tex" | "ltx" => FileType::Latex,
+            "srt" | "vtt" => FileType::Subtitle,
+            "json" | "yaml" | "yml" | "toml" | "xml" => FileType::Structured,
+            "gz" if path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().to_ascii_lowercase().ends_with(".tar")) =>
+            {
+                FileType::Archive
+            }
+            ext if code::language(ext).is_some() => FileType::Code,
+            _ => return None,
+        };
+        Some(file_type)
+    }
+}
+
+/// 识别文件类型：优先根据文件头的魔数判断，无法判断时按扩展名（不区分大小写）识别。
+/// 没有扩展名的文本文件按纯文本处理，其余无法识别的文件返回 `AidenErrors::UnsupportedFileType`
+pub fn detect<T: AsRef<Path>>(file: &T) -> AppResult<FileType> {
+    let path = file.as_ref();
+    if !path.exists() {
+        return Err(AidenErrors::Str("文件找不到"));
+    }
+    let header = read_header(path)?;
+    let by_extension = FileType::from_extension(&path);
+    let no_extension = path.extension().is_none();
+
+    // 魔数前可能有垃圾字节，只在扩展名为 .pdf 或没有扩展名时在前 1 KB 中查找，
+    // 避免内容中提到 %PDF- 的文本或源代码被当作 PDF
+    let maybe_pdf = no_extension || by_extension == Some(FileType::Pdf);
+    if header.starts_with(PDF_MAGIC) || (maybe_pdf && header.windows(PDF_MAGIC.len()).take(1024).any(|w| w == PDF_MAGIC)) {
+        return Ok(FileType::Pdf);
+    }
+    if header.starts_with(RTF_MAGIC) {
+        return Ok(FileType::Rtf);
+    }
+    if header.starts_with(ZIP_MAGIC) {
+        if let Some(file_type) = sniff_zip(path) {
+            return Ok(file_type);
+        }
+        // 普通的 zip 包，jar、apk 这类扩展名不作为压缩包展开
+        if no_extension || by_extension == Some(FileType::Archive) {
+            return Ok(FileType::Archive);
+        }
+    }
+    if header.starts_with(OLE_MAGIC) {
+        if let Some(file_type) = sniff_ole(path) {
+            return Ok(file_type);
+        }
+    }
+    if IMAGE_MAGICS.iter().any(|magic| header.starts_with(magic)) {
+        return Ok(FileType::Image);
+    }
+    if no_extension && (header.starts_with(GZIP_MAGIC) || is_tar(&header)) {
+        return Ok(FileType::Archive);
+    }
+
+    if let Some(file_type) = by_extension {
+        return Ok(file_type);
+    }
+    if no_extension {
+        if let Some(file_type) = sniff_text(&header) {
+            return Ok(file_type);
+        }
+    }
+    Err(AidenErrors::UnsupportedFileType(path.display().to_string()))
+}
+
+pub(crate) fn read_header(path: &Path) -> AppResult<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?.take(HEADER_LEN as u64).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+pub(crate) fn is_tar(header: &[u8]) -> bool {
+    header.get(257..262) == Some(b"ustar")
+}
+
+/// 根据 mimetype 和 [Content_Types].xml 中的内容区分 epub、OpenDocument 和 OOXML
+fn sniff_zip(path: &Path) -> Option<FileType> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path).ok()?)).ok()?;
+
+    let mut mimetype = String::new();
+    if let Ok(mut entry) = archive.by_name("mimetype") {
+        let _ = entry.by_ref().take(128).read_to_string(&mut mimetype);
+    }
+    match mimetype.trim() {
+        "application/epub+zip" => return Some(FileType::Epub),
+        "application/vnd.oasis.opendocument.text" | "application/vnd.oasis.opendocument.presentation" => return Some(FileType::OpenDocument),
+        "application/vnd.oasis.opendocument.spreadsheet" => return Some(FileType::Spreadsheet),
+        _ => {}
+    }
+
+    archive.index_for_name("[Content_Types].xml")?;
+    if archive.index_for_name("word/document.xml").is_some() {
+        Some(FileType::Docx)
+    } else if archive.index_for_name("ppt/presentation.xml").is_some() {
+        Some(FileType::Pptx)
+    } else if archive.index_for_name("xl/workbook.xml").is_some() || archive.index_for_name("xl/workbook.bin").is_some() {
+        Some(FileType::Spreadsheet)
+    } else {
+        None
+    }
+}
+
+/// OLE 复合文档：Word 文档含 WordDocument 流，Excel 工作簿含 Workbook 流
+fn sniff_ole(path: &Path) -> Option<FileType> {
+    let file = cfb::open(path).ok()?;
+    if file.is_stream("WordDocument") {
+        Some(FileType::Doc)
+    } else if file.is_stream("Workbook") || file.is_stream("Book") {
+        Some(FileType::Spreadsheet)
+    } else {
+        None
+    }
+}
+
+/// 没有扩展名的文件：带 BOM 或者是不含 NUL 的 UTF-8 文本
+fn sniff_text(header: &[u8]) -> Option<FileType> {
+    let text = if let Some(rest) = header.strip_prefix(b"\xEF\xBB\xBF") {
+        rest
+    } else if header.starts_with(b"\xFF\xFE") || header.starts_with(b"\xFE\xFF") {
+        return Some(FileType::Text);
+    } else {
+        header
+    };
+    if text.contains(&0) {
+        return None;
+    }
+    // 文件头可能在多字节字符中间截断
+    let valid = match std::str::from_utf8(text) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if !valid {
+        return None;
+    }
+    let start = String::from_utf8_lossy(&text[..text.len().min(64)]).trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some(FileType::Html)
+    } else {
+        Some(FileType::Text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_detect() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, content: &[u8]| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+
+        assert_eq!(detect(&write("REPORT.PDF", b"%PDF-1.7\n")).unwrap(), FileType::Pdf);
+        assert_eq!(detect(&write("report", b"%PDF-1.4\n")).unwrap(), FileType::Pdf);
+        assert_eq!(detect(&write("junk.pdf", b"\x00\x00%PDF-1.5\n")).unwrap(), FileType::Pdf);
+        assert_eq!(detect(&write("magic.md", b"# Magic\n`%PDF-` starts a PDF")).unwrap(), FileType::Markdown);
+        assert_eq!(
+            detect(&write("detect.rs", b"const PDF_MAGIC: &[u8] = b\"%PDF-\";")).unwrap(),
+            FileType::Code
+        );
+        assert_eq!(detect(&write("Notes.MD", b"# Title")).unwrap(), FileType::Markdown);
+        assert_eq!(detect(&write("README", "说明".as_bytes())).unwrap(), FileType::Text);
+        assert_eq!(detect(&write("utf16", b"\xFF\xFEa\x00")).unwrap(), FileType::Text);
+        assert_eq!(detect(&write("page", b"\xEF\xBB\xBF<!DOCTYPE html><p>x</p>")).unwrap(), FileType::Html);
+        assert_eq!(detect(&write("main.RS", b"fn main() {}")).unwrap(), FileType::Code);
+        assert_eq!(detect(&write("image.PNG", b"\x89PNG")).unwrap(), FileType::Image);
+        assert_eq!(detect(&write("scan", b"\xFF\xD8\xFF\xE0")).unwrap(), FileType::Image);
+        assert_eq!(detect(&write("scan.tif", b"II*\x00")).unwrap(), FileType::Image);
+        assert_eq!(detect(&write("analysis.ipynb", b"{\"cells\": []}")).unwrap(), FileType::Notebook);
+        assert_eq!(detect(&write("paper.TEX", b"\\section{Intro}")).unwrap(), FileType::Latex);
+        assert_eq!(detect(&write("talk.vtt", b"WEBVTT\n")).unwrap(), FileType::Subtitle);
+        assert_eq!(detect(&write("openapi.YML", b"openapi: 3.0.0\n")).unwrap(), FileType::Structured);
+        assert!(matches!(
+            detect(&write("blob", b"\x00\x01\x02")),
+            Err(AidenErrors::UnsupportedFileType(_))
+        ));
+        assert!(detect(&dir.path().join("missing.txt")).is_err());
+
+        let docx = dir.path().join("document");
+        let mut zip = ZipWriter::new(File::create(&docx).unwrap());
+        for name in ["[Content_Types].xml", "word/document.xml"] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"<xml/>").unwrap();
+        }
+        zip.finish().unwrap();
+        assert_eq!(detect(&docx).unwrap(), FileType::Docx);
+
+        let bundle = dir.path().join("bundle");
+        let mut zip = ZipWriter::new(File::create(&bundle).unwrap());
+        zip.start_file("a.txt", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"a").unwrap();
+        zip.finish().unwrap();
+        assert_eq!(detect(&bundle).unwrap(), FileType::Archive);
+        let jar = dir.path().join("lib.jar");
+        std::fs::copy(&bundle, &jar).unwrap();
+        assert!(detect(&jar).is_err());
+    }
+}
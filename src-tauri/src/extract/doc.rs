@@ -0,0 +1,258 @@
+use crate::errors::{AidenErrors, AppResult};
+use std::io::Read;
+
+/// FibBase 中的 fEncrypted 标志
+const F_ENCRYPTED: u16 = 0x0100;
+/// FibBase 中的 fWhichTblStm 标志，为 1 时使用 1Table 流
+const F_WHICH_TBL_STM: u16 = 0x0200;
+/// PCD 中 fc 的 fCompressed 标志，为 1 时文本为单字节 cp1252
+const FC_COMPRESSED: u32 = 0x4000_0000;
+
+pub struct DocProcessor;
+
+impl DocProcessor {
+    /// Extracts text from a legacy Word 97-2003 (doc) file.
+    ///
+    /// The main document text is read from the `WordDocument` stream through the piece table
+    /// stored in the table stream. Field instructions are dropped and only field results are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the DOC file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
+        let mut file = cfb::open(path)?;
+
+        let mut word = Vec::new();
+        file.open_stream("WordDocument")?.read_to_end(&mut word)?;
+        let fib = Fib::parse(&word)?;
+        if fib.flags & F_ENCRYPTED != 0 {
+            return Err(AidenErrors::Str("加密的 DOC 文件暂不支持"));
+        }
+
+        let table_name = if fib.flags & F_WHICH_TBL_STM != 0 { "1Table" } else { "0Table" };
+        let mut table = Vec::new();
+        file.open_stream(table_name)?.read_to_end(&mut table)?;
+        let clx = (fib.fc_clx as usize)
+            .checked_add(fib.lcb_clx as usize)
+            .and_then(|end| table.get(fib.fc_clx as usize..end))
+            .ok_or(AidenErrors::Str("DOC 文件的 CLX 数据损坏"))?;
+
+        let mut text = String::new();
+        for piece in pieces(clx)? {
+            if piece.cp_start >= fib.ccp_text {
+                break;
+            }
+            let chars = piece
+                .cp_end
+                .min(fib.ccp_text)
+                .checked_sub(piece.cp_start)
+                .ok_or(AidenErrors::Str("DOC 文件的片段表损坏"))?;
+            text.push_str(&piece.read(&word, chars));
+        }
+
+        Ok(clean_text(&text))
+    }
+}
+
+/// FIB 中需要用到的字段
+struct Fib {
+    flags: u16,
+    ccp_text: u32,
+    fc_clx: u32,
+    lcb_clx: u32,
+}
+
+impl Fib {
+    fn parse(word: &[u8]) -> AppResult<Self> {
+        if read_u16(word, 0) != Some(0xA5EC) {
+            return Err(AidenErrors::Str("不是有效的 DOC 文件"));
+        }
+        let invalid = || AidenErrors::Str("DOC 文件的 FIB 数据损坏");
+        Ok(Self {
+            flags: read_u16(word, 0x0A).ok_or_else(invalid)?,
+            ccp_text: read_u32(word, 0x4C).ok_or_else(invalid)?,
+            fc_clx: read_u32(word, 0x01A2).ok_or_else(invalid)?,
+            lcb_clx: read_u32(word, 0x01A6).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// 文本片段：字符位置区间及其在 WordDocument 流中的偏移
+struct Piece {
+    cp_start: u32,
+    cp_end: u32,
+    fc: u32,
+}
+
+impl Piece {
+    fn read(&self, word: &[u8], chars: u32) -> String {
+        if self.fc & FC_COMPRESSED != 0 {
+            let start = ((self.fc & !FC_COMPRESSED) / 2) as usize;
+            let bytes = start.checked_add(chars as usize).and_then(|end| word.get(start..end)).unwrap_or_default();
+            let (text, _) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes);
+            text.into_owned()
+        } else {
+            let start = self.fc as usize;
+            let bytes = (chars as usize)
+                .checked_mul(2)
+                .and_then(|len| start.checked_add(len))
+                .and_then(|end| word.get(start..end))
+                .unwrap_or_default();
+            let units = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+    }
+}
+
+/// 解析 CLX 中的 PlcPcd，跳过前面的 Prc 格式数据
+fn pieces(clx: &[u8]) -> AppResult<Vec<Piece>> {
+    let invalid = || AidenErrors::Str("DOC 文件的片段表损坏");
+    let mut pos = 0;
+    while clx.get(pos) == Some(&0x01) {
+        let cb = read_u16(clx, pos + 1).ok_or_else(invalid)? as usize;
+        pos = pos.checked_add(3 + cb).ok_or_else(invalid)?;
+    }
+    if clx.get(pos) != Some(&0x02) {
+        return Err(invalid());
+    }
+    let lcb = read_u32(clx, pos + 1).ok_or_else(invalid)? as usize;
+    let plc = (pos + 5).checked_add(lcb).and_then(|end| clx.get(pos + 5..end)).ok_or_else(invalid)?;
+
+    // PlcPcd 由 n + 1 个 CP（4 字节）和 n 个 PCD（8 字节）组成
+    let n = lcb.checked_sub(4).ok_or_else(invalid)? / 12;
+    let mut pieces = Vec::with_capacity(n);
+    for i in 0..n {
+        let cp_start = read_u32(plc, i * 4).ok_or_else(invalid)?;
+        let cp_end = read_u32(plc, (i + 1) * 4).ok_or_else(invalid)?;
+        let fc = read_u32(plc, (n + 1) * 4 + i * 8 + 2).ok_or_else(invalid)?;
+        pieces.push(Piece { cp_start, cp_end, fc });
+    }
+    Ok(pieces)
+}
+
+/// 处理 Word 的特殊字符：段落、单元格、换行以及域代码
+fn clean_text(raw: &str) -> String {
+    let mut text = String::with_capacity(raw.len());
+    // 域嵌套时记录每一层是否处于域代码部分
+    let mut fields: Vec<bool> = Vec::new();
+    for c in raw.chars() {
+        match c {
+            '\u{13}' => fields.push(true),
+            '\u{14}' => {
+                if let Some(instruction) = fields.last_mut() {
+                    *instruction = false;
+                }
+            }
+            '\u{15}' => {
+                fields.pop();
+            }
+            _ if fields.last() == Some(&true) => {}
+            '\r' | '\u{0B}' | '\u{0C}' => text.push('\n'),
+            '\u{07}' => text.push_str(" | "),
+            '\u{1E}' => text.push('-'),
+            '\u{A0}' => text.push(' '),
+            c if c.is_control() && c != '\t' => {}
+            c => text.push(c),
+        }
+    }
+    text.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    /// WordDocument 流的 FIB 部分，CLX 位于 0Table 开头
+    fn word_stream(ccp_text: u32, lcb_clx: u32) -> Vec<u8> {
+        let mut word = vec![0u8; 0x800];
+        word[0..2].copy_from_slice(&0xA5ECu16.to_le_bytes());
+        word[0x4C..0x50].copy_from_slice(&ccp_text.to_le_bytes());
+        word[0x01A6..0x01AA].copy_from_slice(&lcb_clx.to_le_bytes());
+        word
+    }
+
+    /// 只有 PlcPcd 的 CLX
+    fn plc_clx(plc: &[u8]) -> Vec<u8> {
+        let mut clx = vec![0x02];
+        clx.extend((plc.len() as u32).to_le_bytes());
+        clx.extend(plc);
+        clx
+    }
+
+    fn write_doc(path: &std::path::Path, word: &[u8], clx: &[u8]) {
+        let mut file = cfb::create(path).unwrap();
+        file.create_stream("WordDocument").unwrap().write_all(word).unwrap();
+        file.create_stream("0Table").unwrap().write_all(clx).unwrap();
+        file.flush().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_extract_text() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.doc");
+
+        // 第一个片段为 UTF-16，第二个片段为压缩的单字节文本
+        let unicode = "工业平台\r\u{13} HYPERLINK \"x\" \u{14}链接\u{15}\r";
+        let ansi = b"Port\x078080\x07\r";
+        let unicode_len = unicode.encode_utf16().count() as u32;
+        let ccp_text = unicode_len + ansi.len() as u32;
+
+        let mut plc = Vec::new();
+        for cp in [0, unicode_len, ccp_text] {
+            plc.extend(cp.to_le_bytes());
+        }
+        let ansi_offset = 0x800 + unicode_len * 2;
+        for fc in [0x800u32, (ansi_offset * 2) | FC_COMPRESSED] {
+            plc.extend([0u8, 0]);
+            plc.extend(fc.to_le_bytes());
+            plc.extend([0u8, 0]);
+        }
+        // CLX 前有一段 Prc 格式数据
+        let clx = [vec![0x01, 0x02, 0x00, 0xAA, 0xBB], plc_clx(&plc)].concat();
+
+        let mut word = word_stream(ccp_text, clx.len() as u32);
+        word.extend(unicode.encode_utf16().flat_map(u16::to_le_bytes));
+        word.extend_from_slice(ansi);
+        write_doc(&path, &word, &clx);
+
+        let text = DocProcessor::extract_text(&path).await.unwrap();
+        assert_eq!(text, "工业平台\n链接\nPort | 8080 |");
+    }
+
+    #[tokio::test]
+    async fn test_extract_corrupt() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("corrupt.doc");
+        let is_corrupt = |result: AppResult<String>| matches!(result, Err(AidenErrors::Str(msg)) if msg.contains("损坏"));
+
+        // PlcPcd 不足 4 字节
+        let clx = plc_clx(&[0, 0]);
+        write_doc(&path, &word_stream(100, clx.len() as u32), &clx);
+        assert!(is_corrupt(DocProcessor::extract_text(&path).await));
+
+        // 片段的结束位置在起始位置之前
+        let plc = [50u32.to_le_bytes(), 10u32.to_le_bytes()].concat();
+        let clx = plc_clx(&[plc, vec![0; 8]].concat());
+        write_doc(&path, &word_stream(100, clx.len() as u32), &clx);
+        assert!(is_corrupt(DocProcessor::extract_text(&path).await));
+
+        // CLX 的长度超出表流
+        write_doc(&path, &word_stream(100, u32::MAX), &clx);
+        assert!(is_corrupt(DocProcessor::extract_text(&path).await));
+    }
+}
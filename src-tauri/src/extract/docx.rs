@@ -1,126 +1,541 @@
-use docx_rs::*;
-
+use crate::errors::AppResult;
+use crate::extract::package::{read_entry, resolve_target};
+use roxmltree::{Document, Node};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Read, Seek};
+use zip::ZipArchive;
 
-use crate::errors::AppResult;
-use docx_rs::read_docx;
+const HEADER_REL: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/header";
+const FOOTER_REL: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer";
+/// Markdown 最多支持六级标题
+const MAX_HEADING_LEVEL: usize = 6;
 
-pub struct DocxRsProcessor;
+pub struct DocxProcessor;
 
-impl DocxRsProcessor {
-    /// Extracts text from a Docx file.
+impl DocxProcessor {
+    /// Extracts text from a Word (docx) file as Markdown-like text.
+    ///
+    /// Heading styles become `#` headings, numbered and bulleted paragraphs become list items, tables
+    /// become pipe tables and hyperlinks keep their target. Text boxes follow the paragraph that anchors them.
+    /// Page headers come first and page footers last, followed by footnotes, endnotes and comments.
+    /// Blocks are separated by blank lines, so chunking never merges a heading into the next paragraph.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path to the PDF file.
+    /// * `path` - The path to the DOCX file.
     ///
     /// # Returns
     ///
     /// Returns a `Result` containing the extracted text as a `String` if successful,
     /// or an `Error` if an error occurred during the extraction process.
     pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
-        let mut file = File::open(path)?;
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+        let package = DocxPackage::read(&mut archive)?;
 
-        let mut buf = vec![];
-        file.read_to_end(&mut buf)?;
+        let xml = read_entry(&mut archive, "word/document.xml")?;
+        let doc = Document::parse(&xml)?;
+        let mut writer = Writer::new(&package);
+        if let Some(body) = doc.descendants().find(|n| n.has_tag_name("body")) {
+            writer.walk(body);
+        }
 
-        let res = read_docx(&buf)?;
-        let mut text = String::new();
-        let children = res.document.children;
+        let mut blocks = Vec::new();
+        blocks.extend(package.headers.iter().cloned());
+        blocks.append(&mut writer.blocks);
+        blocks.extend(package.footers.iter().cloned());
+        blocks.append(&mut writer.notes);
+        blocks.append(&mut writer.comments);
+        // 与 HTML 一致，块之间空一行：切分时单个换行会被合并为空格
+        Ok(blocks.join("\n\n"))
+    }
+}
 
-        for i in children {
-            match i {
-                DocumentChild::Paragraph(s) => {
-                    parse_paragraph(s, &mut text);
-                }
-                DocumentChild::Table(s) => {
-                    parse_table(s, &mut text);
-                }
-                _ => {}
+/// 段落样式：标题级别以及样式自带的编号
+#[derive(Debug, Default)]
+struct Style {
+    based_on: Option<String>,
+    heading: Option<usize>,
+    numbering: Option<(String, usize)>,
+}
+
+/// 正文之外的部件：样式、编号、超链接、脚注、批注和页眉页脚
+#[derive(Debug, Default)]
+struct DocxPackage {
+    styles: HashMap<String, Style>,
+    /// numId -> 各级别的编号格式，如 bullet、decimal
+    numbering: HashMap<String, HashMap<usize, String>>,
+    /// 外部超链接 rId -> URL
+    links: HashMap<String, String>,
+    footnotes: HashMap<String, String>,
+    endnotes: HashMap<String, String>,
+    /// 批注 id -> (作者, 内容)
+    comments: HashMap<String, (Option<String>, String)>,
+    headers: Vec<String>,
+    footers: Vec<String>,
+}
+
+impl DocxPackage {
+    fn read<R: Read + Seek>(archive: &mut ZipArchive<R>) -> AppResult<Self> {
+        let mut package = DocxPackage::default();
+        if let Some(xml) = read_optional(archive, "word/styles.xml")? {
+            package.styles = parse_styles(&Document::parse(&xml)?);
+        }
+        if let Some(xml) = read_optional(archive, "word/numbering.xml")? {
+            package.numbering = parse_numbering(&Document::parse(&xml)?);
+        }
+        if let Some(xml) = read_optional(archive, "word/footnotes.xml")? {
+            package.footnotes = package.parse_notes(&Document::parse(&xml)?, "footnote");
+        }
+        if let Some(xml) = read_optional(archive, "word/endnotes.xml")? {
+            package.endnotes = package.parse_notes(&Document::parse(&xml)?, "endnote");
+        }
+        if let Some(xml) = read_optional(archive, "word/comments.xml")? {
+            let doc = Document::parse(&xml)?;
+            package.comments = doc
+                .descendants()
+                .filter(|n| n.has_tag_name("comment"))
+                .filter_map(|n| Some((attr(n, "id")?.to_string(), (attr(n, "author").map(str::to_string), package.plain_text(n)))))
+                .collect();
+        }
+
+        let Some(rels) = read_optional(archive, "word/_rels/document.xml.rels")? else {
+            return Ok(package);
+        };
+        let rels = Document::parse(&rels)?;
+        let mut parts = Vec::new();
+        for rel in rels.descendants().filter(|n| n.has_tag_name("Relationship")) {
+            let (Some(id), Some(target)) = (rel.attribute("Id"), rel.attribute("Target")) else {
+                continue;
+            };
+            if rel.attribute("TargetMode") == Some("External") {
+                package.links.insert(id.to_string(), target.to_string());
+            } else if matches!(rel.attribute("Type"), Some(HEADER_REL | FOOTER_REL)) {
+                parts.push((rel.attribute("Type") == Some(HEADER_REL), resolve_target("word", target)));
+            }
+        }
+
+        // 奇偶页、首页的页眉页脚通常内容相同，只保留一次；单独的页码没有检索价值
+        let mut seen = HashSet::new();
+        for (is_header, part) in parts {
+            let Some(xml) = read_optional(archive, &part)? else {
+                continue;
+            };
+            let doc = Document::parse(&xml)?;
+            let mut writer = Writer::new(&package);
+            writer.walk(doc.root_element());
+            let lines = writer
+                .blocks
+                .into_iter()
+                .filter(|l| !l.chars().all(|c| c.is_ascii_digit() || c.is_whitespace()))
+                .filter(|l| seen.insert(l.clone()))
+                .collect::<Vec<_>>();
+            if is_header {
+                package.headers.extend(lines);
+            } else {
+                package.footers.extend(lines);
             }
         }
+        Ok(package)
+    }
+
+    /// 脚注和尾注，跳过分隔线这类特殊注释
+    fn parse_notes(&self, doc: &Document, tag: &str) -> HashMap<String, String> {
+        doc.descendants()
+            .filter(|n| n.has_tag_name(tag) && attr(*n, "type").is_none())
+            .filter_map(|n| Some((attr(n, "id")?.to_string(), self.plain_text(n))))
+            .filter(|(_, text)| !text.is_empty())
+            .collect()
+    }
+
+    /// 注释中的段落合并为一行
+    fn plain_text(&self, node: Node) -> String {
+        let mut writer = Writer::new(self);
+        writer.walk(node);
+        writer.blocks.join(" ").replace('\n', " ")
+    }
+
+    /// 段落的标题级别，直接设置的大纲级别优先于样式
+    fn heading(&self, ppr: Option<Node>, style: Option<&str>) -> Option<usize> {
+        if let Some(level) = ppr.and_then(|p| child(p, "outlineLvl")).and_then(|n| attr(n, "val")) {
+            return outline_level(level);
+        }
+        self.style_chain(style).find_map(|s| s.heading).or_else(|| {
+            // 没有 styles.xml 时按内置样式名识别
+            let id = style?.to_ascii_lowercase();
+            id.strip_prefix("heading")
+                .and_then(|n| n.trim().parse::<usize>().ok())
+                .map(|n| n.clamp(1, MAX_HEADING_LEVEL))
+        })
+    }
+
+    /// 段落的编号 (numId, 级别)，numId 为 0 表示取消编号
+    fn numbering(&self, ppr: Option<Node>, style: Option<&str>) -> Option<(String, usize)> {
+        let numbering = match ppr.and_then(|p| child(p, "numPr")) {
+            Some(num_pr) => num_pr_of(num_pr),
+            None => self.style_chain(style).find_map(|s| s.numbering.clone()),
+        };
+        numbering.filter(|(id, _)| id != "0")
+    }
+
+    /// 样式及其继承的父样式
+    fn style_chain<'a>(&'a self, style: Option<&'a str>) -> impl Iterator<Item = &'a Style> + 'a {
+        let mut next = style;
+        std::iter::from_fn(move || {
+            let style = self.styles.get(next?)?;
+            next = style.based_on.as_deref();
+            Some(style)
+        })
+        .take(16)
+    }
 
-        Ok(text)
+    fn is_bullet(&self, num_id: &str, level: usize) -> bool {
+        self.numbering
+            .get(num_id)
+            .and_then(|levels| levels.get(&level))
+            .is_none_or(|fmt| fmt == "bullet")
     }
 }
 
-fn parse_table(s: Box<Table>, text: &mut String) {
-    for ele in s.rows {
-        match ele {
-            TableChild::TableRow(tr) => {
-                for ele2 in tr.cells {
-                    match ele2 {
-                        TableRowChild::TableCell(tc) => {
-                            for ele3 in tc.children {
-                                match ele3 {
-                                    TableCellContent::Paragraph(tp) => {
-                                        parse_paragraph(Box::new(tp), text);
-                                    }
-                                    TableCellContent::Table(tt) => {
-                                        for row_ele in tt.rows {
-                                            match row_ele {
-                                                TableChild::TableRow(ttr) => {
-                                                    for ttr_ele in ttr.cells {
-                                                        match ttr_ele {
-                                                            TableRowChild::TableCell(ttr_tc) => {
-                                                                for ele in ttr_tc.children {
-                                                                    match ele {
-                                                                        TableCellContent::Paragraph(p) => {
-                                                                            parse_paragraph(Box::new(p), text);
-                                                                        }
-                                                                        TableCellContent::Table(t) => {
-                                                                            parse_table(Box::new(t), text);
-                                                                        }
-                                                                        _ => {}
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
+/// 按文档顺序输出 Markdown 块：段落、标题、一个列表或一个表格
+struct Writer<'a> {
+    package: &'a DocxPackage,
+    blocks: Vec<String>,
+    /// 最后一个块是列表时为其 numId，同一列表的后续项追加到该块中
+    list: Option<String>,
+    notes: Vec<String>,
+    comments: Vec<String>,
+    /// 有序列表的计数，按 numId 记录每一级当前的序号
+    counters: HashMap<String, Vec<usize>>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(package: &'a DocxPackage) -> Self {
+        Self {
+            package,
+            blocks: Vec::new(),
+            list: None,
+            notes: Vec::new(),
+            comments: Vec::new(),
+            counters: HashMap::new(),
+        }
+    }
+
+    fn walk(&mut self, node: Node) {
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "p" => self.paragraph(child),
+                "tbl" => self.table(child),
+                // 兼容内容与 Choice 重复，属性不含文本
+                "Fallback" | "sectPr" | "pPr" | "rPr" | "tblPr" | "sdtPr" => {}
+                _ => self.walk(child),
+            }
+        }
+    }
+
+    fn paragraph(&mut self, p: Node) {
+        let mut text = String::new();
+        let mut boxes = Vec::new();
+        self.inline(p, &mut text, &mut boxes);
+
+        let text = text.trim();
+        if !text.is_empty() {
+            let ppr = child(p, "pPr");
+            let style = ppr.and_then(|p| child(p, "pStyle")).and_then(|n| attr(n, "val"));
+            if let Some(level) = self.package.heading(ppr, style) {
+                self.push_block(format!("{} {}", "#".repeat(level), text.replace('\n', " ")));
+            } else if let Some((num_id, level)) = self.package.numbering(ppr, style) {
+                let indent = "  ".repeat(level);
+                let marker = if self.package.is_bullet(&num_id, level) {
+                    "-".to_string()
+                } else {
+                    let counter = self.counters.entry(num_id.clone()).or_default();
+                    // 回到上一级时重新开始下级的编号
+                    counter.resize(level + 1, 0);
+                    counter[level] += 1;
+                    format!("{}.", counter[level])
+                };
+                let item = format!("{}{} {}", indent, marker, text.replace('\n', " "));
+                match self.blocks.last_mut() {
+                    Some(list) if self.list.as_ref() == Some(&num_id) => {
+                        list.push('\n');
+                        list.push_str(&item);
+                    }
+                    _ => {
+                        self.push_block(item);
+                        self.list = Some(num_id);
                     }
                 }
+            } else {
+                self.push_block(text.to_string());
             }
         }
+
+        // 文本框跟在锚定它的段落之后
+        for text_box in boxes {
+            self.walk(text_box);
+        }
+    }
+
+    fn push_block(&mut self, block: String) {
+        self.blocks.push(block);
+        self.list = None;
     }
-}
 
-fn parse_paragraph(s: Box<Paragraph>, text: &mut String) {
-    for ele in s.children {
-        match ele {
-            ParagraphChild::Run(r) => {
-                for ele2 in r.children {
-                    match ele2 {
-                        RunChild::Text(t) => {
-                            text.push_str(&t.text);
+    /// 整个表格是一个块，第一行作为表头
+    fn table(&mut self, tbl: Node) {
+        let mut rows = Vec::new();
+        for row in tbl.children().filter(|n| n.tag_name().name() == "tr") {
+            let cells = row
+                .children()
+                .filter(|n| n.tag_name().name() == "tc")
+                .map(|tc| {
+                    let outer = std::mem::take(&mut self.blocks);
+                    self.walk(tc);
+                    let cell = std::mem::replace(&mut self.blocks, outer);
+                    cell.join(" ").replace('\n', " ")
+                })
+                .collect::<Vec<_>>();
+            if cells.iter().any(|c| !c.is_empty()) {
+                if rows.len() == 1 {
+                    rows.push(format!("|{}", " --- |".repeat(cells.len())));
+                }
+                rows.push(format!("| {} |", cells.join(" | ")));
+            }
+        }
+        if !rows.is_empty() {
+            self.push_block(rows.join("\n"));
+        }
+    }
+
+    /// 段落中的行内内容，文本框收集到 `boxes` 中稍后输出
+    fn inline<'n, 'i>(&mut self, node: Node<'n, 'i>, text: &mut String, boxes: &mut Vec<Node<'n, 'i>>) {
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "t" => text.push_str(child.text().unwrap_or_default()),
+                "tab" => text.push('\t'),
+                "br" | "cr" if attr(child, "type") != Some("page") => text.push('\n'),
+                "noBreakHyphen" => text.push('-'),
+                "hyperlink" => {
+                    let mut label = String::new();
+                    self.inline(child, &mut label, boxes);
+                    match attr(child, "id").and_then(|id| self.package.links.get(id)) {
+                        Some(url) if !label.trim().is_empty() => text.push_str(&format!("[{}]({})", label.trim(), url)),
+                        _ => text.push_str(&label),
+                    }
+                }
+                "footnoteReference" => self.note(text, attr(child, "id").and_then(|id| self.package.footnotes.get(id))),
+                "endnoteReference" => self.note(text, attr(child, "id").and_then(|id| self.package.endnotes.get(id))),
+                "commentReference" => {
+                    if let Some((author, comment)) = attr(child, "id").and_then(|id| self.package.comments.get(id)) {
+                        match author {
+                            Some(author) => self.comments.push(format!("Comment ({}): {}", author, comment)),
+                            None => self.comments.push(format!("Comment: {}", comment)),
                         }
-                        _ => {}
                     }
                 }
+                "txbxContent" => boxes.push(child),
+                // 页码等域只有缓存值，没有检索价值
+                "fldSimple" if attr(child, "instr").is_some_and(|i| i.contains("PAGE")) => {}
+                // 删除的修订、域代码和格式属性不属于正文
+                "del" | "moveFrom" | "delText" | "instrText" | "Fallback" | "pPr" | "rPr" => {}
+                _ => self.inline(child, text, boxes),
             }
-            _ => {}
         }
     }
+
+    /// 在正文中插入 `[^N]` 标记，注释内容追加到文末
+    fn note(&mut self, text: &mut String, note: Option<&String>) {
+        if let Some(note) = note {
+            let n = self.notes.len() + 1;
+            text.push_str(&format!("[^{}]", n));
+            self.notes.push(format!("[^{}]: {}", n, note));
+        }
+    }
+}
+
+fn parse_styles(doc: &Document) -> HashMap<String, Style> {
+    doc.descendants()
+        .filter(|n| n.tag_name().name() == "style" && attr(*n, "type") == Some("paragraph"))
+        .filter_map(|n| {
+            let id = attr(n, "styleId")?.to_string();
+            let ppr = child(n, "pPr");
+            let name = child(n, "name").and_then(|c| attr(c, "val")).unwrap_or_default().to_lowercase();
+            let heading = match ppr.and_then(|p| child(p, "outlineLvl")).and_then(|c| attr(c, "val")) {
+                Some(level) => outline_level(level),
+                None if name == "title" => Some(1),
+                None => name
+                    .strip_prefix("heading ")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .map(|n| n.clamp(1, MAX_HEADING_LEVEL)),
+            };
+            let style = Style {
+                based_on: child(n, "basedOn").and_then(|c| attr(c, "val")).map(str::to_string),
+                heading,
+                numbering: ppr.and_then(|p| child(p, "numPr")).and_then(num_pr_of),
+            };
+            Some((id, style))
+        })
+        .collect()
+}
+
+fn parse_numbering(doc: &Document) -> HashMap<String, HashMap<usize, String>> {
+    let abstracts = doc
+        .descendants()
+        .filter(|n| n.tag_name().name() == "abstractNum")
+        .filter_map(|n| {
+            let levels = n
+                .children()
+                .filter(|l| l.tag_name().name() == "lvl")
+                .filter_map(|l| {
+                    let level = attr(l, "ilvl")?.parse::<usize>().ok()?;
+                    let format = child(l, "numFmt").and_then(|f| attr(f, "val")).unwrap_or("decimal");
+                    Some((level, format.to_string()))
+                })
+                .collect::<HashMap<_, _>>();
+            Some((attr(n, "abstractNumId")?, levels))
+        })
+        .collect::<HashMap<_, _>>();
+
+    doc.descendants()
+        .filter(|n| n.tag_name().name() == "num")
+        .filter_map(|n| {
+            let abstract_id = child(n, "abstractNumId").and_then(|a| attr(a, "val"))?;
+            Some((attr(n, "numId")?.to_string(), abstracts.get(abstract_id)?.clone()))
+        })
+        .collect()
+}
+
+/// `w:numPr` 中的 (numId, ilvl)
+fn num_pr_of(num_pr: Node) -> Option<(String, usize)> {
+    let num_id = child(num_pr, "numId").and_then(|n| attr(n, "val"))?;
+    let level = child(num_pr, "ilvl")
+        .and_then(|n| attr(n, "val"))
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    Some((num_id.to_string(), level))
+}
+
+/// 大纲级别从 0 开始，9 表示正文
+fn outline_level(level: &str) -> Option<usize> {
+    level.parse::<usize>().ok().filter(|l| *l < 9).map(|l| (l + 1).min(MAX_HEADING_LEVEL))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+/// 按本地名称读取属性，兼容 Transitional 和 Strict 两种命名空间
+fn attr<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == name).map(|a| a.value())
+}
+
+fn read_optional<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> AppResult<Option<String>> {
+    if archive.index_for_name(name).is_none() {
+        return Ok(None);
+    }
+    read_entry(archive, name).map(Some)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const W: &str = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006""#;
+
+    const STYLES: &str = r#"<w:styles W>
+<w:style w:type="paragraph" w:styleId="1"><w:name w:val="heading 1"/><w:pPr><w:outlineLvl w:val="0"/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/></w:style>
+<w:style w:type="paragraph" w:styleId="Custom"><w:name w:val="Custom Heading"/><w:basedOn w:val="Heading2"/></w:style>
+<w:style w:type="paragraph" w:styleId="ListBullet"><w:name w:val="List Bullet"/><w:pPr><w:numPr><w:numId w:val="1"/></w:numPr></w:pPr></w:style>
+</w:styles>"#;
+
+    const NUMBERING: &str = r#"<w:numbering W>
+<w:abstractNum w:abstractNumId="0"><w:lvl w:ilvl="0"><w:numFmt w:val="bullet"/></w:lvl><w:lvl w:ilvl="1"><w:numFmt w:val="bullet"/></w:lvl></w:abstractNum>
+<w:abstractNum w:abstractNumId="1"><w:lvl w:ilvl="0"><w:numFmt w:val="decimal"/></w:lvl><w:lvl w:ilvl="1"><w:numFmt w:val="lowerLetter"/></w:lvl></w:abstractNum>
+<w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>
+<w:num w:numId="2"><w:abstractNumId w:val="1"/></w:num>
+</w:numbering>"#;
+
+    const DOCUMENT: &str = r#"<w:document W><w:body>
+<w:p><w:pPr><w:pStyle w:val="1"/></w:pPr><w:r><w:t>安装</w:t></w:r></w:p>
+<w:p><w:r><w:t xml:space="preserve">Run </w:t></w:r><w:r><w:rPr><w:b/></w:rPr><w:t>setup</w:t></w:r><w:r><w:footnoteReference w:id="2"/></w:r><w:del><w:r><w:delText>old</w:delText></w:r></w:del><w:r><w:t xml:space="preserve"> from </w:t></w:r><w:hyperlink r:id="rId9"><w:r><w:t>the site</w:t></w:r></w:hyperlink><w:r><w:t>.</w:t></w:r><w:r><w:commentReference w:id="0"/></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="Custom"/></w:pPr><w:r><w:t>Network</w:t></w:r></w:p>
+<w:p><w:pPr><w:pStyle w:val="ListBullet"/></w:pPr><w:r><w:t>Linux</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>Ubuntu</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="2"/></w:numPr></w:pPr><w:r><w:t>Open the port</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="1"/><w:numId w:val="2"/></w:numPr></w:pPr><w:r><w:t>TCP</w:t></w:r></w:p>
+<w:p><w:pPr><w:numPr><w:ilvl w:val="0"/><w:numId w:val="2"/></w:numPr></w:pPr><w:r><w:t>Restart</w:t></w:r></w:p>
+<w:tbl><w:tblPr/><w:tr><w:tc><w:p><w:r><w:t>Port</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>8080</w:t></w:r></w:p></w:tc></w:tr>
+<w:tr><w:tc><w:p><w:r><w:t>Host</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>a</w:t></w:r><w:r><w:br/><w:t>b</w:t></w:r></w:p></w:tc></w:tr></w:tbl>
+<w:p><w:r><w:t>See figure</w:t></w:r><w:r><mc:AlternateContent><mc:Choice><w:drawing><w:txbxContent><w:p><w:r><w:t>Box text</w:t></w:r></w:p></w:txbxContent></w:drawing></mc:Choice><mc:Fallback><w:pict><w:txbxContent><w:p><w:r><w:t>Box text</w:t></w:r></w:p></w:txbxContent></w:pict></mc:Fallback></mc:AlternateContent></w:r></w:p>
+<w:sectPr/>
+</w:body></w:document>"#;
+
+    const FOOTNOTES: &str = r#"<w:footnotes W>
+<w:footnote w:type="separator" w:id="-1"><w:p><w:r><w:separator/></w:r></w:p></w:footnote>
+<w:footnote w:id="2"><w:p><w:r><w:footnoteRef/></w:r><w:r><w:t xml:space="preserve"> Requires admin rights.</w:t></w:r></w:p></w:footnote>
+</w:footnotes>"#;
+
+    const COMMENTS: &str = r#"<w:comments W><w:comment w:id="0" w:author="Li"><w:p><w:r><w:t>Check the URL</w:t></w:r></w:p></w:comment></w:comments>"#;
+
+    const HEADER: &str = r#"<w:hdr W><w:p><w:r><w:t>Vendor Manual</w:t></w:r></w:p><w:p><w:fldSimple w:instr=" PAGE "><w:r><w:t>1</w:t></w:r></w:fldSimple></w:p></w:hdr>"#;
+
+    const FOOTER: &str = r#"<w:ftr W><w:p><w:r><w:t>12</w:t></w:r></w:p><w:p><w:r><w:t>Confidential</w:t></w:r></w:p></w:ftr>"#;
+
+    const RELS: &str = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId9" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/setup" TargetMode="External"/>
+<Relationship Id="rId10" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header1.xml"/>
+<Relationship Id="rId11" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/header" Target="header2.xml"/>
+<Relationship Id="rId12" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer" Target="footer1.xml"/>
+</Relationships>"#;
 
     #[tokio::test]
     async fn test_extract_text() {
-        let pdf_file = shellexpand::full("~/Downloads/CISDigital®工业互联网平台（V3.0）产品操作手册-工业时序数据存算平台-V1.0 .docx")
-            .unwrap()
-            .to_string();
-        let text = DocxRsProcessor::extract_text(pdf_file).await.unwrap();
-        println!("{:?}", text);
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.docx");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in [
+            ("word/document.xml", DOCUMENT),
+            ("word/styles.xml", STYLES),
+            ("word/numbering.xml", NUMBERING),
+            ("word/footnotes.xml", FOOTNOTES),
+            ("word/comments.xml", COMMENTS),
+            ("word/header1.xml", HEADER),
+            ("word/header2.xml", HEADER),
+            ("word/footer1.xml", FOOTER),
+            ("word/_rels/document.xml.rels", RELS),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.replace(" W>", &format!(" {}>", W)).as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let text = DocxProcessor::extract_text(&path).await.unwrap();
+        assert_eq!(
+            text,
+            "Vendor Manual\n\n\
+             # 安装\n\n\
+             Run setup[^1] from [the site](https://example.com/setup).\n\n\
+             ## Network\n\n\
+             - Linux\n  \
+             - Ubuntu\n\n\
+             1. Open the port\n  \
+             1. TCP\n\
+             2. Restart\n\n\
+             | Port | 8080 |\n\
+             | --- | --- |\n\
+             | Host | a b |\n\n\
+             See figure\n\n\
+             Box text\n\n\
+             Confidential\n\n\
+             [^1]: Requires admin rights.\n\n\
+             Comment (Li): Check the URL"
+        );
     }
 }
@@ -0,0 +1,310 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::html::html_to_markdown;
+use crate::extract::{extract_sections, Section};
+use mailparse::{parse_mail, DispositionType, MailHeaderMap, ParsedMail};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use tempfile::tempdir;
+
+/// 作为章节元数据保存的邮件头
+static HEADERS: &[(&str, &str)] = &[("From", "from"), ("To", "to"), ("Cc", "cc"), ("Subject", "subject"), ("Date", "date")];
+/// 作为附件的邮件最多嵌套的层数，更深的跳过
+const MAX_NESTED_DEPTH: usize = 3;
+
+pub struct EmailProcessor;
+
+impl EmailProcessor {
+    /// Extracts the messages of an eml file or an mbox archive.
+    ///
+    /// Every message becomes a `Section` titled with its subject and carrying From/To/Cc/Subject/Date
+    /// as metadata. The text/plain body is preferred over text/html. Attachments are extracted through
+    /// `extract_sections` and returned as child sections right after their message; attached messages
+    /// are parsed in place, at most three levels deep.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the eml or mbox file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        let bytes = std::fs::read(path)?;
+        let mut sections = Vec::new();
+        for message in split_mbox(&bytes) {
+            let mail = parse_mail(&message)?;
+            sections.extend(message_sections(&mail, 0).await?);
+        }
+        Ok(sections)
+    }
+}
+
+/// 一封邮件的正文和附件，`depth` 为邮件作为附件嵌套的层数
+async fn message_sections(mail: &ParsedMail<'_>, depth: usize) -> AppResult<Vec<Section>> {
+    let mut metadata = HashMap::new();
+    for (header, key) in HEADERS {
+        if let Some(value) = mail.headers.get_first_value(header).filter(|v| !v.trim().is_empty()) {
+            metadata.insert(key.to_string(), value.trim().to_string());
+        }
+    }
+    let subject = metadata.get("subject").cloned();
+
+    let mut plain = Vec::new();
+    let mut html = Vec::new();
+    let mut attachments = Vec::new();
+    collect_parts(mail, &mut plain, &mut html, &mut attachments);
+
+    let body = if !plain.is_empty() {
+        plain.iter().filter_map(|p| p.get_body().ok()).collect::<Vec<_>>().join("\n\n")
+    } else {
+        html.iter()
+            .filter_map(|p| p.get_body().ok())
+            .map(|h| html_to_markdown(&h))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    let mut text = String::new();
+    for (header, key) in HEADERS {
+        if let Some(value) = metadata.get(*key) {
+            text.push_str(&format!("{}: {}\n", header, value));
+        }
+    }
+    text.push('\n');
+    text.push_str(body.trim());
+
+    let mut sections = vec![Section {
+        title: subject.clone(),
+        text,
+        metadata: metadata.clone(),
+        ..Default::default()
+    }];
+
+    for attachment in attachments {
+        let Some(name) = attachment_name(attachment) else {
+            continue;
+        };
+        let children = if is_message(attachment, &name) {
+            nested_sections(attachment, depth).await
+        } else {
+            attachment_sections(attachment, &name).await
+        };
+        match children {
+            Ok(children) => {
+                for mut child in children {
+                    let mut child_metadata = metadata.clone();
+                    child_metadata.insert("attachment".to_string(), name.clone());
+                    child_metadata.extend(child.metadata);
+                    child.metadata = child_metadata;
+                    child.title = Some(match (&subject, child.title) {
+                        (Some(subject), Some(title)) => format!("{} > {} > {}", subject, name, title),
+                        (Some(subject), None) => format!("{} > {}", subject, name),
+                        (None, Some(title)) => format!("{} > {}", name, title),
+                        (None, None) => name.clone(),
+                    });
+                    sections.push(child);
+                }
+            }
+            Err(e) => log::warn!("Skip attachment {}: {}", name, e),
+        }
+    }
+
+    Ok(sections)
+}
+
+/// 作为附件的邮件直接解析并记录嵌套层数，超过 `MAX_NESTED_DEPTH` 层时返回错误
+async fn nested_sections(attachment: &ParsedMail<'_>, depth: usize) -> AppResult<Vec<Section>> {
+    if depth >= MAX_NESTED_DEPTH {
+        return Err(AidenErrors::String(format!("嵌套的邮件超过 {} 层", MAX_NESTED_DEPTH)));
+    }
+    let body = attachment.get_body_raw()?;
+    let mail = parse_mail(&body)?;
+    Box::pin(message_sections(&mail, depth + 1)).await
+}
+
+fn is_message(attachment: &ParsedMail<'_>, name: &str) -> bool {
+    attachment.ctype.mimetype == "message/rfc822" || name.to_ascii_lowercase().ends_with(".eml")
+}
+
+/// 附件写入临时目录后按普通文件提取。附件可能是包含邮件的压缩包，递归调用 `extract_sections` 需要装箱
+fn attachment_sections(attachment: &ParsedMail<'_>, name: &str) -> Pin<Box<dyn Future<Output = AppResult<Vec<Section>>> + Send>> {
+    let body = attachment.get_body_raw();
+    let name = name.to_string();
+    Box::pin(async move {
+        let dir = tempdir()?;
+        let path = dir.path().join(name);
+        std::fs::write(&path, body?)?;
+        extract_sections(&path).await
+    })
+}
+
+/// 递归遍历 MIME 结构，区分纯文本正文、HTML 正文和附件
+fn collect_parts<'a>(
+    part: &'a ParsedMail<'a>,
+    plain: &mut Vec<&'a ParsedMail<'a>>,
+    html: &mut Vec<&'a ParsedMail<'a>>,
+    attachments: &mut Vec<&'a ParsedMail<'a>>,
+) {
+    if !part.subparts.is_empty() {
+        for sub in &part.subparts {
+            collect_parts(sub, plain, html, attachments);
+        }
+        return;
+    }
+
+    let disposition = part.get_content_disposition();
+    let is_attachment = matches!(disposition.disposition, DispositionType::Attachment) || attachment_name(part).is_some();
+    match part.ctype.mimetype.as_str() {
+        "text/plain" if !is_attachment => plain.push(part),
+        "text/html" if !is_attachment => html.push(part),
+        _ if is_attachment || part.ctype.mimetype == "message/rfc822" => attachments.push(part),
+        _ => {}
+    }
+}
+
+/// 附件文件名，只保留最后一级名称，防止写到临时目录之外
+fn attachment_name(part: &ParsedMail) -> Option<String> {
+    let disposition = part.get_content_disposition();
+    let name = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned()
+        .or_else(|| (part.ctype.mimetype == "message/rfc822").then(|| "message.eml".to_string()))?;
+    Path::new(&name)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .filter(|n| !n.is_empty())
+}
+
+/// 按 mbox 格式拆分邮件（以空行后 "From " 开头的行作为分隔），单封 eml 原样返回
+fn split_mbox(bytes: &[u8]) -> Vec<Vec<u8>> {
+    if !bytes.starts_with(b"From ") {
+        return vec![bytes.to_vec()];
+    }
+
+    let mut messages = Vec::new();
+    let mut current: Option<Vec<u8>> = None;
+    // 分隔行前面必须是空行，避免把正文中未转义的 "From " 当成新邮件
+    let mut after_blank = true;
+    for line in bytes.split_inclusive(|b| *b == b'\n') {
+        if after_blank && line.starts_with(b"From ") {
+            if let Some(message) = current.take() {
+                messages.push(message);
+            }
+            current = Some(Vec::new());
+            continue;
+        }
+        after_blank = line.iter().all(u8::is_ascii_whitespace);
+        if let Some(message) = current.as_mut() {
+            // mboxrd 转义：">From " 还原为 "From "
+            let unescaped = line.iter().position(|b| *b != b'>').filter(|&n| n > 0 && line[n..].starts_with(b"From "));
+            match unescaped {
+                Some(_) => message.extend_from_slice(&line[1..]),
+                None => message.extend_from_slice(line),
+            }
+        }
+    }
+    if let Some(message) = current {
+        messages.push(message);
+    }
+    messages.retain(|m| !m.iter().all(u8::is_ascii_whitespace));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MBOX: &str = "From alice@example.com Mon Jan  6 10:00:00 2025\r
+From: Alice <alice@example.com>\r
+To: team@example.com\r
+Subject: =?UTF-8?B?5ZGo5oql?=\r
+Date: Mon, 6 Jan 2025 10:00:00 +0800\r
+MIME-Version: 1.0\r
+Content-Type: multipart/mixed; boundary=\"outer\"\r
+\r
+--outer\r
+Content-Type: multipart/alternative; boundary=\"inner\"\r
+\r
+--inner\r
+Content-Type: text/plain; charset=utf-8\r
+Content-Transfer-Encoding: quoted-printable\r
+\r
+=E6=9C=AC=E5=91=A8=E5=AE=8C=E6=88=90=E4=BA=86=E5=90=8C=E6=AD=A5\r
+>From now on we ship weekly.\r
+--inner\r
+Content-Type: text/html; charset=utf-8\r
+\r
+<p>HTML version</p>\r
+--inner--\r
+--outer\r
+Content-Type: text/plain; name=\"notes.txt\"\r
+Content-Disposition: attachment; filename=\"notes.txt\"\r
+Content-Transfer-Encoding: base64\r
+\r
+UG9ydCA4MDgw\r
+--outer--\r
+\r
+From bob@example.com Mon Jan  6 11:00:00 2025\r
+From: Bob <bob@example.com>\r
+Subject: Re\r
+Content-Type: text/html; charset=gbk\r
+Content-Transfer-Encoding: base64\r
+\r
+PHA+xOO6ww==\r
+";
+
+    #[test]
+    fn test_split_mbox() {
+        let messages = split_mbox(MBOX.as_bytes());
+        assert_eq!(messages.len(), 2);
+        assert!(String::from_utf8_lossy(&messages[0]).contains("\r\nFrom now on we ship weekly."));
+    }
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inbox.mbox");
+        std::fs::write(&path, MBOX).unwrap();
+
+        let sections = EmailProcessor::extract_sections(&path).await.unwrap();
+        assert_eq!(sections.len(), 3);
+
+        assert_eq!(sections[0].title.as_deref(), Some("周报"));
+        assert_eq!(sections[0].metadata.get("from").map(String::as_str), Some("Alice <alice@example.com>"));
+        assert!(sections[0].text.contains("本周完成了同步"));
+        assert!(!sections[0].text.contains("HTML version"));
+
+        assert_eq!(sections[1].title.as_deref(), Some("周报 > notes.txt"));
+        assert_eq!(sections[1].metadata.get("attachment").map(String::as_str), Some("notes.txt"));
+        assert_eq!(sections[1].text, "Port 8080");
+
+        assert_eq!(sections[2].title.as_deref(), Some("Re"));
+        assert!(sections[2].text.ends_with("你好"));
+    }
+
+    #[tokio::test]
+    async fn test_nested_messages() {
+        let mut message = "Subject: level 0\r\n\r\nbody 0\r\n".to_string();
+        for level in 1..=5 {
+            message = format!(
+                "Subject: level {level}\r\nContent-Type: multipart/mixed; boundary=\"b{level}\"\r\n\r\n\
+                 --b{level}\r\nContent-Type: text/plain\r\n\r\nbody {level}\r\n\
+                 --b{level}\r\nContent-Type: message/rfc822\r\n\r\n{message}--b{level}--\r\n"
+            );
+        }
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("forwarded.eml");
+        std::fs::write(&path, message).unwrap();
+
+        // 最外层的邮件和嵌套的 3 层，更深的跳过
+        let sections = EmailProcessor::extract_sections(&path).await.unwrap();
+        assert_eq!(sections.len(), 4);
+        assert!(sections[3].title.as_deref().unwrap().ends_with("level 2"));
+        assert!(sections[3].text.ends_with("body 2"));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::html::html_body_to_markdown;
+use crate::extract::package::{parent_dir, read_entry, resolve_target};
+use crate::extract::Section;
+use roxmltree::Document;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use zip::ZipArchive;
+
+pub struct EpubProcessor;
+
+impl EpubProcessor {
+    /// Extracts the chapters of an EPUB file.
+    ///
+    /// Chapters are returned in spine (reading) order. The chapter title is taken from the table of
+    /// contents (EPUB 3 nav document or EPUB 2 NCX), falling back to the first heading of the chapter,
+    /// and is kept as the section title and as the `chapter` metadata of every chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the EPUB file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing one `Section` per chapter if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<std::path::Path>>(path: T) -> AppResult<Vec<Section>> {
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+
+        let package = Package::read(&mut archive)?;
+        let toc = package.toc_titles(&mut archive)?;
+
+        let mut sections = Vec::new();
+        for chapter in &package.spine {
+            let text = html_body_to_markdown(&read_entry(&mut archive, chapter)?);
+            if text.trim().is_empty() {
+                continue;
+            }
+            let title = toc.get(chapter).cloned().or_else(|| first_heading(&text));
+            // 章节标题同时保存到元数据中，检索结果可以引用章节
+            let metadata = title.iter().map(|title| ("chapter".to_string(), title.clone())).collect();
+            sections.push(Section {
+                title,
+                text,
+                metadata,
+                ..Default::default()
+            });
+        }
+
+        if sections.is_empty() {
+            return Err(AidenErrors::Str("EPUB 中没有章节内容"));
+        }
+        Ok(sections)
+    }
+}
+
+/// OPF 中的清单和阅读顺序，路径均为压缩包内的完整路径
+struct Package {
+    spine: Vec<String>,
+    /// EPUB 3 的导航文档
+    nav: Option<String>,
+    /// EPUB 2 的 toc.ncx
+    ncx: Option<String>,
+}
+
+impl Package {
+    fn read<R: Read + Seek>(archive: &mut ZipArchive<R>) -> AppResult<Self> {
+        let container = read_entry(archive, "META-INF/container.xml")?;
+        let container = Document::parse(&container)?;
+        let opf_path = container
+            .descendants()
+            .find(|n| n.has_tag_name("rootfile"))
+            .and_then(|n| n.attribute("full-path"))
+            .ok_or(AidenErrors::Str("EPUB 缺少 OPF 文件"))?
+            .to_string();
+
+        let opf = read_entry(archive, &opf_path)?;
+        let opf = Document::parse(&opf)?;
+        let base = parent_dir(&opf_path);
+
+        let mut manifest = HashMap::new();
+        let mut nav = None;
+        for item in opf.descendants().filter(|n| n.has_tag_name("item")) {
+            let (Some(id), Some(href)) = (item.attribute("id"), item.attribute("href")) else {
+                continue;
+            };
+            let path = resolve_target(base, href);
+            if item.attribute("properties").is_some_and(|p| p.split_whitespace().any(|p| p == "nav")) {
+                nav = Some(path.clone());
+            }
+            manifest.insert(id, path);
+        }
+
+        let spine_node = opf.descendants().find(|n| n.has_tag_name("spine"));
+        let ncx = spine_node.and_then(|n| n.attribute("toc")).and_then(|id| manifest.get(id)).cloned();
+        let spine = spine_node
+            .into_iter()
+            .flat_map(|n| n.children().filter(|n| n.has_tag_name("itemref")))
+            .filter(|n| n.attribute("linear") != Some("no"))
+            .filter_map(|n| n.attribute("idref"))
+            .filter_map(|id| manifest.get(id).cloned())
+            .collect();
+
+        Ok(Self { spine, nav, ncx })
+    }
+
+    /// 目录中每个章节文件对应的标题，同一文件取目录中第一次出现的标题
+    fn toc_titles<R: Read + Seek>(&self, archive: &mut ZipArchive<R>) -> AppResult<HashMap<String, String>> {
+        let mut titles = HashMap::new();
+        if let Some(nav) = &self.nav {
+            let xml = read_entry(archive, nav)?;
+            let doc = Document::parse_with_options(&xml, xml_options())?;
+            let toc = doc
+                .descendants()
+                .filter(|n| n.has_tag_name("nav"))
+                .find(|n| n.attributes().any(|a| a.name() == "type" && a.value() == "toc"));
+            for link in toc.iter().flat_map(|n| n.descendants().filter(|n| n.has_tag_name("a"))) {
+                let title = collapse_text(link.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()));
+                if let (Some(href), false) = (link.attribute("href"), title.is_empty()) {
+                    titles.entry(resolve_target(parent_dir(nav), href)).or_insert(title);
+                }
+            }
+        } else if let Some(ncx) = &self.ncx {
+            let xml = read_entry(archive, ncx)?;
+            let doc = Document::parse_with_options(&xml, xml_options())?;
+            for point in doc.descendants().filter(|n| n.has_tag_name("navPoint")) {
+                let title = point
+                    .children()
+                    .find(|n| n.has_tag_name("navLabel"))
+                    .map(|label| collapse_text(label.descendants().filter(|n| n.is_text()).filter_map(|n| n.text())))
+                    .unwrap_or_default();
+                let src = point.children().find(|n| n.has_tag_name("content")).and_then(|n| n.attribute("src"));
+                if let (Some(src), false) = (src, title.is_empty()) {
+                    titles.entry(resolve_target(parent_dir(ncx), src)).or_insert(title);
+                }
+            }
+        }
+        Ok(titles)
+    }
+}
+
+/// 允许 XHTML 中的 DOCTYPE 声明
+fn xml_options() -> roxmltree::ParsingOptions {
+    roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    }
+}
+
+fn collapse_text<'a>(texts: impl Iterator<Item = &'a str>) -> String {
+    texts.flat_map(str::split_whitespace).collect::<Vec<_>>().join(" ")
+}
+
+fn first_heading(text: &str) -> Option<String> {
+    text.lines()
+        .find(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+    const OPF: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="2.0">
+  <manifest>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+    <item id="cover" href="text/cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="text/ch%202.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine toc="ncx">
+    <itemref idref="cover" linear="no"/>
+    <itemref idref="c1"/>
+    <itemref idref="c2"/>
+  </spine>
+</package>"#;
+
+    const NCX: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE ncx PUBLIC "-//NISO//DTD ncx 2005-1//EN" "http://www.daisy.org/z3986/2005/ncx-2005-1.dtd">
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="p1"><navLabel><text>Chapter 4: Flow Control</text></navLabel><content src="text/ch1.xhtml"/>
+      <navPoint id="p1-1"><navLabel><text>4.1 Loops</text></navLabel><content src="text/ch1.xhtml#loops"/></navPoint>
+    </navPoint>
+  </navMap>
+</ncx>"#;
+
+    const CH1: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>ch1</title></head>
+<body><h1>Flow Control</h1><p>Use loop to repeat.</p></body></html>"#;
+
+    const CH2: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><h2>Appendix</h2><p>Extra notes.</p></body></html>"#;
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("book.epub");
+
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in [
+            ("META-INF/container.xml", CONTAINER),
+            ("OEBPS/content.opf", OPF),
+            ("OEBPS/toc.ncx", NCX),
+            ("OEBPS/text/cover.xhtml", "<html><body><p>Cover</p></body></html>"),
+            ("OEBPS/text/ch1.xhtml", CH1),
+            ("OEBPS/text/ch 2.xhtml", CH2),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let sections = EpubProcessor::extract_sections(&path).await.unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title.as_deref(), Some("Chapter 4: Flow Control"));
+        assert_eq!(sections[0].metadata.get("chapter").map(String::as_str), Some("Chapter 4: Flow Control"));
+        assert!(sections[0].text.contains("Use loop to repeat."));
+        // 章节的 <title> 不作为标题
+        assert!(sections[0].text.starts_with("# Flow Control\n"));
+        assert_eq!(sections[1].title.as_deref(), Some("Appendix"));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::errors::AppResult;
+use crate::extract::text::decode_text;
+use ego_tree::NodeRef;
+use encoding_rs::Encoding;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::sync::LazyLock;
+
+/// 查找 `<meta charset>` 声明时读取的字节数，与浏览器的预扫描范围相同
+const META_SNIFF_LEN: usize = 1024;
+
+static TITLE: LazyLock<Selector> = LazyLock::new(|| Selector::parse("title").unwrap());
+
+/// 这些元素不包含正文内容（脚本、样式、导航等），转换时整体跳过
+static IGNORE: &[&str] = &[
+    "script", "style", "noscript", "template", "head", "nav", "footer", "aside", "form", "button", "iframe", "svg", "canvas",
+];
+/// 这些元素是 body 的直接子元素时为页眉等页面框架，跳过；在 article、section 中时常包含标题，保留
+static IGNORE_IN_BODY: &[&str] = &["header"];
+static IGNORE_ROLES: &[&str] = &["navigation", "banner", "contentinfo", "search", "menu"];
+
+pub struct HtmlProcessor;
+
+impl HtmlProcessor {
+    /// Extracts text from an HTML file.
+    ///
+    /// The encoding is taken from the BOM or the `<meta charset>` declaration, and detected from the
+    /// content when neither is present. Scripts, styles and navigation boilerplate are dropped. Headings, lists, tables and
+    /// preformatted blocks are kept as Markdown, and the page `<title>` becomes the top heading.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the HTML file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
+        let bytes = std::fs::read(path)?;
+        Ok(html_to_markdown(&decode_html(&bytes)))
+    }
+}
+
+/// 按 BOM 或 `<meta charset>` 声明的编码解码 HTML，都没有时检测编码
+fn decode_html(bytes: &[u8]) -> String {
+    match meta_charset(bytes) {
+        Some(encoding) if Encoding::for_bom(bytes).is_none() => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        _ => decode_text(bytes),
+    }
+}
+
+/// 在文件开头的 `<meta>` 中查找声明的编码，如 `<meta charset="gbk">` 或
+/// `<meta http-equiv="Content-Type" content="text/html; charset=gbk">`
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(META_SNIFF_LEN)]).to_ascii_lowercase();
+    head.split("<meta").skip(1).find_map(|tag| {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let value = tag[tag.find("charset")? + "charset".len()..].trim_start().strip_prefix('=')?;
+        let label = value.trim_start().trim_start_matches(['"', '\'']);
+        let label = &label[..label.find(['"', '\'', ';', ' ', '/']).unwrap_or(label.len())];
+        // 声明为 UTF-16 时按 UTF-8 处理（能读到 ASCII 的声明说明不是 UTF-16）
+        Encoding::for_label(label.as_bytes()).map(Encoding::output_encoding)
+    })
+}
+
+/// 将 HTML 转换为类 Markdown 文本，页面 `<title>` 作为一级标题放在最前面
+pub(crate) fn html_to_markdown(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let title = document
+        .select(&TITLE)
+        .next()
+        .map(|t| collapse_whitespace(&t.text().collect::<String>()))
+        .filter(|t| !t.is_empty());
+
+    let body = body_to_markdown(&document);
+
+    match title {
+        Some(title) if !body.starts_with(&format!("# {}\n", title)) => format!("# {}\n\n{}", title, body),
+        _ => body,
+    }
+}
+
+/// 将 HTML 正文转换为类 Markdown 文本，不使用页面 `<title>`。EPUB 章节的 `<title>` 多为文件名或书名
+pub(crate) fn html_body_to_markdown(html: &str) -> String {
+    body_to_markdown(&Html::parse_document(html))
+}
+
+fn body_to_markdown(document: &Html) -> String {
+    let mut writer = MarkdownWriter::default();
+    writer.walk(*document.root_element());
+    writer.finish()
+}
+
+#[derive(Default)]
+struct MarkdownWriter {
+    out: String,
+    /// 当前所在列表的序号，None 表示无序列表
+    lists: Vec<Option<usize>>,
+}
+
+impl MarkdownWriter {
+    fn walk(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            match child.value() {
+                Node::Text(text) => self.push_inline(text),
+                Node::Element(_) => {
+                    if let Some(element) = ElementRef::wrap(child) {
+                        self.element(element);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if IGNORE.contains(&name) || element.value().attr("role").is_some_and(|role| IGNORE_ROLES.contains(&role)) {
+            return;
+        }
+        if element.value().attr("aria-hidden") == Some("true") || element.value().attr("hidden").is_some() {
+            return;
+        }
+        if IGNORE_IN_BODY.contains(&name)
+            && element
+                .parent()
+                .and_then(ElementRef::wrap)
+                .is_some_and(|parent| parent.value().name() == "body")
+        {
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = collapse_whitespace(&element.text().collect::<String>());
+                if !text.is_empty() {
+                    self.block();
+                    self.out.push_str(&format!("{} {}", "#".repeat(level), text));
+                    self.block();
+                }
+            }
+            "ul" | "ol" => {
+                self.line();
+                self.lists.push((name == "ol").then_some(0));
+                self.walk(*element);
+                self.lists.pop();
+                self.line();
+            }
+            "li" => {
+                self.line();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", n)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.out.push_str(&format!("{}{}", "  ".repeat(depth), marker));
+                self.walk(*element);
+                self.line();
+            }
+            "table" => {
+                self.block();
+                self.table(element);
+                self.block();
+            }
+            "pre" => {
+                self.block();
+                self.out.push_str(&format!("```\n{}\n```", element.text().collect::<String>().trim_end()));
+                self.block();
+            }
+            "br" => self.line(),
+            "hr" => {
+                self.block();
+                self.out.push_str("---");
+                self.block();
+            }
+            "p" | "div" | "section" | "article" | "header" | "main" | "blockquote" | "figure" | "figcaption" | "dl" | "dt" | "dd" | "body" | "html" => {
+                self.block();
+                self.walk(*element);
+                self.block();
+            }
+            _ => self.walk(*element),
+        }
+    }
+
+    fn table(&mut self, table: ElementRef) {
+        let mut header_written = false;
+        for row in table.descendants().filter_map(ElementRef::wrap).filter(|e| e.value().name() == "tr") {
+            // 只处理属于当前表格的行，嵌套表格的行由单元格文本带出
+            if row.ancestors().filter_map(ElementRef::wrap).find(|e| e.value().name() == "table") != Some(table) {
+                continue;
+            }
+            let cells = row
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|e| matches!(e.value().name(), "td" | "th"))
+                .collect::<Vec<_>>();
+            if cells.is_empty() {
+                continue;
+            }
+
+            let texts = cells
+                .iter()
+                .map(|c| collapse_whitespace(&c.text().collect::<String>()).replace('|', "\\|"))
+                .collect::<Vec<_>>();
+            self.out.push_str(&format!("| {} |\n", texts.join(" | ")));
+
+            if !header_written {
+                header_written = true;
+                if cells.iter().all(|c| c.value().name() == "th") {
+                    self.out.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+                }
+            }
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        let collapsed = collapse_whitespace(text);
+        if collapsed.is_empty() {
+            if text.chars().next().is_some_and(char::is_whitespace) && !self.out.ends_with([' ', '\n']) && !self.out.is_empty() {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) && !self.out.ends_with([' ', '\n']) && !self.out.is_empty() {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    /// 结束当前行
+    fn line(&mut self) {
+        self.trim_trailing_spaces();
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    /// 结束当前块，块与块之间空一行
+    fn block(&mut self) {
+        self.line();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn trim_trailing_spaces(&mut self) {
+        let len = self.out.trim_end_matches(' ').len();
+        self.out.truncate(len);
+    }
+
+    fn finish(mut self) -> String {
+        self.trim_trailing_spaces();
+        let mut out = self.out.trim().to_string();
+        out.push('\n');
+        out
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Install Guide</title><style>body { color: red; }</style></head>
+<body>
+  <header><a href="/">Logo</a> Search</header>
+  <nav><a href="/">Home</a> &gt; <a href="/docs">Docs</a></nav>
+  <article id="content">
+    <header><h2>Requirements</h2><p>Updated 2024-05-01</p></header>
+    <p>You need <b>Rust</b>
+       and a network.</p>
+    <ul><li>Linux</li><li>Windows<ol><li>10</li><li>11</li></ol></li></ul>
+    <table>
+      <tr><th>Name</th><th>Value</th></tr>
+      <tr><td>Port</td><td>8080</td></tr>
+    </table>
+    <pre>cargo build
+cargo run</pre>
+  </article>
+  <script>alert("hi")</script>
+  <footer>Copyright</footer>
+</body>
+</html>"#;
+
+        assert_eq!(
+            html_to_markdown(html),
+            "# Install Guide\n\n## Requirements\n\nUpdated 2024-05-01\n\nYou need Rust and a network.\n\n- Linux\n- Windows\n  1. 10\n  2. 11\n\n\
+             | Name | Value |\n| --- | --- |\n| Port | 8080 |\n\n```\ncargo build\ncargo run\n```\n"
+        );
+        assert!(html_body_to_markdown(html).starts_with("## Requirements\n"));
+    }
+
+    #[test]
+    fn test_decode_html() {
+        let (gbk, _, _) = encoding_rs::GBK.encode("<html><head><meta charset=\"gb2312\"><title>安装指南</title></head><body>您好</body></html>");
+        assert_eq!(html_to_markdown(&decode_html(&gbk)), "# 安装指南\n\n您好\n");
+
+        let html = "<meta http-equiv='Content-Type' content='text/html; charset=windows-1252'><p>caf\u{e9}</p>";
+        let (latin, _, _) = encoding_rs::WINDOWS_1252.encode(html);
+        assert_eq!(meta_charset(&latin), Some(encoding_rs::WINDOWS_1252));
+        assert_eq!(decode_html(&latin), html);
+        // 没有声明时检测编码
+        assert_eq!(decode_html("<p>您好</p>".as_bytes()), "<p>您好</p>");
+    }
+}
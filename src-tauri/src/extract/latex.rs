@@ -0,0 +1,454 @@
+use crate::errors::AppResult;
+use crate::extract::{read_text, Section};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 章节命令及其层级
+static SECTIONING: &[(&str, usize)] = &[
+    ("part", 1),
+    ("chapter", 2),
+    ("section", 3),
+    ("subsection", 4),
+    ("subsubsection", 5),
+    ("paragraph", 6),
+];
+/// 连同参数一起丢弃的命令
+static DROPPED_COMMANDS: &[&str] = &[
+    "label",
+    "ref",
+    "eqref",
+    "pageref",
+    "cite",
+    "citep",
+    "citet",
+    "includegraphics",
+    "documentclass",
+    "usepackage",
+    "bibliographystyle",
+    "bibliography",
+    "newcommand",
+    "renewcommand",
+    "providecommand",
+    "newenvironment",
+    "renewenvironment",
+    "setlength",
+    "setcounter",
+    "vspace",
+    "hspace",
+    "input",
+    "include",
+    "hypersetup",
+    "pagestyle",
+    "thispagestyle",
+    "graphicspath",
+];
+/// 内容整体丢弃的环境：公式和注释
+static DROPPED_ENVIRONMENTS: &[&str] = &[
+    "equation",
+    "equation*",
+    "align",
+    "align*",
+    "gather",
+    "gather*",
+    "multline",
+    "multline*",
+    "eqnarray",
+    "eqnarray*",
+    "displaymath",
+    "comment",
+    "tikzpicture",
+];
+/// 内容原样保留的环境
+static VERBATIM_ENVIRONMENTS: &[&str] = &["verbatim", "verbatim*", "lstlisting", "minted"];
+/// 作为元数据保存的导言区信息
+static PREAMBLE_KEYS: &[(&str, &str)] = &[("title", "title"), ("author", "author"), ("date", "date")];
+
+pub struct LatexProcessor;
+
+impl LatexProcessor {
+    /// Extracts a LaTeX document as plain text split at its sectioning commands.
+    ///
+    /// Sections are titled with their heading path, e.g. `Method > Data collection`. Macros are removed
+    /// while the text of formatting commands (`\emph{..}`, `\textbf{..}`, ...) is kept; references,
+    /// citations, display math and comments are dropped, verbatim environments are kept unchanged and
+    /// list items become `- ` lines. `\title`, `\author` and `\date` of the preamble are kept as metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the tex file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        Ok(latex_to_sections(&read_text(path)?))
+    }
+}
+
+fn latex_to_sections(source: &str) -> Vec<Section> {
+    let source = strip_comments(source);
+    let (preamble, body) = match source.split_once("\\begin{document}") {
+        Some((preamble, body)) => (preamble, body.split("\\end{document}").next().unwrap_or(body)),
+        None => ("", source.as_str()),
+    };
+
+    let mut metadata = HashMap::new();
+    for (command, key) in PREAMBLE_KEYS {
+        if let Some(value) = command_argument(preamble, command).map(|arg| plain_text(&arg)).filter(|v| !v.is_empty()) {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+
+    let mut converter = Converter::default();
+    converter.convert(body);
+    converter.finish();
+
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut sections = Vec::new();
+    for (heading, text) in converter.blocks {
+        if let Some((level, title)) = heading {
+            path.retain(|(l, _)| *l < level);
+            path.push((level, title));
+        }
+        let text = collapse_blank_lines(&text);
+        if text.is_empty() {
+            continue;
+        }
+        sections.push(Section {
+            title: (!path.is_empty()).then(|| path.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>().join(" > ")),
+            text,
+            metadata: metadata.clone(),
+            ..Default::default()
+        });
+    }
+    sections
+}
+
+/// 去掉 `%` 开始的注释，`\%` 不是注释
+fn strip_comments(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| {
+            let mut escaped = false;
+            for (idx, c) in line.char_indices() {
+                match c {
+                    '%' if !escaped => return &line[..idx],
+                    '\\' => escaped = !escaped,
+                    _ => escaped = false,
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 导言区中命令的第一个参数，如 `\title{...}`
+fn command_argument(source: &str, command: &str) -> Option<String> {
+    let pattern = format!("\\{}", command);
+    let mut from = 0;
+    while let Some(idx) = source[from..].find(&pattern) {
+        let rest = &source[from + idx + pattern.len()..];
+        // 跳过前缀相同的其他命令，如 \titlepage
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let rest = rest.trim_start();
+            let rest = if rest.starts_with('[') {
+                rest.find(']').map(|i| &rest[i + 1..])?.trim_start()
+            } else {
+                rest
+            };
+            return rest.starts_with('{').then(|| group(rest).0.to_string());
+        }
+        from += idx + pattern.len();
+    }
+    None
+}
+
+/// 以 `{` 开始的分组，返回分组内容和分组之后的文本
+fn group(text: &str) -> (&str, &str) {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '\\' => {
+                escaped = !escaped;
+                continue;
+            }
+            '{' if !escaped => depth += 1,
+            '}' if !escaped => {
+                depth -= 1;
+                if depth == 0 {
+                    return (&text[1..idx], &text[idx + 1..]);
+                }
+            }
+            _ => {}
+        }
+        escaped = false;
+    }
+    (&text[1.min(text.len())..], "")
+}
+
+/// 转换一段 LaTeX 为纯文本，用于标题和元数据
+fn plain_text(source: &str) -> String {
+    let mut converter = Converter::default();
+    converter.convert(source);
+    converter.text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 连续的空行合并为一个，每行去掉首尾空白
+fn collapse_blank_lines(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[derive(Default)]
+struct Converter {
+    /// 当前章节的文本
+    text: String,
+    /// 已结束的章节：(标题层级和标题, 文本)
+    blocks: Vec<(Option<(usize, String)>, String)>,
+    heading: Option<(usize, String)>,
+}
+
+impl Converter {
+    fn convert(&mut self, mut rest: &str) {
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '\\' => rest = self.command(&rest[1..]),
+                '{' | '}' => rest = &rest[1..],
+                '$' => {
+                    // 行内公式原样保留，$$...$$ 为行间公式，丢弃
+                    if let Some(math) = rest.strip_prefix("$$") {
+                        rest = math.find("$$").map(|i| &math[i + 2..]).unwrap_or("");
+                    } else {
+                        let end = rest[1..].find('$').map(|i| i + 2).unwrap_or(rest.len());
+                        self.text.push_str(&rest[..end]);
+                        rest = &rest[end..];
+                    }
+                }
+                '~' => {
+                    self.text.push(' ');
+                    rest = &rest[1..];
+                }
+                '&' => {
+                    // 表格的列分隔
+                    self.text.truncate(self.text.trim_end_matches(' ').len());
+                    self.text.push_str(" | ");
+                    rest = rest[1..].trim_start_matches(' ');
+                }
+                _ => {
+                    self.text.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+    }
+
+    /// 处理 `\` 之后的命令，返回命令之后的文本
+    fn command<'a>(&mut self, rest: &'a str) -> &'a str {
+        let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        if name_len == 0 {
+            // 转义字符和 \\ 换行
+            let Some(c) = rest.chars().next() else {
+                return rest;
+            };
+            match c {
+                '\\' => {
+                    // 表格的行尾，之后的空白和横线不再换行
+                    self.text.truncate(self.text.trim_end_matches(' ').len());
+                    self.text.push('\n');
+                    return rest[1..].trim_start();
+                }
+                '[' => return rest.find("\\]").map(|i| &rest[i + 2..]).unwrap_or(""),
+                ',' | ';' | ' ' => self.text.push(' '),
+                _ => self.text.push(c),
+            }
+            return &rest[c.len_utf8()..];
+        }
+        let name = &rest[..name_len];
+        let mut rest = &rest[name_len..];
+        let starred = rest.starts_with('*');
+        if starred {
+            rest = &rest[1..];
+        }
+
+        match name {
+            "begin" | "end" => {
+                let (env, after) = next_group(rest);
+                if name == "begin" {
+                    if let Some(body) = self.environment(env, after) {
+                        return body;
+                    }
+                }
+                self.text.push('\n');
+                // 表格的列格式
+                if name == "begin" && (env.starts_with("tabular") || env == "array") {
+                    return next_group(skip_options(after)).1;
+                }
+                after
+            }
+            "item" => {
+                self.text.truncate(self.text.trim_end().len());
+                self.text.push_str("\n- ");
+                skip_options(rest).trim_start()
+            }
+            "par" | "newline" => {
+                self.text.push('\n');
+                rest
+            }
+            "hline" | "toprule" | "midrule" | "bottomrule" => rest.trim_start(),
+            "footnote" => {
+                let (arg, rest) = next_group(skip_options(rest));
+                self.text.push_str(" (");
+                self.convert(arg);
+                self.text.push(')');
+                rest
+            }
+            "href" => {
+                let (_, rest) = next_group(rest);
+                let (label, rest) = next_group(rest);
+                self.convert(label);
+                rest
+            }
+            _ if SECTIONING.iter().any(|(n, _)| *n == name) => {
+                let level = SECTIONING.iter().find(|(n, _)| *n == name).map(|(_, l)| *l).unwrap_or(3);
+                let (title, rest) = next_group(skip_options(rest));
+                self.finish();
+                self.heading = Some((level, plain_text(title)));
+                rest
+            }
+            _ if DROPPED_COMMANDS.contains(&name) => {
+                // 引用前的空格（`文本~\cite{..}`）一起去掉
+                self.text.truncate(self.text.trim_end_matches(' ').len());
+                // \newcommand{\name}[1]{...} 这类命令的参数都丢弃
+                let mut rest = skip_options(rest);
+                while rest.trim_start().starts_with('{') {
+                    rest = skip_options(next_group(rest).1);
+                }
+                rest
+            }
+            _ => {
+                // 其余命令保留参数中的文本，如 \emph{..}、\textbf{..}
+                skip_options(rest)
+            }
+        }
+    }
+
+    /// 处理环境，需要特殊处理的返回环境结束之后的文本
+    fn environment<'a>(&mut self, env: &str, rest: &'a str) -> Option<&'a str> {
+        let end = format!("\\end{{{}}}", env);
+        if DROPPED_ENVIRONMENTS.contains(&env) {
+            return Some(rest.find(&end).map(|i| &rest[i + end.len()..]).unwrap_or(""));
+        }
+        if VERBATIM_ENVIRONMENTS.contains(&env) {
+            let (body, after) = rest.split_once(&end).unwrap_or((rest, ""));
+            // minted 的第一个参数是语言
+            let body = if env == "minted" {
+                next_group(skip_options(body)).1
+            } else {
+                skip_options(body)
+            };
+            self.text.push('\n');
+            self.text.push_str(body.trim_matches('\n'));
+            self.text.push('\n');
+            return Some(after);
+        }
+        None
+    }
+
+    /// 结束当前章节
+    fn finish(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        self.blocks.push((self.heading.take(), text));
+    }
+}
+
+/// 跳过空白后的分组，没有分组时返回空内容
+fn next_group(rest: &str) -> (&str, &str) {
+    let trimmed = rest.trim_start();
+    if trimmed.starts_with('{') {
+        group(trimmed)
+    } else {
+        ("", rest)
+    }
+}
+
+/// 跳过可选参数 `[...]`
+fn skip_options(rest: &str) -> &str {
+    let trimmed = rest.trim_start();
+    match trimmed.strip_prefix('[') {
+        Some(options) => options.find(']').map(|i| &options[i + 1..]).unwrap_or(rest),
+        None => rest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("paper.tex");
+        std::fs::write(
+            &path,
+            r"\documentclass[11pt]{article}
+\usepackage{amsmath}
+\newcommand{\kiln}[1]{\textbf{#1}}
+\title{Kiln \emph{Temperature} Control}
+\author{R\&D Lab}
+\begin{document}
+\maketitle
+Preface text.
+
+\section{Introduction}\label{sec:intro}
+The \emph{kiln} runs at $T \le 450$~degrees~\cite{smith2020}. % internal note
+Growth is 5\% per year\footnote{Measured in 2023.}.
+
+\subsection*{Data collection}
+\begin{itemize}
+  \item Sensors sampled every \textbf{10 s}.
+  \item See \href{https://example.com}{the manual}.
+\end{itemize}
+\begin{equation}
+  E = mc^2
+\end{equation}
+\begin{verbatim}
+read_sensor(0x1F)
+\end{verbatim}
+
+\section{Results}
+\begin{tabular}{ll}
+Zone & Temp \\ \hline
+A & 440 \\
+\end{tabular}
+\end{document}
+",
+        )
+        .unwrap();
+
+        let sections = LatexProcessor::extract_sections(&path).await.unwrap();
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![None, Some("Introduction"), Some("Introduction > Data collection"), Some("Results")]
+        );
+        assert_eq!(sections[0].text, "Preface text.");
+        assert_eq!(
+            sections[1].text,
+            "The kiln runs at $T \\le 450$ degrees.\nGrowth is 5% per year (Measured in 2023.)."
+        );
+        assert_eq!(sections[2].text, "- Sensors sampled every 10 s.\n- See the manual.\n\nread_sensor(0x1F)");
+        assert_eq!(sections[3].text, "Zone | Temp\nA | 440");
+        assert_eq!(sections[1].metadata.get("title").map(String::as_str), Some("Kiln Temperature Control"));
+        assert_eq!(sections[1].metadata.get("author").map(String::as_str), Some("R&D Lab"));
+    }
+}
@@ -0,0 +1,239 @@
+//! 单个文件的提取限制：超出限制的文件跳过并记录原因，避免个别文件拖住整个同步流程
+
+use crate::errors::{AidenErrors, AppResult};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// 当前生效的限制，启动时从设置表加载
+static LIMITS: LazyLock<RwLock<ExtractLimits>> = LazyLock::new(Default::default);
+
+tokio::task_local! {
+    /// 正在提取的文件的截止时间
+    static DEADLINE: Deadline;
+}
+
+/// 每个文件的提取限制，为 0 的项不限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExtractLimits {
+    /// 文件大小上限（字节）
+    pub max_file_size: u64,
+    /// PDF 页数上限
+    pub max_pages: usize,
+    /// 提取出的字符数上限
+    pub max_chars: usize,
+    /// 提取超时（秒）。只限制读取和解析文件，之后的切分和向量化耗时与文本长度成正比，
+    /// 已由 `max_chars` 限制，不计入超时
+    pub timeout_secs: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 200 * 1024 * 1024,
+            max_pages: 2000,
+            max_chars: 5_000_000,
+            timeout_secs: 120,
+        }
+    }
+}
+
+impl ExtractLimits {
+    /// 在设置表中的键
+    pub const SETTINGS_KEY: &'static str = "extract_limits";
+
+    /// 当前生效的限制
+    pub fn current() -> Self {
+        *LIMITS.read().unwrap()
+    }
+
+    /// 替换当前生效的限制，之后开始提取的文件使用新的限制
+    pub fn apply(self) {
+        *LIMITS.write().unwrap() = self;
+    }
+
+    /// 提取超时，不限制时为 None
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout_secs > 0).then(|| Duration::from_secs(self.timeout_secs))
+    }
+
+    /// 检查文件大小，在读取文件之前调用
+    pub fn check_file_size(&self, path: &Path) -> AppResult<()> {
+        let size = path.metadata()?.len();
+        if exceeds(size, self.max_file_size) {
+            return Err(exceeded(
+                path,
+                format!("文件大小 {} 超过上限 {}", format_size(size), format_size(self.max_file_size)),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 检查 PDF 页数
+    pub fn check_pages(&self, path: &Path, pages: usize) -> AppResult<()> {
+        if exceeds(pages as u64, self.max_pages as u64) {
+            return Err(exceeded(path, format!("页数 {} 超过上限 {}", pages, self.max_pages)));
+        }
+        Ok(())
+    }
+
+    /// 检查提取出的字符数
+    pub fn check_chars(&self, path: &Path, text: &str) -> AppResult<()> {
+        // 字节数不超过上限时字符数也不会超过，不必逐个计数
+        if text.len() <= self.max_chars {
+            return Ok(());
+        }
+        let chars = text.chars().count();
+        if exceeds(chars as u64, self.max_chars as u64) {
+            return Err(exceeded(path, format!("字符数 {} 超过上限 {}", chars, self.max_chars)));
+        }
+        Ok(())
+    }
+
+    /// 提取超时的错误
+    pub fn timed_out(&self, path: &Path) -> AidenErrors {
+        exceeded(path, format!("提取超过 {} 秒", self.timeout_secs))
+    }
+
+    /// 从现在开始计算的提取截止时间，不限制超时时为 None
+    pub fn deadline(&self) -> Option<Deadline> {
+        self.timeout().map(|timeout| Deadline {
+            at: Instant::now() + timeout,
+            limits: *self,
+        })
+    }
+}
+
+/// 提取的截止时间。超时后只能取消异步部分，同步解析的线程无法强制结束，
+/// 长时间运行的解析（如逐页解析 PDF、逐个提取压缩包成员）需要用 `Deadline::check` 检查后自行结束
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: Instant,
+    limits: ExtractLimits,
+}
+
+impl Deadline {
+    /// 当前提取任务的截止时间，不限制超时或不在提取任务中时为 None。
+    /// 截止时间不会传递到 rayon 等其他线程，需要先取出再传入
+    pub fn current() -> Option<Self> {
+        DEADLINE.try_with(|deadline| *deadline).ok()
+    }
+
+    /// 超过截止时间时返回 `AidenErrors::LimitExceeded`
+    pub fn check(&self, path: &Path) -> AppResult<()> {
+        if Instant::now() >= self.at {
+            return Err(self.limits.timed_out(path));
+        }
+        Ok(())
+    }
+
+    /// 在截止时间内运行提取，期间 `Deadline::current` 返回此截止时间；超时后在下一次让出执行权时取消
+    pub(crate) async fn run<T>(deadline: Option<Self>, path: &Path, extract: impl Future<Output = AppResult<T>>) -> AppResult<T> {
+        let Some(deadline) = deadline else {
+            return extract.await;
+        };
+        let extract = tokio::time::timeout_at(deadline.at.into(), extract);
+        match DEADLINE.scope(deadline, extract).await {
+            Ok(result) => result,
+            Err(_) => Err(deadline.limits.timed_out(path)),
+        }
+    }
+}
+
+fn exceeds(value: u64, limit: u64) -> bool {
+    limit > 0 && value > limit
+}
+
+fn exceeded(path: &Path, reason: String) -> AidenErrors {
+    AidenErrors::LimitExceeded(path.display().to_string(), reason)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_limits() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("big.txt");
+        std::fs::write(&path, "字".repeat(100)).unwrap();
+
+        let limits = ExtractLimits {
+            max_file_size: 200,
+            max_pages: 10,
+            max_chars: 50,
+            timeout_secs: 0,
+        };
+        match limits.check_file_size(&path) {
+            Err(AidenErrors::LimitExceeded(file, reason)) => {
+                assert_eq!(file, path.display().to_string());
+                assert_eq!(reason, "文件大小 300 B 超过上限 200 B");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(limits.check_pages(&path, 10).is_ok());
+        assert!(limits.check_pages(&path, 11).is_err());
+        // 按字符计数，300 字节的 100 个汉字超过 50 个字符的上限
+        assert!(limits.check_chars(&path, &"字".repeat(50)).is_ok());
+        assert!(matches!(
+            limits.check_chars(&path, &"字".repeat(51)),
+            Err(AidenErrors::LimitExceeded(_, reason)) if reason == "字符数 51 超过上限 50"
+        ));
+        assert_eq!(limits.timeout(), None);
+        assert_eq!(format_size(200 * 1024 * 1024), "200.0 MB");
+
+        // 为 0 的项不限制
+        let unlimited = ExtractLimits {
+            max_file_size: 0,
+            max_pages: 0,
+            max_chars: 0,
+            timeout_secs: 0,
+        };
+        assert!(unlimited.check_file_size(&path).is_ok());
+        assert!(unlimited.check_pages(&path, usize::MAX).is_ok());
+        assert!(unlimited.check_chars(&path, &"字".repeat(100)).is_ok());
+        assert!(unlimited.deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let path = Path::new("slow.pdf");
+        let limits = ExtractLimits {
+            timeout_secs: 1,
+            ..Default::default()
+        };
+        assert!(Deadline::current().is_none());
+
+        // 同步解析在检查点发现超时后自行结束
+        let result = Deadline::run(limits.deadline(), path, async {
+            let deadline = Deadline::current().unwrap();
+            deadline.check(path)?;
+            std::thread::sleep(Duration::from_millis(1100));
+            deadline.check(path)
+        })
+        .await;
+        assert!(matches!(result, Err(AidenErrors::LimitExceeded(_, reason)) if reason == "提取超过 1 秒"));
+
+        // 异步部分超时后取消
+        let result = Deadline::run(limits.deadline(), path, async {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(AidenErrors::LimitExceeded(_, _))));
+
+        assert_eq!(Deadline::run(None, path, async { Ok(1) }).await.unwrap(), 1);
+    }
+}
@@ -1,12 +1,74 @@
-use crate::errors::AppResult;
-use lopdf::{Document, Object};
-use rayon::iter::IntoParallelIterator;
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::limits::{Deadline, ExtractLimits};
+use crate::extract::ocr::{OcrProcessor, OCR_KEY};
+use crate::extract::Section;
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::encryption::{self, DecryptionError};
+use lopdf::xobject::PdfImage;
+use lopdf::{decode_text_string, Dictionary, Document, Encoding, Object, ObjectId, ObjectStream};
 use rayon::iter::ParallelIterator;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefMutIterator};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+/// 字号至少是正文字号的多少倍才可能是标题
+const HEADING_RATIO: f32 = 1.15;
+/// 标题行的最大字符数，更长的文本块即使字号较大也按正文处理
+const MAX_HEADING_CHARS: usize = 80;
+/// 按字号识别的标题层级数
+const MAX_HEADING_LEVEL: usize = 3;
+/// 作为章节元数据保存的文档信息
+static INFO_KEYS: &[(&[u8], &str)] = &[(b"Title", "title"), (b"Author", "author"), (b"Subject", "subject")];
+/// 加载时对象流改用这个类型，避免 lopdf 在解密之前解析（加密的对象流无法解析，会被直接丢弃）
+const DEFERRED_OBJECT_STREAM: &[u8] = b"AidenObjStm";
+
+/// 与 lopdf 的加载过滤函数签名相同
+type FilterFunc = fn((u32, u16), &mut Object) -> Option<((u32, u16), Object)>;
+
+/// 补足密码到 32 字节使用的固定字节
+const PAD: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F,
+    0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+/// 字符串和流使用的加密算法
+#[derive(Clone, Copy)]
+enum Cipher {
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+/// 已登记的密码，键为同步的文件或目录，目录下的 PDF 文件都使用该密码
+static PASSWORDS: LazyLock<RwLock<HashMap<PathBuf, String>>> = LazyLock::new(Default::default);
 
 pub struct LoPdfProcessor;
 
 impl LoPdfProcessor {
-    /// Extracts text from a PDF file.
+    /// Registers the password of an encrypted PDF file, or of every PDF file under a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The synced file or directory.
+    /// * `password` - The user or owner password.
+    pub fn register_password<T: AsRef<Path>>(path: T, password: String) {
+        PASSWORDS.write().unwrap().insert(path.as_ref().to_path_buf(), password);
+    }
+
+    /// Removes the password registered for `path`.
+    pub fn unregister_password<T: AsRef<Path>>(path: T) {
+        PASSWORDS.write().unwrap().remove(path.as_ref());
+    }
+
+    /// Extracts text from a PDF file. Pages without a text layer (scans) are recognized with OCR
+    /// when the OCR models are loaded.
     ///
     /// # Arguments
     ///
@@ -17,21 +79,700 @@ impl LoPdfProcessor {
     /// Returns a `Result` containing the extracted text as a `String` if successful,
     /// or an `Error` if an error occurred during the extraction process.
     pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
-        let doc = Document::load_filtered(path, filter_func).await?;
-        let pages = doc
-            .get_pages()
-            .into_par_iter()
-            .map(|(page_num, _): (u32, _)| {
-                doc.extract_text(&[page_num])
-                    .unwrap_or_default()
-                    .split('\n')
-                    .map(|s| s.trim_end().to_string())
-                    .collect::<String>()
+        let doc = load(path.as_ref(), filter_func).await?;
+        let pages = read_pages(path.as_ref(), &doc).await?;
+        Ok(layout(&pages, &[]).text)
+    }
+
+    /// Extracts a PDF file as sections that keep the page boundaries.
+    ///
+    /// The document is split at the entries of its outline (bookmarks). Without an outline, lines set
+    /// noticeably larger than the body text are treated as headings, the larger the font the higher the
+    /// level. Every section is titled with its heading path, e.g. `3 Installation > 3.2 Network`, and
+    /// carries Title/Author/Subject of the document info as metadata. `Section::pages` records where
+    /// every page starts, so each chunk can be traced back to its page range. Sections containing pages
+    /// recognized with OCR are marked with the `ocr` metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the PDF file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<std::path::Path>>(path: T) -> AppResult<Vec<Section>> {
+        let doc = load(path.as_ref(), filter_func).await?;
+        let pages = read_pages(path.as_ref(), &doc).await?;
+        let metadata = read_info(&doc);
+
+        let outline = doc.get_toc().map(|toc| toc.toc).unwrap_or_default();
+        let pdf_text = if outline.is_empty() {
+            layout(&pages, &heading_sizes(&pages))
+        } else {
+            let mut pdf_text = layout(&pages, &[]);
+            pdf_text.headings = outline
+                .into_iter()
+                .filter_map(|entry| pdf_text.locate(entry.page as u32, entry.level, entry.title))
+                .collect();
+            pdf_text.headings.sort_by_key(|heading| heading.offset);
+            pdf_text
+        };
+        Ok(pdf_text.into_sections(&metadata))
+    }
+}
+
+/// 加载 PDF。加密的文件先尝试空密码（只设置了权限密码），再使用登记的密码，
+/// 都无法打开时返回 `AidenErrors::PasswordRequired`；页数超过提取限制时返回 `AidenErrors::LimitExceeded`
+async fn load(path: &Path, filter: FilterFunc) -> AppResult<Document> {
+    let mut doc = Document::load_filtered(path, filter).await?;
+    if doc.is_encrypted() {
+        let password = path.ancestors().find_map(|p| PASSWORDS.read().unwrap().get(p).cloned());
+        let mut result = decrypt(&mut doc, "");
+        if let (Err(_), Some(password)) = (&result, password) {
+            result = decrypt(&mut doc, &password);
+        }
+        match result {
+            Ok(()) => {}
+            Err(lopdf::Error::Decryption(DecryptionError::IncorrectPassword)) => {
+                return Err(AidenErrors::PasswordRequired(path.display().to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    expand_object_streams(&mut doc, filter);
+    ExtractLimits::current().check_pages(path, doc.get_pages().len())?;
+    Ok(doc)
+}
+
+/// 解密全部对象。lopdf 自带的 `decrypt` 要求存在 /CF（RC4 加密的旧文档没有），
+/// 不处理字典和数组中的字符串（如书签标题），也不支持 AES-256（V5）
+fn decrypt(doc: &mut Document, password: &str) -> lopdf::Result<()> {
+    let encrypted = doc.get_encrypted()?;
+    let (key, cipher) = if encrypted.get(b"V").and_then(Object::as_i64).ok() == Some(5) {
+        (aes256_file_key(encrypted, password.as_bytes())?, Cipher::Aes256)
+    } else {
+        let is_aes = encrypted
+            .get(b"CF")
+            .and_then(Object::as_dict)
+            .and_then(|cf| cf.get(b"StdCF"))
+            .and_then(Object::as_dict)
+            .and_then(|std_cf| std_cf.get(b"CFM"))
+            .and_then(Object::as_name)
+            .is_ok_and(|cfm| cfm == b"AESV2");
+        (standard_key(doc, password)?, if is_aes { Cipher::Aes128 } else { Cipher::Rc4 })
+    };
+    let metadata_encrypted = encrypted.get(b"EncryptMetadata").and_then(Object::as_bool).unwrap_or(true);
+    let encrypt_id = doc.trailer.get(b"Encrypt").and_then(Object::as_reference).ok();
+
+    for (id, object) in doc.objects.iter_mut() {
+        if Some(*id) == encrypt_id || (!metadata_encrypted && object.type_name().ok() == Some(b"Metadata")) {
+            continue;
+        }
+        decrypt_object(&key, *id, object, cipher);
+    }
+    doc.trailer.remove(b"Encrypt");
+    Ok(())
+}
+
+/// 计算 RC4 和 AES-128 加密（R2–R4）的文件密钥。`password` 不是用户密码时按所有者密码处理，
+/// 先用它从 /O 解出用户密码再计算
+fn standard_key(doc: &Document, password: &str) -> lopdf::Result<Vec<u8>> {
+    match encryption::get_encryption_key(doc, password, true) {
+        Err(DecryptionError::IncorrectPassword) => {
+            let encrypted = doc.get_encrypted()?;
+            let revision = encrypted.get(b"R").and_then(Object::as_i64)?;
+            let key_len = match revision {
+                2 => 5,
+                _ => encrypted.get(b"Length").and_then(Object::as_i64).unwrap_or(40) as usize / 8,
+            };
+            let owner_hash = encrypted.get(b"O").and_then(Object::as_str)?;
+            let key = owner_key(revision, key_len, password.as_bytes());
+            let user_password = match revision {
+                2 => rc4(&key, owner_hash),
+                // R3 起加密 /O 时用密钥依次异或 0..=19 做了 20 次 RC4，解密时倒序进行
+                _ => (0..20u8)
+                    .rev()
+                    .fold(owner_hash.to_vec(), |data, i| rc4(&key.iter().map(|b| b ^ i).collect::<Vec<_>>(), &data)),
+            };
+            Ok(encryption::get_encryption_key(doc, user_password, true)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// 由所有者密码计算加密 /O 使用的 RC4 密钥
+fn owner_key(revision: i64, key_len: usize, password: &[u8]) -> Vec<u8> {
+    let mut digest = md5::compute(pad_password(password)).0;
+    if revision >= 3 {
+        for _ in 0..50 {
+            digest = md5::compute(digest).0;
+        }
+    }
+    digest[..key_len.min(16)].to_vec()
+}
+
+/// 密码截断或用固定的字节补足到 32 字节
+fn pad_password(password: &[u8]) -> Vec<u8> {
+    let len = password.len().min(32);
+    [&password[..len], &PAD[..32 - len]].concat()
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state = (0..=255u8).collect::<Vec<_>>();
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|b| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            b ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+/// 计算 AES-256 加密（V5，R5/R6）的文件密钥。依次按用户密码和所有者密码校验 /U、/O，
+/// 通过后用对应的哈希解出 /UE 或 /OE 中的文件密钥
+fn aes256_file_key(encrypted: &Dictionary, password: &[u8]) -> lopdf::Result<Vec<u8>> {
+    let revision = encrypted.get(b"R").and_then(Object::as_i64)?;
+    // 密码为 UTF-8，只使用前 127 字节
+    let password = &password[..password.len().min(127)];
+    let user = encrypted.get(b"U").and_then(Object::as_str)?;
+    let owner = encrypted.get(b"O").and_then(Object::as_str)?;
+    if user.len() < 48 || owner.len() < 48 {
+        return Err(DecryptionError::InvalidType.into());
+    }
+    // /U、/O 由 32 字节哈希、8 字节校验盐和 8 字节密钥盐组成，所有者密码的哈希还包含 /U
+    for (hash, key_name, udata) in [(user, b"UE", &[][..]), (owner, b"OE", &user[..48])] {
+        if aes256_hash(revision, password, &hash[32..40], udata) != hash[..32] {
+            continue;
+        }
+        let mut file_key = encrypted.get(key_name).and_then(Object::as_str)?.to_vec();
+        if file_key.len() != 32 {
+            return Err(DecryptionError::InvalidType.into());
+        }
+        let intermediate = aes256_hash(revision, password, &hash[40..48], udata);
+        Aes256CbcDec::new(intermediate.as_slice().into(), &[0; 16].into())
+            .decrypt_padded_mut::<NoPadding>(&mut file_key)
+            .map_err(|_| DecryptionError::InvalidType)?;
+        return Ok(file_key);
+    }
+    Err(DecryptionError::IncorrectPassword.into())
+}
+
+/// 计算 AES-256 加密的密码哈希。R5 为一次 SHA-256，R6 在此基础上反复做 AES-128 加密和
+/// SHA-256/384/512 哈希（ISO 32000-2 算法 2.B），至少 64 轮
+fn aes256_hash(revision: i64, password: &[u8], salt: &[u8], udata: &[u8]) -> Vec<u8> {
+    let mut hash = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(udata)
+        .finalize()
+        .to_vec();
+    if revision < 6 {
+        return hash;
+    }
+    let mut round = 0;
+    loop {
+        let mut data = [password, &hash, udata].concat().repeat(64);
+        let len = data.len();
+        Aes128CbcEnc::new(hash[..16].into(), hash[16..32].into())
+            .encrypt_padded_mut::<NoPadding>(&mut data, len)
+            .expect("the data is a multiple of the block size");
+        hash = match data[..16].iter().map(|b| *b as usize).sum::<usize>() % 3 {
+            0 => Sha256::digest(&data).to_vec(),
+            1 => Sha384::digest(&data).to_vec(),
+            _ => Sha512::digest(&data).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && data[len - 1] as usize <= round - 32 {
+            break;
+        }
+    }
+    hash.truncate(32);
+    hash
+}
+
+/// 递归解密字符串和流，同一个间接对象中的字符串使用该对象的编号计算密钥（AES-256 直接使用文件密钥）
+fn decrypt_object(key: &[u8], id: ObjectId, object: &mut Object, cipher: Cipher) {
+    match object {
+        Object::Array(items) => items.iter_mut().for_each(|item| decrypt_object(key, id, item, cipher)),
+        Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| decrypt_object(key, id, value, cipher)),
+        _ => {
+            let content = match &*object {
+                Object::String(content, _) => content,
+                Object::Stream(stream) => &stream.content,
+                _ => return,
+            };
+            // AES 密文由 16 字节的 IV 和若干 16 字节的块组成，不符合的内容 lopdf 解密时会 panic
+            if !matches!(cipher, Cipher::Rc4) && (content.len() < 32 || content.len() % 16 != 0) {
+                return;
+            }
+            let decrypted = match cipher {
+                Cipher::Aes256 => aes256_decrypt(key, content),
+                _ => encryption::decrypt_object(key, id, object, matches!(cipher, Cipher::Aes128)).ok(),
+            };
+            let Some(decrypted) = decrypted else {
+                return;
+            };
+            match object {
+                Object::Stream(stream) => stream.set_content(decrypted),
+                Object::String(content, _) => *content = decrypted,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// 用文件密钥解密 AES-256 加密的内容，开头 16 字节为 IV，之后为 PKCS#7 填充的密文
+fn aes256_decrypt(key: &[u8], content: &[u8]) -> Option<Vec<u8>> {
+    let (iv, data) = content.split_at(16);
+    let mut data = data.to_vec();
+    let len = Aes256CbcDec::new(key.into(), iv.into())
+        .decrypt_padded_mut::<Pkcs7>(&mut data)
+        .ok()?
+        .len();
+    data.truncate(len);
+    Some(data)
+}
+
+/// 展开加载时推迟解析的对象流，对象流中的对象不会覆盖同编号的顶层对象
+fn expand_object_streams(doc: &mut Document, filter: FilterFunc) {
+    let ids = doc
+        .objects
+        .iter()
+        .filter(|(_, object)| object.type_name().ok() == Some(DEFERRED_OBJECT_STREAM))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    for id in ids {
+        let Some(Object::Stream(mut stream)) = doc.objects.remove(&id) else {
+            continue;
+        };
+        match ObjectStream::new(&mut stream) {
+            Ok(object_stream) => {
+                for (object_id, mut object) in object_stream.objects {
+                    if let Some((object_id, object)) = filter(object_id, &mut object) {
+                        doc.objects.entry(object_id).or_insert(object);
+                    }
+                }
+            }
+            Err(e) => log::warn!("Skip object stream {:?}: {}", id, e),
+        }
+    }
+}
+
+/// 一行文本和其中最大的字号
+#[derive(Debug, Default)]
+struct TextLine {
+    text: String,
+    size: f32,
+}
+
+struct PdfPage {
+    /// 页码，从 1 开始
+    page_num: u32,
+    lines: Vec<TextLine>,
+    /// 文本来自 OCR
+    ocr: bool,
+}
+
+/// 标题在全文中的字节位置、层级（从 1 开始）和标题文本
+#[derive(Debug)]
+struct Heading {
+    offset: usize,
+    level: usize,
+    title: String,
+}
+
+/// 整个文档的文本，页与页之间以空行分隔
+#[derive(Debug, Default)]
+struct PdfText {
+    text: String,
+    /// 每页的起始字节位置和页码
+    pages: Vec<(usize, u32)>,
+    headings: Vec<Heading>,
+    /// OCR 识别的页码
+    ocr_pages: Vec<u32>,
+}
+
+impl PdfText {
+    /// 书签对应的标题位置：在书签指向的页中查找标题文本，找不到时取该页的开头
+    fn locate(&self, page_num: u32, level: usize, title: String) -> Option<Heading> {
+        let idx = self.pages.iter().position(|(_, num)| *num >= page_num)?;
+        let start = self.pages[idx].0;
+        let end = self.pages.get(idx + 1).map(|(offset, _)| *offset).unwrap_or(self.text.len());
+        let title = title.trim().to_string();
+        let offset = match self.text[start..end].find(&title) {
+            Some(pos) if !title.is_empty() => start + pos,
+            _ => start,
+        };
+        Some(Heading { offset, level, title })
+    }
+
+    /// 在标题处切分为章节，章节标题为从顶层到当前标题的路径
+    fn into_sections(self, metadata: &HashMap<String, String>) -> Vec<Section> {
+        let mut sections = Vec::new();
+        let mut path: Vec<(usize, String)> = Vec::new();
+        let mut start = 0;
+        let mut title = None;
+        for heading in &self.headings {
+            self.push_section(start, heading.offset, title.take(), metadata, &mut sections);
+            path.retain(|(level, _)| *level < heading.level);
+            path.push((heading.level, heading.title.clone()));
+            title = Some(path.iter().map(|(_, title)| title.as_str()).collect::<Vec<_>>().join(" > "));
+            start = heading.offset;
+        }
+        self.push_section(start, self.text.len(), title, metadata, &mut sections);
+        sections
+    }
+
+    fn push_section(&self, start: usize, end: usize, title: Option<String>, metadata: &HashMap<String, String>, sections: &mut Vec<Section>) {
+        let text = &self.text[start..end];
+        if text.trim().is_empty() {
+            return;
+        }
+        // 章节开始所在的页，以及章节中间开始的各页
+        let first = self.pages.iter().rev().find(|(offset, _)| *offset <= start).map(|(_, num)| (0, *num));
+        let rest = self
+            .pages
+            .iter()
+            .filter(|(offset, _)| *offset > start && *offset < end)
+            .map(|(offset, num)| (offset - start, *num));
+        let pages = first.into_iter().chain(rest).collect::<Vec<_>>();
+        let mut metadata = metadata.clone();
+        if pages.iter().any(|(_, num)| self.ocr_pages.contains(num)) {
+            metadata.insert(OCR_KEY.to_string(), "true".to_string());
+        }
+        sections.push(Section {
+            title,
+            text: text.to_string(),
+            metadata,
+            pages,
+        });
+    }
+}
+
+/// 并行读取每一页的文本行。没有文本层的页（扫描件）在 OCR 模型已加载时识别页面中的图片，
+/// 仍然没有文本的页跳过。没有 OCR 模型且所有页都没有文本层时返回 `AidenErrors::OcrUnavailable`
+async fn read_pages(path: &Path, doc: &Document) -> AppResult<Vec<PdfPage>> {
+    // 每页开始前检查是否超时，超时后不再解析剩下的页
+    let deadline = Deadline::current();
+    let check_deadline = || deadline.map_or(Ok(()), |deadline| deadline.check(path));
+    let mut pages = doc
+        .get_pages()
+        .into_par_iter()
+        .map(|(page_num, page_id): (u32, ObjectId)| {
+            check_deadline()?;
+            Ok(PdfPage {
+                page_num,
+                lines: read_lines(doc, page_id).unwrap_or_default(),
+                ocr: false,
             })
-            .collect::<Vec<_>>();
+        })
+        .collect::<AppResult<Vec<_>>>()?;
 
-        Ok(pages.join(""))
+    let scanned = pages.iter().filter(|page| page.lines.is_empty()).count();
+    if scanned > 0 && !OcrProcessor::is_available() {
+        if scanned == pages.len() {
+            return Err(AidenErrors::OcrUnavailable(path.display().to_string()));
+        }
+        log::warn!("OCR unavailable, {} of {} pages skipped in {}", scanned, pages.len(), path.display());
+    } else if scanned > 0 {
+        // 加载时过滤掉了图片，重新完整加载
+        let full = load(path, keep_images).await?;
+        let page_ids = full.get_pages();
+        pages.par_iter_mut().filter(|page| page.lines.is_empty()).try_for_each(|page| {
+            check_deadline()?;
+            if let Some(page_id) = page_ids.get(&page.page_num) {
+                page.lines = ocr_lines(&full, *page_id);
+                page.ocr = !page.lines.is_empty();
+            }
+            AppResult::Ok(())
+        })?;
     }
+    pages.retain(|page| !page.lines.is_empty());
+    Ok(pages)
+}
+
+/// 识别页面中的图片，每行文本的字号记为 0，不参与标题识别
+fn ocr_lines(doc: &Document, page_id: ObjectId) -> Vec<TextLine> {
+    let images = doc.get_page_images(page_id).unwrap_or_default();
+    let mut lines = Vec::new();
+    for image in images.iter().filter_map(|image| decode_image(doc, image)) {
+        match OcrProcessor::recognize(&image) {
+            Ok(text) => lines.extend(text.lines().map(str::trim).filter(|line| !line.is_empty()).map(|line| TextLine {
+                text: line.to_string(),
+                size: 0.0,
+            })),
+            Err(e) => log::warn!("OCR failed on page {:?}: {}", page_id, e),
+        }
+    }
+    lines
+}
+
+/// 解码扫描页常见的图片：JPEG（DCTDecode）、CCITT 传真编码的黑白图片，以及未压缩或 Flate 压缩的
+/// 8 位灰度、RGB 图片。JBIG2 没有可用的解码器，跳过并记录日志
+fn decode_image(doc: &Document, image: &PdfImage) -> Option<DynamicImage> {
+    let filters = image.filters.as_deref().unwrap_or_default();
+    if filters.iter().any(|filter| filter == "DCTDecode") {
+        return image::load_from_memory_with_format(image.content, ImageFormat::Jpeg).ok();
+    }
+    if filters.iter().any(|filter| filter == "JBIG2Decode") {
+        log::warn!("JBIG2 image {:?} is not supported, skipped", image.id);
+        return None;
+    }
+    if filters == ["CCITTFaxDecode"] {
+        let decoded = decode_ccitt(doc, image);
+        if decoded.is_none() {
+            log::warn!("Failed to decode CCITT image {:?}", image.id);
+        }
+        return decoded;
+    }
+    if image.bits_per_component != Some(8) || !filters.iter().all(|filter| filter == "FlateDecode") {
+        return None;
+    }
+    let data = if filters.is_empty() {
+        image.content.to_vec()
+    } else {
+        doc.get_object(image.id).and_then(Object::as_stream).ok()?.decompressed_content().ok()?
+    };
+    let (width, height) = (u32::try_from(image.width).ok()?, u32::try_from(image.height).ok()?);
+    match image.color_space.as_deref() {
+        Some("DeviceGray") => GrayImage::from_raw(width, height, data).map(DynamicImage::ImageLuma8),
+        Some("DeviceRGB") => RgbImage::from_raw(width, height, data).map(DynamicImage::ImageRgb8),
+        _ => None,
+    }
+}
+
+/// 解码 CCITT 传真编码的图片：K < 0 为 G4，否则按带 EOL 的 G3 一维编码解码。
+/// 按 BlackIs1 和 Decode 数组换算成灰度，黑色为 0
+fn decode_ccitt(doc: &Document, image: &PdfImage) -> Option<DynamicImage> {
+    let params = match image.origin_dict.get(b"DecodeParms").and_then(|params| doc.dereference(params)) {
+        Ok((_, Object::Dictionary(params))) => Some(params),
+        Ok((_, Object::Array(params))) => params
+            .first()
+            .and_then(|params| doc.dereference(params).ok())
+            .and_then(|(_, params)| params.as_dict().ok()),
+        _ => None,
+    };
+    let param = |key: &[u8]| params.and_then(|params| params.get(key).ok());
+    let k = param(b"K").and_then(|k| k.as_i64().ok()).unwrap_or(0);
+    let columns = u16::try_from(param(b"Columns").and_then(|columns| columns.as_i64().ok()).unwrap_or(1728)).ok()?;
+    let rows = u16::try_from(param(b"Rows").and_then(|rows| rows.as_i64().ok()).unwrap_or(image.height)).ok()?;
+    let black_is_1 = param(b"BlackIs1").and_then(|black| black.as_bool().ok()).unwrap_or(false);
+    // Decode 为 [1 0] 时取值反转
+    let inverted = image
+        .origin_dict
+        .get(b"Decode")
+        .and_then(Object::as_array)
+        .is_ok_and(|decode| decode.first().and_then(|value| value.as_float().ok()) == Some(1.0));
+
+    let mut data = Vec::with_capacity(usize::from(columns) * usize::from(rows));
+    let mut height = 0;
+    let push_line = |line: &[u16]| {
+        height += 1;
+        data.extend(fax::decoder::pels(line, columns).map(|color| {
+            // 采样值为 1 的像素在默认 Decode 下显示为白色
+            let sample = (color == fax::Color::Black) == black_is_1;
+            if sample != inverted {
+                255
+            } else {
+                0
+            }
+        }));
+    };
+    let input = image.content.iter().copied();
+    if k < 0 {
+        fax::decoder::decode_g4(input, columns, Some(rows), push_line)?;
+    } else {
+        fax::decoder::decode_g3(input, push_line)?;
+    }
+    GrayImage::from_raw(u32::from(columns), height, data).map(DynamicImage::ImageLuma8)
+}
+
+/// 解析页面内容流，按换行操作（Td/TD 的纵向移动、T*、'、"、ET）拆分文本行，
+/// 并记录每行的有效字号（Tf 的字号乘以 Tm 的纵向缩放）
+fn read_lines(doc: &Document, page_id: ObjectId) -> AppResult<Vec<TextLine>> {
+    let encodings = doc
+        .get_page_fonts(page_id)?
+        .into_iter()
+        .filter_map(|(name, font)| font.get_font_encoding(doc).ok().map(|encoding| (name, encoding)))
+        .collect::<BTreeMap<_, _>>();
+    let content = doc.get_and_decode_page_content(page_id)?;
+
+    let mut lines = Vec::new();
+    let mut line = TextLine::default();
+    let mut encoding = None;
+    let mut font_size = 0.0;
+    let mut scale = 1.0;
+    let mut matrix_y = None;
+    for operation in &content.operations {
+        let operands = &operation.operands;
+        let new_line = match operation.operator.as_str() {
+            "BT" => {
+                scale = 1.0;
+                matrix_y = None;
+                false
+            }
+            "Tf" => {
+                encoding = operands.first().and_then(|font| font.as_name().ok()).and_then(|font| encodings.get(font));
+                font_size = operands.get(1).and_then(|size| size.as_float().ok()).unwrap_or(font_size);
+                false
+            }
+            "Tm" => {
+                let values = operands.iter().filter_map(|o| o.as_float().ok()).collect::<Vec<_>>();
+                if let [_, _, c, d, _, f] = values[..] {
+                    scale = c.hypot(d);
+                    let moved = matrix_y.is_some_and(|y| y != f);
+                    matrix_y = Some(f);
+                    moved
+                } else {
+                    false
+                }
+            }
+            "Td" | "TD" => operands.get(1).and_then(|ty| ty.as_float().ok()).is_some_and(|ty| ty != 0.0),
+            "T*" | "'" | "\"" | "ET" => true,
+            _ => false,
+        };
+        if new_line && !line.text.trim().is_empty() {
+            lines.push(std::mem::take(&mut line));
+        } else if new_line {
+            line = TextLine::default();
+        }
+
+        if matches!(operation.operator.as_str(), "Tj" | "TJ" | "'" | "\"") {
+            if let Some(encoding) = encoding {
+                let len = line.text.len();
+                collect_text(&mut line.text, encoding, operands);
+                if line.text.len() > len {
+                    line.size = line.size.max(font_size * scale);
+                }
+            }
+        }
+    }
+    if !line.text.trim().is_empty() {
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// 与 lopdf 的 `extract_text` 相同：TJ 中较大的负数间距视为空格
+fn collect_text(text: &mut String, encoding: &Encoding, operands: &[Object]) {
+    for operand in operands {
+        match operand {
+            Object::String(bytes, _) => text.push_str(&Document::decode_text(encoding, bytes).unwrap_or_default()),
+            Object::Array(array) => {
+                collect_text(text, encoding, array);
+                text.push(' ');
+            }
+            Object::Integer(_) | Object::Real(_) if operand.as_float().is_ok_and(|n| n < -100.0) => text.push(' '),
+            _ => {}
+        }
+    }
+}
+
+/// 字号按 0.5 分档，避免浮点误差
+fn size_key(size: f32) -> u32 {
+    (size * 2.0).round() as u32
+}
+
+/// 标题的字号档位，从大到小排列。正文字号取字符数最多的字号
+fn heading_sizes(pages: &[PdfPage]) -> Vec<u32> {
+    let mut chars: HashMap<u32, usize> = HashMap::new();
+    for line in pages.iter().filter(|page| !page.ocr).flat_map(|page| &page.lines) {
+        *chars.entry(size_key(line.size)).or_default() += line.text.chars().count();
+    }
+    let Some(body) = chars
+        .iter()
+        .max_by_key(|(size, count)| (**count, std::cmp::Reverse(**size)))
+        .map(|(size, _)| *size)
+    else {
+        return vec![];
+    };
+
+    let mut sizes = chars
+        .into_keys()
+        .filter(|size| *size as f32 >= body as f32 * HEADING_RATIO)
+        .collect::<Vec<_>>();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    sizes.truncate(MAX_HEADING_LEVEL);
+    sizes
+}
+
+/// 拼接全文。正文各行直接相连（与按页提取时一致），标题单独成行；
+/// 连续的同级标题行（换行的长标题）合并为一个标题
+fn layout(pages: &[PdfPage], heading_sizes: &[u32]) -> PdfText {
+    let mut pdf_text = PdfText::default();
+    for page in pages {
+        if !pdf_text.text.is_empty() {
+            pdf_text.text.push_str("\n\n");
+        }
+        pdf_text.pages.push((pdf_text.text.len(), page.page_num));
+        if page.ocr {
+            // OCR 的文本保留原有的换行，没有字号信息
+            pdf_text.ocr_pages.push(page.page_num);
+            for line in &page.lines {
+                pdf_text.text.push_str(&line.text);
+                pdf_text.text.push('\n');
+            }
+            continue;
+        }
+
+        let mut after_heading = false;
+        for line in &page.lines {
+            let text = line.text.trim();
+            let level = (text.chars().count() <= MAX_HEADING_CHARS)
+                .then(|| heading_sizes.iter().position(|size| *size == size_key(line.size)))
+                .flatten()
+                .map(|idx| idx + 1);
+            match level {
+                Some(level) if after_heading && pdf_text.headings.last().is_some_and(|h| h.level == level) => {
+                    pdf_text.text.pop();
+                    pdf_text.text.push(' ');
+                    pdf_text.text.push_str(text);
+                    pdf_text.text.push('\n');
+                    let heading = pdf_text.headings.last_mut().unwrap();
+                    heading.title.push(' ');
+                    heading.title.push_str(text);
+                }
+                Some(level) => {
+                    if !pdf_text.text.is_empty() && !pdf_text.text.ends_with('\n') {
+                        pdf_text.text.push('\n');
+                    }
+                    pdf_text.headings.push(Heading {
+                        offset: pdf_text.text.len(),
+                        level,
+                        title: text.to_string(),
+                    });
+                    pdf_text.text.push_str(text);
+                    pdf_text.text.push('\n');
+                }
+                None => pdf_text.text.push_str(line.text.trim_end()),
+            }
+            after_heading = level.is_some();
+        }
+    }
+    pdf_text
+}
+
+/// 文档信息中的 Title/Author/Subject
+fn read_info(doc: &Document) -> HashMap<String, String> {
+    let Some(info) = doc.trailer.get_deref(b"Info", doc).and_then(Object::as_dict).ok() else {
+        return HashMap::new();
+    };
+    INFO_KEYS
+        .iter()
+        .filter_map(|(key, name)| {
+            let value = info.get(key).ok().and_then(|v| decode_text_string(v).ok())?;
+            let value = value.trim();
+            (!value.is_empty()).then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
 }
 
 static IGNORE: &[&[u8]] = &[
@@ -60,6 +801,7 @@ static IGNORE: &[&[u8]] = &[
 ];
 
 fn filter_func(object_id: (u32, u16), object: &mut Object) -> Option<((u32, u16), Object)> {
+    defer_object_stream(object);
     if IGNORE.contains(&object.type_name().unwrap_or_default()) {
         return None;
     }
@@ -79,9 +821,289 @@ fn filter_func(object_id: (u32, u16), object: &mut Object) -> Option<((u32, u16)
     Some((object_id, object.to_owned()))
 }
 
+/// 只推迟对象流的解析，保留图片，用于 OCR
+fn keep_images(object_id: (u32, u16), object: &mut Object) -> Option<((u32, u16), Object)> {
+    defer_object_stream(object);
+    Some((object_id, object.to_owned()))
+}
+
+fn defer_object_stream(object: &mut Object) {
+    if let Object::Stream(stream) = object {
+        if stream.dict.has_type(b"ObjStm") {
+            stream.dict.set("Type", Object::Name(DEFERRED_OBJECT_STREAM.to_vec()));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Bookmark, Stream};
+    use tempfile::tempdir;
+
+    type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+
+    /// 按 R6 生成 /U（/O）和 /UE（/OE），`salt` 为校验盐和密钥盐
+    fn aes256_entry(password: &str, salt: &[u8; 16], udata: &[u8], file_key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let hash = [aes256_hash(6, password.as_bytes(), &salt[..8], udata), salt.to_vec()].concat();
+        let intermediate = aes256_hash(6, password.as_bytes(), &salt[8..], udata);
+        let mut encrypted_key = file_key.to_vec();
+        Aes256CbcEnc::new(intermediate.as_slice().into(), &[0; 16].into())
+            .encrypt_padded_mut::<NoPadding>(&mut encrypted_key, 32)
+            .unwrap();
+        (hash, encrypted_key)
+    }
+
+    fn aes256_encrypt_object(key: &[u8], object: &mut Object) {
+        let encrypt = |content: &[u8]| {
+            let iv = [0x24; 16];
+            let mut data = content.to_vec();
+            data.resize(content.len() / 16 * 16 + 16, 0);
+            Aes256CbcEnc::new(key.into(), &iv.into())
+                .encrypt_padded_mut::<Pkcs7>(&mut data, content.len())
+                .unwrap();
+            [iv.to_vec(), data].concat()
+        };
+        match object {
+            Object::Array(items) => items.iter_mut().for_each(|item| aes256_encrypt_object(key, item)),
+            Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| aes256_encrypt_object(key, value)),
+            Object::String(content, _) => *content = encrypt(content),
+            Object::Stream(stream) => stream.set_content(encrypt(&stream.content)),
+            _ => {}
+        }
+    }
+
+    /// 生成测试用的 PDF，每页由若干 (字号, 文本) 行组成。指定 (用户密码, 修订号) 时用 RC4（40 位，R2）
+    /// 或 AES-256（R6）加密，所有者密码为 owner
+    fn write_pdf(path: &Path, pages: &[&[(i64, &str)]], bookmarks: &[(&str, usize)], encryption: Option<(&str, i64)>) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let resources_id = doc.add_object(dictionary! { "Font" => dictionary! { "F1" => font_id } });
+
+        let mut page_ids = Vec::new();
+        for lines in pages {
+            let mut operations = Vec::new();
+            for (idx, (size, text)) in lines.iter().enumerate() {
+                operations.push(Operation::new("BT", vec![]));
+                operations.push(Operation::new("Tf", vec!["F1".into(), (*size).into()]));
+                operations.push(Operation::new("Td", vec![72.into(), (760 - idx as i64 * 30).into()]));
+                operations.push(Operation::new("Tj", vec![Object::string_literal(*text)]));
+                operations.push(Operation::new("ET", vec![]));
+            }
+            let content_id = doc.add_object(Stream::new(dictionary! {}, Content { operations }.encode().unwrap()));
+            page_ids.push(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            }));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => page_ids.iter().map(|id| Object::from(*id)).collect::<Vec<_>>(),
+                "Count" => page_ids.len() as i64,
+                "Resources" => resources_id,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Furnace Manual"),
+            "Author" => Object::string_literal("Aiden"),
+        });
+        doc.trailer.set("Info", info_id);
+
+        let mut parent = None;
+        for (title, page) in bookmarks {
+            let id = doc.add_bookmark(Bookmark::new(title.to_string(), [0.0; 3], 0, page_ids[*page]), parent);
+            parent.get_or_insert(id);
+        }
+        if let Some(outline_id) = doc.build_outline() {
+            doc.get_object_mut(catalog_id).unwrap().as_dict_mut().unwrap().set("Outlines", outline_id);
+        }
+        if let Some((password, revision)) = encryption {
+            let ids = doc.objects.keys().copied().collect::<Vec<_>>();
+            doc.trailer.set("ID", vec![Object::string_literal("0123456789abcdef"); 2]);
+            if revision == 6 {
+                let file_key = [0x5A; 32];
+                let (user_hash, user_key) = aes256_entry(password, b"user-salt-16byte", &[], &file_key);
+                let (owner_hash, owner_key) = aes256_entry("owner", b"owner-salt-16byt", &user_hash, &file_key);
+                let encrypt_id = doc.add_object(dictionary! {
+                    "Filter" => "Standard",
+                    "V" => 5,
+                    "R" => 6,
+                    "Length" => 256,
+                    "CF" => dictionary! { "StdCF" => dictionary! { "CFM" => "AESV3", "Length" => 32 } },
+                    "StmF" => "StdCF",
+                    "StrF" => "StdCF",
+                    "U" => Object::string_literal(user_hash),
+                    "UE" => Object::string_literal(user_key),
+                    "O" => Object::string_literal(owner_hash),
+                    "OE" => Object::string_literal(owner_key),
+                    "P" => -4,
+                });
+                doc.trailer.set("Encrypt", encrypt_id);
+                for id in ids {
+                    aes256_encrypt_object(&file_key, doc.objects.get_mut(&id).unwrap());
+                }
+            } else {
+                let owner_hash = rc4(&owner_key(2, 5, b"owner"), &pad_password(password.as_bytes()));
+                let encrypt_id = doc.add_object(dictionary! {
+                    "Filter" => "Standard",
+                    "V" => 1,
+                    "R" => 2,
+                    "O" => Object::string_literal(owner_hash),
+                    "P" => -4,
+                });
+                doc.trailer.set("Encrypt", encrypt_id);
+                let key = encryption::get_encryption_key(&doc, password, false).unwrap();
+                let user_hash = Object::string_literal(rc4(&key, &PAD));
+                doc.get_object_mut(encrypt_id).unwrap().as_dict_mut().unwrap().set("U", user_hash);
+                // RC4 加密和解密是同一个运算
+                for id in ids {
+                    decrypt_object(&key, id, doc.objects.get_mut(&id).unwrap(), Cipher::Rc4);
+                }
+            }
+        }
+        doc.save(path).unwrap();
+    }
+
+    const BODY: &str = "The furnace must be installed on a level concrete floor with enough clearance.";
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let pages: &[&[(i64, &str)]] = &[
+            &[(24, "3 Installation"), (11, BODY), (11, BODY)],
+            &[(11, BODY), (16, "3.2 Network"), (11, BODY), (11, BODY)],
+            &[(11, BODY)],
+        ];
+
+        // 没有书签时按字号识别标题
+        let path = dir.path().join("manual.pdf");
+        write_pdf(&path, pages, &[], None);
+        let sections = LoPdfProcessor::extract_sections(&path).await.unwrap();
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(titles, vec![Some("3 Installation"), Some("3 Installation > 3.2 Network")]);
+        assert_eq!(sections[0].metadata.get("title").map(String::as_str), Some("Furnace Manual"));
+        assert_eq!(sections[0].metadata.get("author").map(String::as_str), Some("Aiden"));
+        assert_eq!(sections[0].pages, vec![(0, 1), (sections[0].text.find("\n\n").unwrap() + 2, 2)]);
+        assert!(sections[1].text.starts_with("3.2 Network\n"));
+        assert_eq!(sections[1].pages.iter().map(|(_, num)| *num).collect::<Vec<_>>(), vec![2, 3]);
+
+        // 有书签时按书签切分
+        let path = dir.path().join("outline.pdf");
+        write_pdf(&path, pages, &[("3 Installation", 0), ("3.2 Network", 1)], None);
+        let sections = LoPdfProcessor::extract_sections(&path).await.unwrap();
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(titles, vec![Some("3 Installation"), Some("3 Installation > 3.2 Network")]);
+        assert!(sections[1].text.starts_with("3.2 Network"));
+
+        let text = LoPdfProcessor::extract_text(&path).await.unwrap();
+        assert_eq!(text.matches("\n\n").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_extract_encrypted() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vendor.pdf");
+        write_pdf(&path, &[&[(11, BODY)]], &[], Some(("secret", 2)));
+
+        let result = LoPdfProcessor::extract_sections(&path).await;
+        assert!(matches!(result, Err(AidenErrors::PasswordRequired(_))));
+
+        // 为同步的目录登记密码，目录下的文件都可以打开
+        LoPdfProcessor::register_password(dir.path(), "secret".to_string());
+        let sections = LoPdfProcessor::extract_sections(&path).await.unwrap();
+        LoPdfProcessor::unregister_password(dir.path());
+        assert_eq!(sections[0].text, BODY);
+        assert_eq!(sections[0].metadata.get("title").map(String::as_str), Some("Furnace Manual"));
+
+        // 所有者密码同样可以打开
+        LoPdfProcessor::register_password(&path, "owner".to_string());
+        let sections = LoPdfProcessor::extract_sections(&path).await.unwrap();
+        LoPdfProcessor::unregister_password(&path);
+        assert_eq!(sections[0].text, BODY);
+    }
+
+    #[tokio::test]
+    async fn test_extract_aes256() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vendor.pdf");
+        write_pdf(&path, &[&[(11, BODY)]], &[], Some(("secret", 6)));
+
+        let result = LoPdfProcessor::extract_sections(&path).await;
+        assert!(matches!(result, Err(AidenErrors::PasswordRequired(_))));
+
+        for password in ["secret", "owner"] {
+            LoPdfProcessor::register_password(&path, password.to_string());
+            let sections = LoPdfProcessor::extract_sections(&path).await.unwrap();
+            LoPdfProcessor::unregister_password(&path);
+            assert_eq!(sections[0].text, BODY);
+            assert_eq!(sections[0].metadata.get("title").map(String::as_str), Some("Furnace Manual"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_scanned_without_ocr() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("scan.pdf");
+        write_pdf(&path, &[&[]], &[], None);
+        assert!(matches!(
+            LoPdfProcessor::extract_sections(&path).await,
+            Err(AidenErrors::OcrUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_ccitt() {
+        // 8x4 的黑白图片，左半边为黑色
+        let (width, height) = (8u16, 4u16);
+        let mut encoder = fax::encoder::Encoder::new(fax::VecWriter::new());
+        for _ in 0..height {
+            let pels = (0..width).map(|x| if x < 4 { fax::Color::Black } else { fax::Color::White });
+            encoder.encode_line(pels, width).unwrap();
+        }
+        let content = encoder.finish().unwrap().finish();
+
+        let doc = Document::with_version("1.5");
+        let white_is_0 = dictionary! {
+            "DecodeParms" => dictionary! { "K" => -1, "Columns" => width as i64, "Rows" => height as i64 },
+        };
+        // BlackIs1 为 true 时黑色像素的采样值为 1，需要 Decode [1 0] 才显示为黑色
+        let black_is_1 = dictionary! {
+            "DecodeParms" => dictionary! { "K" => -1, "Columns" => width as i64, "Rows" => height as i64, "BlackIs1" => true },
+            "Decode" => vec![1.into(), 0.into()],
+        };
+        let image = |origin_dict| PdfImage {
+            id: (1, 0),
+            width: width as i64,
+            height: height as i64,
+            color_space: None,
+            filters: Some(vec!["CCITTFaxDecode".to_string()]),
+            bits_per_component: Some(1),
+            content: &content,
+            origin_dict,
+        };
+        for origin_dict in [&white_is_0, &black_is_1] {
+            let decoded = decode_image(&doc, &image(origin_dict)).unwrap().to_luma8();
+            assert_eq!(decoded.dimensions(), (8, 4));
+            assert!(decoded.rows().all(|row| row.map(|pixel| pixel[0]).eq([0, 0, 0, 0, 255, 255, 255, 255])));
+        }
+
+        // JBIG2 没有解码器
+        let mut jbig2 = image(&white_is_0);
+        jbig2.filters = Some(vec!["JBIG2Decode".to_string()]);
+        assert!(decode_image(&doc, &jbig2).is_none());
+    }
 
     #[tokio::test]
     async fn test_extract_text() {
@@ -0,0 +1,239 @@
+use crate::errors::AppResult;
+use crate::extract::{read_text, Section};
+use std::path::Path;
+
+pub struct MarkdownProcessor;
+
+impl MarkdownProcessor {
+    /// Extracts a markdown file as sections following its heading hierarchy.
+    ///
+    /// A new section starts at every ATX heading (`#` to `######`) outside of fenced code blocks and
+    /// is titled with the breadcrumb of its parent headings (e.g. `安装 > Windows > 常见问题`). A heading
+    /// directly followed by a subheading does not get a section of its own. The text is kept as it is,
+    /// so code blocks and tables can be chunked as whole blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the markdown file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        Ok(markdown_sections(&read_text(path)?))
+    }
+}
+
+fn markdown_sections(text: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current = Section::default();
+    // 当前章节是否有标题行以外的内容
+    let mut has_body = false;
+    // 上级标题的 (级别, 标题)
+    let mut breadcrumb: Vec<(usize, String)> = Vec::new();
+    let mut fence: Option<String> = None;
+    for line in text.lines() {
+        if let Some(marker) = &fence {
+            if closes_fence(line, marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = fence_marker(line) {
+            fence = Some(marker);
+        } else if let Some((level, heading)) = parse_heading(line) {
+            if has_body {
+                sections.push(std::mem::take(&mut current));
+                has_body = false;
+            }
+            breadcrumb.retain(|(parent, _)| *parent < level);
+            breadcrumb.push((level, heading));
+            current.title = Some(breadcrumb.iter().map(|(_, h)| h.as_str()).collect::<Vec<_>>().join(" > "));
+            push_line(&mut current.text, line);
+            continue;
+        }
+        has_body |= !line.trim().is_empty();
+        push_line(&mut current.text, line);
+    }
+    // 文末只有标题的章节也保留，如只有一个标题的文件
+    if !current.text.trim().is_empty() {
+        sections.push(current);
+    }
+    for section in &mut sections {
+        section.text = section.text.trim_matches('\n').to_string();
+    }
+    sections
+}
+
+fn push_line(text: &mut String, line: &str) {
+    text.push_str(line.trim_end());
+    text.push('\n');
+}
+
+/// 切分 markdown 时的块
+#[derive(Debug, PartialEq)]
+pub(crate) enum MarkdownBlock {
+    /// 段落、列表、引用等，以空行或标题分隔
+    Text(String),
+    /// 围栏代码块，`fence` 为起始的围栏行（含语言），`marker` 为结束的围栏
+    Code { fence: String, marker: String, body: String },
+    /// 表格，`header` 为表头行和分隔行
+    Table { header: String, rows: Vec<String> },
+}
+
+impl MarkdownBlock {
+    /// 块的完整文本
+    pub(crate) fn render(&self) -> String {
+        match self {
+            MarkdownBlock::Text(text) => text.clone(),
+            MarkdownBlock::Code { fence, marker, body } => format!("{}\n{}\n{}", fence, body, marker),
+            MarkdownBlock::Table { header, rows } => [header.as_str()]
+                .into_iter()
+                .chain(rows.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// 把 markdown 文本分为段落、代码块和表格。标题行不在结果中，标题由章节的标题路径代替
+pub(crate) fn markdown_blocks(text: &str) -> Vec<MarkdownBlock> {
+    let lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<MarkdownBlock>| {
+        if !paragraph.is_empty() {
+            blocks.push(MarkdownBlock::Text(paragraph.join("\n")));
+            paragraph.clear();
+        }
+    };
+
+    let mut idx = 0;
+    while idx < lines.len() {
+        let line = lines[idx];
+        if let Some(marker) = fence_marker(line) {
+            flush(&mut paragraph, &mut blocks);
+            // 没有结束围栏的代码块延续到文末
+            let end = (idx + 1..lines.len()).find(|i| closes_fence(lines[*i], &marker)).unwrap_or(lines.len());
+            let body = lines[idx + 1..end].join("\n");
+            if !body.trim().is_empty() {
+                blocks.push(MarkdownBlock::Code {
+                    fence: line.trim().to_string(),
+                    marker,
+                    body,
+                });
+            }
+            idx = end + 1;
+            continue;
+        }
+        if line.contains('|') && lines.get(idx + 1).is_some_and(|next| is_table_delimiter(next)) {
+            flush(&mut paragraph, &mut blocks);
+            let end = (idx + 2..lines.len())
+                .find(|i| lines[*i].trim().is_empty() || !lines[*i].contains('|'))
+                .unwrap_or(lines.len());
+            blocks.push(MarkdownBlock::Table {
+                header: lines[idx..idx + 2].join("\n"),
+                rows: lines[idx + 2..end].iter().map(|row| row.to_string()).collect(),
+            });
+            idx = end;
+            continue;
+        }
+        if line.trim().is_empty() || parse_heading(line).is_some() {
+            flush(&mut paragraph, &mut blocks);
+        } else {
+            paragraph.push(line);
+        }
+        idx += 1;
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+/// ATX 标题：最多缩进 3 个空格，1 到 6 个 `#` 后跟空格，去掉结尾的 `#`。`#标签` 不是标题
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let heading = rest.trim().trim_end_matches('#').trim();
+    (!heading.is_empty()).then(|| (level, heading.to_string()))
+}
+
+/// 起始围栏：至少 3 个 ` 或 ~，返回结束围栏需要的字符
+fn fence_marker(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    let ch = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then(|| ch.to_string().repeat(len))
+}
+
+/// 结束围栏：只由同样的字符组成，且不短于起始围栏
+fn closes_fence(line: &str, marker: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= marker.len() && trimmed.chars().all(|c| marker.starts_with(c))
+}
+
+/// 表格的分隔行，如 `|---|:--:|` 或 `--- | ---`
+fn is_table_delimiter(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.contains('-') && trimmed.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MANUAL: &str = "烧结炉操作手册\n\n\
+                          # 安装\n\
+                          ## Windows\n\
+                          运行安装程序：\n\n\
+                          ```bash\n# 以管理员身份运行\nsetup.exe --silent\n\n./check.sh\n```\n\n\
+                          ### 常见问题 ##\n\
+                          | 错误码 | 说明 |\n|---|---|\n| E01 | 温度传感器断开 |\n| E02 | 炉门未关闭 |\n\n\
+                          #标签不是标题\n\
+                          ## Linux\n\
+                          - 下载 deb 包\n- 执行 dpkg -i\n";
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("manual.md");
+        std::fs::write(&path, MANUAL.replace('\n', "\r\n")).unwrap();
+
+        let sections = MarkdownProcessor::extract_sections(&path).await.unwrap();
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![None, Some("安装 > Windows"), Some("安装 > Windows > 常见问题"), Some("安装 > Linux")]
+        );
+        assert_eq!(sections[0].text, "烧结炉操作手册");
+        // 代码块中的注释不是标题，只有标题的上级标题并入下一章节
+        assert!(sections[1]
+            .text
+            .starts_with("# 安装\n## Windows\n运行安装程序：\n\n```bash\n# 以管理员身份运行"));
+        assert!(sections[2].text.ends_with("#标签不是标题"));
+    }
+
+    #[test]
+    fn test_markdown_blocks() {
+        let blocks = markdown_blocks(MANUAL);
+        assert_eq!(blocks[0], MarkdownBlock::Text("烧结炉操作手册".to_string()));
+        assert_eq!(blocks[1], MarkdownBlock::Text("运行安装程序：".to_string()));
+        assert_eq!(blocks[2].render(), "```bash\n# 以管理员身份运行\nsetup.exe --silent\n\n./check.sh\n```");
+        assert_eq!(
+            blocks[3],
+            MarkdownBlock::Table {
+                header: "| 错误码 | 说明 |\n|---|---|".to_string(),
+                rows: vec!["| E01 | 温度传感器断开 |".to_string(), "| E02 | 炉门未关闭 |".to_string()],
+            }
+        );
+        assert_eq!(blocks[4], MarkdownBlock::Text("#标签不是标题".to_string()));
+        assert_eq!(blocks[5], MarkdownBlock::Text("- 下载 deb 包\n- 执行 dpkg -i".to_string()));
+        assert_eq!(blocks.len(), 6);
+    }
+}
@@ -1,22 +1,53 @@
+mod archive;
+mod code;
+mod detect;
+mod doc;
 mod docx;
+mod email;
+mod epub;
+mod html;
+mod latex;
+mod limits;
 mod lopdf;
+mod markdown;
+mod notebook;
+mod ocr;
+mod odf;
+mod package;
+mod pptx;
+mod profiles;
+mod registry;
+mod rtf;
+mod spreadsheet;
+mod structured;
+mod subtitle;
+mod text;
 
-use crate::embed::text_loader::TextLoader;
-use crate::errors::{AidenErrors, AppResult};
-use crate::extract::docx::DocxRsProcessor;
-use crate::extract::lopdf::LoPdfProcessor;
+use crate::agent::RetrievalOptions;
+use crate::embed::text_loader::{join_lines, TextLoader};
+use crate::errors::AppResult;
+use crate::extract::archive::ArchiveProcessor;
+pub use crate::extract::detect::{detect, FileType};
+pub use crate::extract::limits::ExtractLimits;
+pub use crate::extract::lopdf::LoPdfProcessor;
+pub(crate) use crate::extract::markdown::{markdown_blocks, MarkdownBlock};
+pub use crate::extract::notebook::{NotebookOptions, NotebookProcessor};
+pub use crate::extract::ocr::OcrProcessor;
+pub use crate::extract::profiles::{ChunkStrategy, ChunkTokenizer, ChunkingProfile, ChunkingProfiles};
+pub use crate::extract::registry::{extract, find_extractor, register_extractor, Document, Extractor};
+pub use crate::extract::text::read_text;
 use embed_anything::config::TextEmbedConfig;
-use embed_anything::embeddings::embed::{EmbedData, EmbedImage, Embedder, TextEmbedder, VisionEmbedder};
-use embed_anything::embeddings::get_text_metadata;
-use embed_anything::file_processor::markdown_processor::MarkdownProcessor;
-use embed_anything::file_processor::txt_processor::TxtProcessor;
-use embed_anything::text_loader::SplittingStrategy;
-use std::collections::HashMap;
+use embed_anything::embeddings::embed::{EmbedData, EmbedImage, Embedder, EmbeddingResult, TextEmbedder, VisionEmbedder};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::rc::Rc;
+use std::ops::Range;
 use std::sync::Arc;
 use text_cleaner::clean::Clean;
 
+/// 语义切分时向量化句子片段的默认批大小
+const SEMANTIC_BATCH_SIZE: usize = 32;
+
+/// 向量化文件，按文件类型使用 `ChunkingProfiles` 中的切分配置，`config` 只提供批大小和语义切分的编码器
 pub async fn embed_file<T: AsRef<std::path::Path>, F>(
     file_name: T,
     embedder: &Arc<Embedder>,
@@ -28,70 +59,219 @@ where
 {
     let binding = TextEmbedConfig::default();
     let config = config.unwrap_or(&binding);
-    let chunk_size = config.chunk_size.unwrap_or(256);
-    let overlap_ratio = config.overlap_ratio.unwrap_or(0.2);
     let batch_size = config.batch_size;
-    let splitting_strategy = config.splitting_strategy.unwrap_or(SplittingStrategy::Sentence);
     let semantic_encoder = config.semantic_encoder.clone().unwrap_or(embedder.clone());
+    // 语义切分使用同一个模型时，文本块直接使用切分时计算的向量
+    let reuse_embeddings = Arc::ptr_eq(&semantic_encoder, embedder);
 
     match embedder.as_ref() {
         Embedder::Text(embedder) => {
-            emb_text(
-                file_name,
-                embedder,
-                chunk_size,
-                overlap_ratio,
-                batch_size,
-                splitting_strategy,
-                semantic_encoder,
-                adapter,
-            )
-            .await
+            let profiles = ChunkingProfiles::current();
+            emb_text(file_name, embedder, &profiles, batch_size, semantic_encoder, reuse_embeddings, adapter).await
         }
         Embedder::Vision(embedder) => Ok(Some(vec![emb_image(file_name, embedder).await?])),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
 async fn emb_text<T: AsRef<std::path::Path>, F>(
     file: T,
     embedding_model: &TextEmbedder,
-    chunk_size: usize,
-    overlap_ratio: f32,
+    profiles: &ChunkingProfiles,
     batch_size: Option<usize>,
-    splitting_strategy: SplittingStrategy,
     semantic_encoder: Arc<Embedder>,
+    reuse_embeddings: bool,
     adapter: Option<F>,
 ) -> anyhow::Result<Option<Vec<EmbedData>>>
 where
     F: Fn(Vec<EmbedData>),
 {
-    let text = extract_text(&file)
-        .await?
-        .remove_leading_spaces()
-        .remove_trailing_spaces()
-        .remove_empty_lines();
-    let textloader = TextLoader::new(chunk_size, overlap_ratio);
+    let file_type = detect(&file).ok();
+
+    let document = extract(&file).await?;
+    let mut file_metadata = TextLoader::get_metadata(&file).ok().unwrap_or_default();
+    file_metadata.extend(document.metadata);
+
+    // 按章节分别切分，文本块不会跨越章节
+    let mut chunks = Vec::new();
+    let mut metadata = Vec::new();
+    // 语义切分时已经计算的文本块向量，其余文本块为 None
+    let mut encoded_chunks = Vec::new();
+    // 每种文件类型的切分器只创建一次
+    let mut textloaders: HashMap<Option<FileType>, TextLoader> = HashMap::new();
+    // 已经记录了上级文本的 parent_id
+    let mut parents = HashSet::new();
+    // 上级文本不超过检索时的上下文预算，修改预算后重新同步的文件才按新的预算分组
+    let max_parent_tokens = RetrievalOptions::current().context_tokens;
+    let file_id = file.as_ref().to_string_lossy().to_string();
+    for (section_idx, section) in document.sections.into_iter().enumerate() {
+        let mut section_metadata = file_metadata.clone();
+        section_metadata.extend(section.metadata.clone());
+        // 压缩包中的文件按其自身的类型切分
+        let file_type = section.metadata.get("virtual_path").and_then(FileType::from_extension).or(file_type);
+        let profile = profiles.profile(file_type);
+        let chunking = match profile.strategy {
+            ChunkStrategy::Sentence => Chunking::Text,
+            ChunkStrategy::Semantic => Chunking::Semantic,
+            ChunkStrategy::Structural => file_type.map(Chunking::from).unwrap_or(Chunking::Text),
+        };
+        let textloader = textloaders
+            .entry(file_type)
+            .or_insert_with(|| TextLoader::with_tokenizer(profile.chunk_size, profile.overlap_ratio, profile.tokenizer.tokenizer()));
 
-    let chunks = textloader
-        .split_into_chunks(&text, splitting_strategy, semantic_encoder)
-        .unwrap_or_default();
+        if chunking == Chunking::Code {
+            // 源代码保留缩进，过长的定义按行切分，并记录每块实际的行号
+            let first_line = section.metadata.get("start_line").and_then(|l| l.parse::<usize>().ok()).unwrap_or(1);
+            let pieces = textloader.split_into_lines(&section.text);
+            let tokens = pieces.iter().map(|(chunk, ..)| textloader.count_tokens(chunk)).collect::<Vec<_>>();
+            for (group_idx, group) in group_chunks(&tokens, max_parent_tokens).into_iter().enumerate() {
+                // 上级文本是组内文本块所在的连续行
+                let (first, last) = (pieces[group.start].1, pieces[group.end - 1].2);
+                let has_parent = group.len() > 1;
+                for (chunk, start, end) in &pieces[group] {
+                    let mut chunk_metadata = section_metadata.clone();
+                    chunk_metadata.insert("start_line".to_string(), (first_line + start).to_string());
+                    chunk_metadata.insert("end_line".to_string(), (first_line + end).to_string());
+                    if has_parent {
+                        let parent_id = format!("{}#{}.{}", file_id, section_idx, group_idx);
+                        link_parent(&mut chunk_metadata, &mut parents, parent_id, || {
+                            section.label(section.text.lines().skip(first).take(last - first + 1).collect::<Vec<_>>().join("\n"))
+                        });
+                    }
+                    metadata.push(chunk_metadata);
+                    chunks.push(section.label(chunk.clone()));
+                    encoded_chunks.push(None);
+                }
+            }
+            continue;
+        }
+
+        // markdown 保留原文的换行和缩进，代码块和表格按整块切分
+        let section = if chunking == Chunking::Markdown { section } else { section.cleaned() };
+        let (section_chunks, section_encoded) = match chunking {
+            Chunking::Rows => (textloader.split_into_rows(&section.text).unwrap_or_default(), Vec::new()),
+            Chunking::Markdown => (textloader.split_markdown(&section.text).unwrap_or_default(), Vec::new()),
+            Chunking::Semantic => {
+                let batch_size = batch_size.unwrap_or(SEMANTIC_BATCH_SIZE);
+                let semantic_chunks = textloader.split_semantic(&section.text, semantic_encoder.clone(), batch_size).await?;
+                semantic_chunks
+                    .into_iter()
+                    .map(|chunk| (chunk.text, reuse_embeddings.then_some(chunk.embedding)))
+                    .unzip()
+            }
+            _ => (textloader.split_into_chunks(&section.text).unwrap_or_default(), Vec::new()),
+        };
+        let mut section_encoded = section_encoded.into_iter();
+        // 文本块在章节中的字节区间，用于计算页码范围和截取上级文本
+        let ranges = locate_chunks(&join_lines(&section.text), &section_chunks);
+        let tokens = section_chunks.iter().map(|chunk| textloader.count_tokens(chunk)).collect::<Vec<_>>();
+        let groups = group_chunks(&tokens, max_parent_tokens);
+        let group_of = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, group)| group.clone().map(move |_| idx))
+            .collect::<Vec<_>>();
+        // 组内文本块在原文中的连续片段，定位不到时把文本块接起来
+        let group_text = |group: &Range<usize>| match (ranges[group.start], ranges[group.end - 1]) {
+            (Some((start, _)), Some((_, end))) if start < end => section.text[start..end].to_string(),
+            _ => section_chunks[group.clone()].join("\n"),
+        };
+        // 只有一个文本块的章节不需要上级文本
+        let has_parent = section_chunks.len() > 1;
+        let mut page_tokens = HashMap::new();
+        for (idx, (chunk, range)) in section_chunks.iter().zip(&ranges).enumerate() {
+            let mut chunk_metadata = section_metadata.clone();
+            // 有页码表的章节（PDF）记录每个文本块所在的页码范围
+            let pages = range.and_then(|(start, end)| section.page_range(start, end));
+            if let Some((first, last)) = pages {
+                chunk_metadata.insert("page_start".to_string(), first.to_string());
+                chunk_metadata.insert("page_end".to_string(), last.to_string());
+            }
+            // 有页码的文本块的上级文本是它所在的页，页超出上下文预算时与其余文本块一样，
+            // 按相邻的文本块分组，只有一个文本块的组不需要上级文本
+            let page_text = pages
+                .and_then(|(first, last)| Some((first, last, section.page_text(first, last)?)))
+                .filter(|(first, last, text)| *page_tokens.entry((*first, *last)).or_insert_with(|| textloader.count_tokens(text)) <= max_parent_tokens);
+            let group = &groups[group_of[idx]];
+            match page_text {
+                Some((first, last, page_text)) if has_parent => {
+                    let parent_id = format!("{}#{}:{}-{}", file_id, section_idx, first, last);
+                    link_parent(&mut chunk_metadata, &mut parents, parent_id, || section.label(page_text.to_string()));
+                }
+                _ if group.len() > 1 => {
+                    let parent_id = format!("{}#{}.{}", file_id, section_idx, group_of[idx]);
+                    link_parent(&mut chunk_metadata, &mut parents, parent_id, || section.label(group_text(group)));
+                }
+                _ => {}
+            }
+            metadata.push(chunk_metadata);
+            chunks.push(section.label(chunk.clone()));
+            encoded_chunks.push(section_encoded.next().flatten());
+        }
+    }
 
-    let metadata = TextLoader::get_metadata(file).ok();
+    // 只向量化切分时没有计算向量的文本块
+    let unencoded = chunks
+        .iter()
+        .zip(&encoded_chunks)
+        .filter(|(_, encoded)| encoded.is_none())
+        .map(|(chunk, _)| chunk.clone())
+        .collect::<Vec<_>>();
+    let mut encodings = if unencoded.is_empty() {
+        Vec::new()
+    } else {
+        embedding_model.embed(&unencoded, batch_size).await?
+    }
+    .into_iter();
+    let embeddings = chunks
+        .into_iter()
+        .zip(encoded_chunks)
+        .zip(metadata)
+        .filter_map(|((chunk, encoded), metadata)| {
+            let encoding = match encoded {
+                Some(embedding) => EmbeddingResult::DenseVector(embedding),
+                None => encodings.next()?,
+            };
+            Some(EmbedData::new(encoding, Some(chunk), Some(metadata)))
+        })
+        .collect::<Vec<_>>();
 
     if let Some(adapter) = adapter {
-        let encodings = embedding_model.embed(&chunks, batch_size).await?;
-        let embeddings = get_text_metadata(&Rc::new(encodings), &chunks, &metadata)?;
         adapter(embeddings);
         Ok(None)
     } else {
-        let encodings = embedding_model.embed(&chunks, batch_size).await?;
-        let embeddings = get_text_metadata(&Rc::new(encodings), &chunks, &metadata)?;
-
         Ok(Some(embeddings))
     }
 }
 
+/// 把相邻的文本块按顺序分组作为上级文本，`tokens` 为每个文本块的 token 数，每组合计不超过 `max_tokens`，
+/// 超出的单个文本块自成一组。相邻文本块的重叠部分重复计算，组内原文的实际 token 数只会更少
+fn group_chunks(tokens: &[usize], max_tokens: usize) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    let mut group_tokens = 0;
+    for (idx, tokens) in tokens.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if group_tokens + tokens <= max_tokens => {
+                group.end = idx + 1;
+                group_tokens += tokens;
+            }
+            _ => {
+                groups.push(idx..idx + 1);
+                group_tokens = *tokens;
+            }
+        }
+    }
+    groups
+}
+
+/// 记录文本块的上级文本：每个文本块保存 `parent_id`，同一上级文本只在第一个文本块中保存 `parent_text`，
+/// 写入时移到 `file_parents` 表，见 `FileParentRecordFields`
+fn link_parent<F: FnOnce() -> String>(metadata: &mut HashMap<String, String>, parents: &mut HashSet<String>, parent_id: String, text: F) {
+    if parents.insert(parent_id.clone()) {
+        metadata.insert("parent_text".to_string(), text());
+    }
+    metadata.insert("parent_id".to_string(), parent_id);
+}
+
 async fn emb_image<T: AsRef<std::path::Path>>(image_path: T, embedding_model: &VisionEmbedder) -> anyhow::Result<EmbedData> {
     let mut metadata = HashMap::new();
     metadata.insert("file_name".to_string(), fs::canonicalize(&image_path)?.to_str().unwrap().to_string());
@@ -100,16 +280,170 @@ async fn emb_image<T: AsRef<std::path::Path>>(image_path: T, embedding_model: &V
     Ok(embedding.clone())
 }
 
+/// 文档中的一个章节，切分时不会跨越章节边界
+#[derive(Debug, Default, Clone)]
+pub struct Section {
+    /// 章节标题，会附加到该章节的每个文本块前
+    pub title: Option<String>,
+    pub text: String,
+    /// 章节元数据（如邮件头），保存到该章节的每个文本块中
+    pub metadata: HashMap<String, String>,
+    /// 页码表：每页在 text 中的起始字节位置和页码，按位置升序排列，没有分页的格式为空
+    pub pages: Vec<(usize, u32)>,
+}
+
+impl Section {
+    /// 在文本块前加上章节标题，已经以标题开头的文本块保持不变
+    fn label(&self, chunk: String) -> String {
+        match &self.title {
+            Some(title) if !chunk.trim_start_matches(['#', ' ']).starts_with(title.as_str()) => format!("{}\n{}", title, chunk),
+            _ => chunk,
+        }
+    }
+
+    /// 清理多余的空白。有页码表时逐页清理并重建页码表，保证页码表与清理后的文本对应
+    fn cleaned(&self) -> Section {
+        let clean = |text: String| text.remove_leading_spaces().remove_trailing_spaces().remove_empty_lines();
+        if self.pages.is_empty() {
+            return Section {
+                text: clean(self.text.clone()),
+                ..self.clone()
+            };
+        }
+
+        let mut section = Section {
+            text: String::new(),
+            pages: Vec::new(),
+            ..self.clone()
+        };
+        for (idx, (start, page_num)) in self.pages.iter().enumerate() {
+            let end = self.pages.get(idx + 1).map(|(next, _)| *next).unwrap_or(self.text.len());
+            let text = clean(self.text[*start..end].to_string());
+            if text.trim().is_empty() {
+                continue;
+            }
+            if !section.text.is_empty() {
+                section.text.push_str("\n\n");
+            }
+            section.pages.push((section.text.len(), *page_num));
+            section.text.push_str(str::trim(&text));
+        }
+        section
+    }
+
+    /// 第 first 页到第 last 页的文本
+    fn page_text(&self, first: u32, last: u32) -> Option<&str> {
+        let start = self.pages.iter().find(|(_, page_num)| *page_num == first)?.0;
+        let end = self
+            .pages
+            .iter()
+            .find(|(_, page_num)| *page_num > last)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.text.len());
+        Some(self.text[start..end].trim())
+    }
+
+    /// 字节区间 [start, end) 跨越的首页和末页
+    fn page_range(&self, start: usize, end: usize) -> Option<(u32, u32)> {
+        let page_at = |offset: usize| {
+            self.pages
+                .iter()
+                .rev()
+                .find(|(page_start, _)| *page_start <= offset)
+                .map(|(_, page_num)| *page_num)
+        };
+        Some((page_at(start)?, page_at(end.saturating_sub(1).max(start))?))
+    }
+}
+
+/// 在文本中依次定位文本块，返回每个文本块的字节区间。按句子和按语义切分得到的文本块都是原文的连续片段，
+/// 找不到的为 None。相邻的文本块可能重叠，因此从上一个文本块的起始位置之后开始查找
+fn locate_chunks(text: &str, chunks: &[String]) -> Vec<Option<(usize, usize)>> {
+    let mut from = 0;
+    chunks
+        .iter()
+        .map(|chunk| {
+            let start = from + text.get(from..)?.find(chunk.as_str())?;
+            from = start + chunk.chars().next().map(char::len_utf8).unwrap_or(1);
+            Some((start, start + chunk.len()))
+        })
+        .collect()
+}
+
+/// 按章节提取文本，不区分章节的格式返回一个无标题的章节
+pub async fn extract_sections<T: AsRef<std::path::Path>>(file: &T) -> AppResult<Vec<Section>> {
+    Ok(extract(file).await?.sections)
+}
+
+/// 压缩包中的每个文件分别提取，章节标题前加上文件在压缩包中的路径，
+/// 元数据中的 `file_name` 和 `virtual_path` 为虚拟路径（如 `bundle.zip!/docs/intro.md`）
+async fn extract_archive_sections<T: AsRef<std::path::Path>>(file: &T) -> AppResult<Vec<Section>> {
+    let archive = ArchiveProcessor::expand(file)?;
+    let mut sections = Vec::new();
+    for member in &archive.members {
+        // 嵌套的压缩包已经展开，成员不会再是压缩包
+        let document = match Box::pin(extract(&member.path)).await {
+            Ok(document) => document,
+            Err(e) => {
+                log::warn!("Skip {}: {}", member.virtual_path, e);
+                continue;
+            }
+        };
+        for mut section in document.sections {
+            for (key, value) in &document.metadata {
+                section.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            section.title = Some(match section.title {
+                Some(title) => format!("{} > {}", member.name, title),
+                None => member.name.clone(),
+            });
+            section.metadata.insert("file_name".to_string(), member.virtual_path.clone());
+            section.metadata.insert("virtual_path".to_string(), member.virtual_path.clone());
+            sections.push(section);
+        }
+    }
+    Ok(sections)
+}
+
+/// 按文件内容识别类型后提取文本，无法识别的文件返回 `AidenErrors::UnsupportedFileType`
 pub async fn extract_text<T: AsRef<std::path::Path>>(file: &T) -> AppResult<String> {
-    if !file.as_ref().exists() {
-        return Err(AidenErrors::Str("文件找不到"));
+    Ok(extract(file).await?.text)
+}
+
+/// 文本块的切分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chunking {
+    /// 按句子切分
+    Text,
+    /// 按语义切分，文本块的向量在切分时计算
+    Semantic,
+    /// 表格类文件每行是一条完整的数据，按行组块
+    Rows,
+    /// 源代码按定义切分，保留缩进
+    Code,
+    /// markdown 按段落、代码块和表格组块，文本块前加上标题路径
+    Markdown,
+}
+
+impl From<FileType> for Chunking {
+    fn from(file_type: FileType) -> Self {
+        match file_type {
+            FileType::Spreadsheet | FileType::Csv | FileType::Structured => Chunking::Rows,
+            FileType::Code => Chunking::Code,
+            FileType::Markdown | FileType::Notebook => Chunking::Markdown,
+            _ => Chunking::Text,
+        }
     }
-    let file_extension = file.as_ref().extension().unwrap();
-    match file_extension.to_str().unwrap() {
-        "pdf" => Ok(LoPdfProcessor::extract_text(file).await?),
-        "md" => Ok(MarkdownProcessor::extract_text(file)?),
-        "txt" => Ok(TxtProcessor::extract_text(file)?),
-        "docx" => Ok(DocxRsProcessor::extract_text(file).await?),
-        _ => Err(AidenErrors::Str("其他文件格式未实现")),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_chunks() {
+        assert_eq!(group_chunks(&[300, 300, 300, 2000, 100, 100], 1000), vec![0..3, 3..4, 4..6]);
+        assert_eq!(group_chunks(&[1500, 100], 1000), vec![0..1, 1..2]);
+        assert!(group_chunks(&[], 1000).is_empty());
     }
 }
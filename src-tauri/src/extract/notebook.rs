@@ -0,0 +1,195 @@
+use crate::errors::AppResult;
+use crate::extract::{read_text, Section};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+
+/// 当前生效的 Notebook 提取配置，启动时从设置表加载
+static OPTIONS: LazyLock<RwLock<NotebookOptions>> = LazyLock::new(Default::default);
+/// 提取的输出类型，图片等其他类型跳过
+static TEXT_MIME_TYPES: &[&str] = &["text/markdown", "text/plain"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NotebookOptions {
+    /// 是否提取代码单元的输出，默认不提取（输出多为日志和数据预览）
+    pub include_outputs: bool,
+}
+
+impl NotebookOptions {
+    /// 在设置表中的键
+    pub const SETTINGS_KEY: &'static str = "notebook_options";
+
+    /// 当前生效的配置
+    pub fn current() -> Self {
+        *OPTIONS.read().unwrap()
+    }
+
+    /// 替换当前生效的配置，之后同步的 Notebook 使用新的配置
+    pub fn apply(self) {
+        *OPTIONS.write().unwrap() = self;
+    }
+}
+
+pub struct NotebookProcessor;
+
+impl NotebookProcessor {
+    /// Extracts the markdown and code cells of a Jupyter notebook.
+    ///
+    /// Markdown cells are kept as they are and code cells become fenced code blocks in the kernel's
+    /// language. A new section starts at every markdown cell beginning with a heading and is titled
+    /// with it, so code stays together with the text explaining it. Text outputs of code cells are
+    /// added after the cell when enabled in `NotebookOptions`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the ipynb file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        let notebook: Value = serde_json::from_str(&read_text(path)?)?;
+        Ok(notebook_sections(&notebook, NotebookOptions::current().include_outputs))
+    }
+}
+
+fn notebook_sections(notebook: &Value, include_outputs: bool) -> Vec<Section> {
+    let language = notebook["metadata"]["kernelspec"]["language"]
+        .as_str()
+        .or(notebook["metadata"]["language_info"]["name"].as_str())
+        .unwrap_or("python");
+    // nbformat 4 的单元在 cells 中，nbformat 3 在 worksheets[0].cells 中
+    let cells = notebook["cells"]
+        .as_array()
+        .or(notebook["worksheets"][0]["cells"].as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let mut sections: Vec<Section> = Vec::new();
+    let mut current = Section::default();
+    for cell in cells {
+        // nbformat 3 的代码在 input 中
+        let source = source_text(if cell["source"].is_null() { &cell["input"] } else { &cell["source"] });
+        let source = source.trim_matches('\n');
+        if source.trim().is_empty() {
+            continue;
+        }
+        let block = match cell["cell_type"].as_str() {
+            Some("markdown") => {
+                if let Some(heading) = source.lines().next().and_then(|line| line.strip_prefix('#')) {
+                    if !current.text.is_empty() {
+                        sections.push(std::mem::take(&mut current));
+                    }
+                    current.title = Some(heading.trim_start_matches('#').trim().to_string());
+                }
+                source.to_string()
+            }
+            Some("code") => {
+                let mut block = format!("```{}\n{}\n```", language, source);
+                if include_outputs {
+                    let outputs = cell["outputs"].as_array().map(Vec::as_slice).unwrap_or_default();
+                    let outputs = outputs.iter().filter_map(output_text).collect::<Vec<_>>();
+                    if !outputs.is_empty() {
+                        block.push_str(&format!("\n\nOutput:\n```\n{}\n```", outputs.join("\n").trim_matches('\n')));
+                    }
+                }
+                block
+            }
+            // raw 单元通常是给 nbconvert 的模板
+            _ => continue,
+        };
+        if !current.text.is_empty() {
+            current.text.push_str("\n\n");
+        }
+        current.text.push_str(&block);
+    }
+    if !current.text.is_empty() {
+        sections.push(current);
+    }
+    sections
+}
+
+/// 单元的 source 和输出的 text 可以是字符串，也可以是按行拆分的字符串数组
+fn source_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// 输出的文本：stream 的 text，execute_result/display_data 的文本数据，error 的异常名和信息
+fn output_text(output: &Value) -> Option<String> {
+    let text = match output["output_type"].as_str()? {
+        "stream" => source_text(&output["text"]),
+        "execute_result" | "display_data" | "pyout" => TEXT_MIME_TYPES
+            .iter()
+            .map(|mime| source_text(&output["data"][mime]))
+            .find(|text| !text.is_empty())
+            .unwrap_or_else(|| source_text(&output["text"])),
+        "error" | "pyerr" => format!("{}: {}", output["ename"].as_str()?, output["evalue"].as_str().unwrap_or_default()),
+        _ => return None,
+    };
+    let text = text.trim_matches('\n');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let notebook = json!({
+            "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+            "nbformat": 4,
+            "cells": [
+                {"cell_type": "markdown", "source": ["Notes on kiln data.\n"]},
+                {"cell_type": "markdown", "source": "## Load data\nRead the sensor export."},
+                {"cell_type": "code", "source": ["import pandas as pd\n", "df = pd.read_csv('kiln.csv')\n", "df.shape"], "outputs": [
+                    {"output_type": "execute_result", "data": {"text/plain": ["(1200, 4)"], "image/png": "iVBOR"}},
+                ]},
+                {"cell_type": "code", "source": "", "outputs": []},
+                {"cell_type": "raw", "source": "{{ template }}"},
+                {"cell_type": "markdown", "source": "# Errors"},
+                {"cell_type": "code", "source": "df['zone'].mean()", "outputs": [
+                    {"output_type": "stream", "name": "stderr", "text": ["warning: mixed types\n"]},
+                    {"output_type": "error", "ename": "TypeError", "evalue": "could not convert", "traceback": []},
+                ]},
+            ]
+        });
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("kiln.ipynb");
+        std::fs::write(&path, notebook.to_string()).unwrap();
+
+        let sections = NotebookProcessor::extract_sections(&path).await.unwrap();
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(titles, vec![None, Some("Load data"), Some("Errors")]);
+        assert_eq!(sections[0].text, "Notes on kiln data.");
+        assert_eq!(
+            sections[1].text,
+            "## Load data\nRead the sensor export.\n\n```python\nimport pandas as pd\ndf = pd.read_csv('kiln.csv')\ndf.shape\n```"
+        );
+
+        // 输出是可选的
+        let sections = notebook_sections(&notebook, true);
+        assert!(sections[1].text.ends_with("df.shape\n```\n\nOutput:\n```\n(1200, 4)\n```"));
+        assert!(sections[2]
+            .text
+            .ends_with("```python\ndf['zone'].mean()\n```\n\nOutput:\n```\nwarning: mixed types\nTypeError: could not convert\n```"));
+    }
+
+    #[test]
+    fn test_options() {
+        let options: NotebookOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options, NotebookOptions::default());
+        assert!(!options.include_outputs);
+        let options: NotebookOptions = serde_json::from_str(r#"{"include_outputs": true}"#).unwrap();
+        assert!(options.include_outputs);
+    }
+}
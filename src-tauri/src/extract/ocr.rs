@@ -0,0 +1,122 @@
+//! 本地 OCR：扫描版 PDF 中没有文本层的页面，以及 png/jpg/tiff 图片
+
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::Section;
+use image::DynamicImage;
+use ocrs::{ImageSource, OcrEngine, OcrEngineParams};
+use rten::Model;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 文字检测和识别模型，随应用打包在 assets/models/ocr 下
+const DETECTION_MODEL: &str = "text-detection.rten";
+const RECOGNITION_MODEL: &str = "text-recognition.rten";
+/// OCR 得到的文本块在元数据中带有此标记，检索时可以过滤或降权
+pub const OCR_KEY: &str = "ocr";
+
+static ENGINE: OnceLock<OcrEngine> = OnceLock::new();
+
+pub struct OcrProcessor;
+
+impl OcrProcessor {
+    /// Loads the bundled detection and recognition models. Until this succeeds, scanned PDFs and
+    /// image files are skipped with `AidenErrors::OcrUnavailable`.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_dir` - The directory containing `text-detection.rten` and `text-recognition.rten`.
+    pub fn init<T: AsRef<Path>>(model_dir: T) -> AppResult<()> {
+        if ENGINE.get().is_some() {
+            return Ok(());
+        }
+        let detection_model = Model::load_file(model_dir.as_ref().join(DETECTION_MODEL)).map_err(anyhow::Error::from)?;
+        let recognition_model = Model::load_file(model_dir.as_ref().join(RECOGNITION_MODEL)).map_err(anyhow::Error::from)?;
+        let engine = OcrEngine::new(OcrEngineParams {
+            detection_model: Some(detection_model),
+            recognition_model: Some(recognition_model),
+            ..Default::default()
+        })?;
+        let _ = ENGINE.set(engine);
+        Ok(())
+    }
+
+    /// 模型是否已加载
+    pub fn is_available() -> bool {
+        ENGINE.get().is_some()
+    }
+
+    /// Recognizes the text of an image, one line per text line.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - The decoded image, e.g. a scanned page.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the recognized text as a `String` if successful,
+    /// or an `Error` if the models are not loaded or recognition failed.
+    pub fn recognize(image: &DynamicImage) -> AppResult<String> {
+        let engine = ENGINE.get().ok_or(AidenErrors::Str("OCR 模型未加载"))?;
+        let image = image.to_rgb8();
+        let source = ImageSource::from_bytes(image.as_raw(), image.dimensions()).map_err(anyhow::Error::from)?;
+        let input = engine.prepare_input(source)?;
+        Ok(engine.get_text(&input)?)
+    }
+
+    /// Extracts text from a png/jpg/tiff image with OCR.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the image file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing one section marked with the `ocr` metadata if successful,
+    /// or an `Error` if the models are not loaded or an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        if !Self::is_available() {
+            return Err(AidenErrors::OcrUnavailable(path.as_ref().display().to_string()));
+        }
+        let image = image::open(path.as_ref()).map_err(anyhow::Error::from)?;
+        let text = tokio::task::spawn_blocking(move || Self::recognize(&image)).await??;
+        Ok(vec![Section {
+            text,
+            metadata: HashMap::from([(OCR_KEY.to_string(), "true".to_string())]),
+            ..Default::default()
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_extract_without_models() {
+        let dir = tempdir().unwrap();
+        assert!(OcrProcessor::init(dir.path()).is_err());
+        assert!(!OcrProcessor::is_available());
+
+        let path = dir.path().join("scan.png");
+        DynamicImage::new_luma8(8, 8).save(&path).unwrap();
+        assert!(matches!(OcrProcessor::extract_sections(&path).await, Err(AidenErrors::OcrUnavailable(_))));
+    }
+
+    /// 模型加载后全局生效，需要单独运行：cargo test test_extract_fixture -- --ignored
+    #[tokio::test]
+    #[ignore = "需要先按 README 下载 OCR 模型到 assets/models/ocr"]
+    async fn test_extract_fixture() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        OcrProcessor::init(root.join("assets").join("models").join("ocr")).unwrap();
+
+        let sections = OcrProcessor::extract_sections(root.join("tests").join("fixtures").join("invoice.png"))
+            .await
+            .unwrap();
+        assert_eq!(sections.len(), 1);
+        assert!(sections[0].text.contains("Invoice number 2024"));
+        assert!(sections[0].text.contains("Total amount 315"));
+        assert_eq!(sections[0].metadata.get(OCR_KEY).map(String::as_str), Some("true"));
+    }
+}
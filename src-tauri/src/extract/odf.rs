@@ -0,0 +1,199 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::package::read_entry;
+use roxmltree::{Document, Node};
+use std::fs::File;
+use std::io::BufReader;
+use zip::ZipArchive;
+
+const PRESENTATION_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:presentation:1.0";
+
+pub struct OdfProcessor;
+
+impl OdfProcessor {
+    /// Extracts text from an OpenDocument file (odt/odp).
+    ///
+    /// Headings keep their outline level as `#`, list items become `- ` lines and table rows are
+    /// pipe-delimited. Presentation pages start with a `## Slide N` heading followed by their speaker notes.
+    /// Spreadsheets (ods) are handled by `SpreadsheetProcessor`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the OpenDocument file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+        let xml = read_entry(&mut archive, "content.xml")?;
+        let doc = Document::parse(&xml)?;
+
+        let body = doc
+            .descendants()
+            .find(|n| n.has_tag_name("body"))
+            .ok_or(AidenErrors::Str("OpenDocument 缺少正文"))?;
+
+        let mut lines = Vec::new();
+        walk(body, &mut lines, 0);
+        Ok(lines.join("\n"))
+    }
+}
+
+fn walk(node: Node, lines: &mut Vec<String>, list_depth: usize) {
+    let mut slide = 0;
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "h" => {
+                let level = child.attributes().find(|a| a.name() == "outline-level").and_then(|a| a.value().parse().ok()).unwrap_or(1);
+                push_line(lines, format!("{} {}", "#".repeat(level), inline_text(child)));
+            }
+            "p" => push_line(lines, inline_text(child)),
+            "list" => walk(child, lines, list_depth + 1),
+            "list-item" | "list-header" => {
+                let mut item = Vec::new();
+                walk(child, &mut item, list_depth);
+                let indent = "  ".repeat(list_depth.saturating_sub(1));
+                for (idx, line) in item.into_iter().enumerate() {
+                    if idx == 0 && !line.starts_with(' ') {
+                        lines.push(format!("{}- {}", indent, line));
+                    } else {
+                        lines.push(line);
+                    }
+                }
+            }
+            "table-row" => {
+                let cells = child
+                    .children()
+                    .filter(|n| n.has_tag_name("table-cell"))
+                    .map(|cell| {
+                        let mut texts = Vec::new();
+                        walk(cell, &mut texts, 0);
+                        texts.join(" ")
+                    })
+                    .collect::<Vec<_>>();
+                if cells.iter().any(|c| !c.is_empty()) {
+                    lines.push(format!("| {} |", cells.join(" | ")));
+                }
+            }
+            "page" => {
+                slide += 1;
+                let title = child
+                    .descendants()
+                    .find(|n| n.has_tag_name("frame") && n.attribute((PRESENTATION_NS, "class")) == Some("title"))
+                    .map(inline_text)
+                    .filter(|t| !t.is_empty());
+                match title {
+                    Some(title) => lines.push(format!("## Slide {}: {}", slide, title)),
+                    None => lines.push(format!("## Slide {}", slide)),
+                }
+                walk_page(child, lines);
+                lines.push(String::new());
+            }
+            // 脚注、批注和修订信息不属于正文
+            "note" | "annotation" | "tracked-changes" | "notes" => {}
+            _ => walk(child, lines, list_depth),
+        }
+    }
+}
+
+/// 幻灯片页面：标题已经写在页头，演讲者备注放在页尾
+fn walk_page(page: Node, lines: &mut Vec<String>) {
+    for frame in page.children().filter(Node::is_element) {
+        if frame.has_tag_name("notes") || frame.attribute((PRESENTATION_NS, "class")) == Some("title") {
+            continue;
+        }
+        walk(frame, lines, 0);
+    }
+
+    if let Some(notes) = page.children().find(|n| n.has_tag_name("notes")) {
+        let mut texts = Vec::new();
+        for frame in notes.children().filter(|n| n.has_tag_name("frame")) {
+            walk(frame, &mut texts, 0);
+        }
+        if !texts.is_empty() {
+            lines.push(format!("Notes: {}", texts.join(" ")));
+        }
+    }
+}
+
+/// 段落中的行内文本，`text:s` 为连续空格，`text:tab` 为制表符
+fn inline_text(node: Node) -> String {
+    let mut text = String::new();
+    collect_inline(node, &mut text);
+    text.trim().to_string()
+}
+
+fn collect_inline(node: Node, text: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            text.push_str(child.text().unwrap_or_default());
+            continue;
+        }
+        match child.tag_name().name() {
+            "s" => {
+                let count = child.attributes().find(|a| a.name() == "c").and_then(|a| a.value().parse().ok()).unwrap_or(1);
+                text.push_str(&" ".repeat(count));
+            }
+            "tab" => text.push('\t'),
+            "line-break" => text.push('\n'),
+            "note" | "annotation" => {}
+            _ => collect_inline(child, text),
+        }
+    }
+}
+
+fn push_line(lines: &mut Vec<String>, line: String) {
+    if !line.trim().is_empty() {
+        lines.push(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const ODT: &str = r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0">
+<office:body><office:text>
+<text:h text:outline-level="2">安装</text:h>
+<text:p>Run<text:s text:c="2"/>setup<text:note><text:note-body><text:p>footnote</text:p></text:note-body></text:note> now.</text:p>
+<text:list><text:list-item><text:p>Linux</text:p></text:list-item><text:list-item><text:p>Windows</text:p></text:list-item></text:list>
+<table:table><table:table-row><table:table-cell><text:p>Port</text:p></table:table-cell><table:table-cell><text:p>8080</text:p></table:table-cell></table:table-row></table:table>
+</office:text></office:body></office:document-content>"#;
+
+    const ODP: &str = r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:draw="urn:oasis:names:tc:opendocument:xmlns:drawing:1.0" xmlns:presentation="urn:oasis:names:tc:opendocument:xmlns:presentation:1.0">
+<office:body><office:presentation>
+<draw:page draw:name="page1">
+<draw:frame presentation:class="title"><draw:text-box><text:p>Overview</text:p></draw:text-box></draw:frame>
+<draw:frame presentation:class="outline"><draw:text-box><text:list><text:list-item><text:p>First point</text:p></text:list-item></text:list></draw:text-box></draw:frame>
+<presentation:notes><draw:frame presentation:class="notes"><draw:text-box><text:p>Remember the demo</text:p></draw:text-box></draw:frame></presentation:notes>
+</draw:page>
+</office:presentation></office:body></office:document-content>"#;
+
+    async fn extract(name: &str, content: &str) -> String {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(name);
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("content.xml", SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+        zip.finish().unwrap();
+        OdfProcessor::extract_text(&path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract_odt() {
+        let text = extract("test.odt", ODT).await;
+        assert_eq!(text, "## 安装\nRun  setup now.\n- Linux\n- Windows\n| Port | 8080 |");
+    }
+
+    #[tokio::test]
+    async fn test_extract_odp() {
+        let text = extract("test.odp", ODP).await;
+        assert_eq!(text, "## Slide 1: Overview\n- First point\nNotes: Remember the demo\n");
+    }
+}
@@ -0,0 +1,70 @@
+//! pptx、epub 等基于 zip 的文档包的公共读取方法
+
+use crate::errors::AppResult;
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+/// 读取压缩包中的文本文件
+pub(crate) fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> AppResult<String> {
+    let mut entry = archive.by_name(name)?;
+    let mut xml = String::new();
+    entry.read_to_string(&mut xml)?;
+    Ok(xml)
+}
+
+/// 将包内的相对路径（如 ../notesSlides/notesSlide1.xml）解析为压缩包内的路径，
+/// 会去掉 `#` 之后的锚点并解码 `%20` 这类转义字符
+pub(crate) fn resolve_target(base_dir: &str, target: &str) -> String {
+    let target = target.split('#').next().unwrap_or_default();
+    let target = percent_decode(target);
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut parts = base_dir.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    for segment in target.split('/') {
+        match segment {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            s => parts.push(s),
+        }
+    }
+    parts.join("/")
+}
+
+/// 包内路径所在的目录
+pub(crate) fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_target() {
+        assert_eq!(resolve_target("ppt", "slides/slide1.xml"), "ppt/slides/slide1.xml");
+        assert_eq!(resolve_target("ppt/slides", "../notesSlides/notesSlide1.xml"), "ppt/notesSlides/notesSlide1.xml");
+        assert_eq!(resolve_target("OEBPS", "/OEBPS/text/ch%201.xhtml#sec2"), "OEBPS/text/ch 1.xhtml");
+        assert_eq!(resolve_target("", "content.opf"), "content.opf");
+    }
+}
@@ -0,0 +1,245 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::package::{parent_dir, read_entry, resolve_target};
+use roxmltree::{Document, Node};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use zip::ZipArchive;
+
+const DRAWING_NS: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const NOTES_SLIDE_REL: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesSlide";
+
+pub struct PptxProcessor;
+
+impl PptxProcessor {
+    /// Extracts text from a PowerPoint (pptx) file.
+    ///
+    /// Slides are read in presentation order. Every slide starts with a `## Slide N` heading,
+    /// followed by its title, body text, tables (one pipe-delimited line per row) and speaker notes.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the PPTX file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
+        let file = File::open(path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file))?;
+
+        let mut text = String::new();
+        for (idx, slide_path) in slide_paths(&mut archive)?.iter().enumerate() {
+            let xml = read_entry(&mut archive, slide_path)?;
+            let slide = SlideContent::parse(&xml)?;
+
+            text.push_str(&format!("## Slide {}", idx + 1));
+            if let Some(title) = &slide.title {
+                text.push_str(&format!(": {}", title));
+            }
+            text.push('\n');
+            for line in slide.lines {
+                text.push_str(&line);
+                text.push('\n');
+            }
+
+            let notes = notes_path(&mut archive, slide_path)?
+                .map(|p| read_entry(&mut archive, &p))
+                .transpose()?
+                .map(|xml| parse_notes(&xml))
+                .transpose()?
+                .unwrap_or_default();
+            if !notes.is_empty() {
+                text.push_str(&format!("Notes: {}\n", notes.join(" ")));
+            }
+            text.push('\n');
+        }
+
+        Ok(text)
+    }
+}
+
+/// 单页幻灯片中的内容
+#[derive(Debug, Default)]
+struct SlideContent {
+    title: Option<String>,
+    lines: Vec<String>,
+}
+
+impl SlideContent {
+    fn parse(xml: &str) -> AppResult<Self> {
+        let doc = Document::parse(xml)?;
+        let mut slide = SlideContent::default();
+        walk_shapes(doc.root_element(), &mut slide);
+        Ok(slide)
+    }
+}
+
+fn walk_shapes(node: Node, slide: &mut SlideContent) {
+    for child in node.children().filter(Node::is_element) {
+        match child.tag_name().name() {
+            "sp" => {
+                let paragraphs = paragraphs(child);
+                if paragraphs.is_empty() {
+                    continue;
+                }
+                if slide.title.is_none() && matches!(placeholder_type(child), Some("title" | "ctrTitle")) {
+                    slide.title = Some(paragraphs.join(" "));
+                } else {
+                    slide.lines.extend(paragraphs);
+                }
+            }
+            "tbl" => {
+                for row in child.children().filter(|n| n.has_tag_name("tr")) {
+                    let cells = row
+                        .children()
+                        .filter(|n| n.has_tag_name("tc"))
+                        .map(|tc| paragraphs(tc).join(" "))
+                        .collect::<Vec<_>>();
+                    slide.lines.push(format!("| {} |", cells.join(" | ")));
+                }
+            }
+            _ => walk_shapes(child, slide),
+        }
+    }
+}
+
+/// 占位符类型，例如 title、body、sldNum
+fn placeholder_type<'a>(shape: Node<'a, '_>) -> Option<&'a str> {
+    shape.descendants().find(|n| n.has_tag_name("ph")).map(|ph| ph.attribute("type").unwrap_or("body"))
+}
+
+/// 读取形状中的所有段落，忽略空段落
+fn paragraphs(node: Node) -> Vec<String> {
+    node.descendants()
+        .filter(|n| n.has_tag_name((DRAWING_NS, "p")))
+        .map(|p| p.descendants().filter(|n| n.has_tag_name((DRAWING_NS, "t"))).filter_map(|t| t.text()).collect::<String>())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn parse_notes(xml: &str) -> AppResult<Vec<String>> {
+    let doc = Document::parse(xml)?;
+    let notes = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("sp") && placeholder_type(*n) == Some("body"))
+        .flat_map(paragraphs)
+        .collect();
+    Ok(notes)
+}
+
+/// 按 presentation.xml 中 sldIdLst 的顺序返回幻灯片在压缩包中的路径
+fn slide_paths<R: Read + Seek>(archive: &mut ZipArchive<R>) -> AppResult<Vec<String>> {
+    let presentation = read_entry(archive, "ppt/presentation.xml")?;
+    let rels = read_entry(archive, "ppt/_rels/presentation.xml.rels")?;
+
+    let presentation = Document::parse(&presentation)?;
+    let rels = Document::parse(&rels)?;
+
+    let paths = presentation
+        .descendants()
+        .filter(|n| n.has_tag_name("sldId"))
+        .filter_map(|n| n.attribute((REL_NS, "id")))
+        .filter_map(|id| relationship_target(&rels, |r| r.attribute("Id") == Some(id)))
+        .map(|target| resolve_target("ppt", target))
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return Err(AidenErrors::Str("PPTX 中没有幻灯片"));
+    }
+    Ok(paths)
+}
+
+fn notes_path<R: Read + Seek>(archive: &mut ZipArchive<R>, slide_path: &str) -> AppResult<Option<String>> {
+    let dir = parent_dir(slide_path);
+    let name = slide_path.rsplit('/').next().unwrap_or(slide_path);
+    let rels_path = format!("{}/_rels/{}.rels", dir, name);
+    if archive.index_for_name(&rels_path).is_none() {
+        return Ok(None);
+    }
+
+    let rels = read_entry(archive, &rels_path)?;
+    let rels = Document::parse(&rels)?;
+    Ok(relationship_target(&rels, |r| r.attribute("Type") == Some(NOTES_SLIDE_REL)).map(|target| resolve_target(dir, target)))
+}
+
+fn relationship_target<'a, F>(rels: &'a Document, predicate: F) -> Option<&'a str>
+where
+    F: Fn(&Node) -> bool,
+{
+    rels.descendants()
+        .filter(|n| n.has_tag_name("Relationship"))
+        .find(|n| predicate(n))
+        .and_then(|n| n.attribute("Target"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const PRESENTATION: &str = r#"<p:presentation xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<p:sldIdLst><p:sldId id="256" r:id="rId3"/><p:sldId id="257" r:id="rId2"/></p:sldIdLst></p:presentation>"#;
+
+    const PRESENTATION_RELS: &str = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide" Target="slides/slide1.xml"/>
+<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide" Target="slides/slide2.xml"/>
+</Relationships>"#;
+
+    const SLIDE_1: &str = r#"<p:sld xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+<p:cSld><p:spTree>
+<p:sp><p:nvSpPr><p:nvPr><p:ph type="title"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>参数</a:t></a:r></a:p></p:txBody></p:sp>
+<p:graphicFrame><a:graphic><a:graphicData><a:tbl>
+<a:tr><a:tc><a:txBody><a:p><a:r><a:t>名称</a:t></a:r></a:p></a:txBody></a:tc><a:tc><a:txBody><a:p><a:r><a:t>值</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+<a:tr><a:tc><a:txBody><a:p><a:r><a:t>温度</a:t></a:r></a:p></a:txBody></a:tc><a:tc><a:txBody><a:p><a:r><a:t>450</a:t></a:r></a:p></a:txBody></a:tc></a:tr>
+</a:tbl></a:graphicData></a:graphic></p:graphicFrame>
+</p:spTree></p:cSld></p:sld>"#;
+
+    const SLIDE_2: &str = r#"<p:sld xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+<p:cSld><p:spTree>
+<p:sp><p:nvSpPr><p:nvPr><p:ph type="ctrTitle"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>Overview</a:t></a:r></a:p></p:txBody></p:sp>
+<p:sp><p:nvSpPr><p:nvPr><p:ph idx="1"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>First </a:t></a:r><a:r><a:t>point</a:t></a:r></a:p><a:p><a:r><a:t>Second point</a:t></a:r></a:p></p:txBody></p:sp>
+</p:spTree></p:cSld></p:sld>"#;
+
+    const SLIDE_2_RELS: &str = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/notesSlide" Target="../notesSlides/notesSlide1.xml"/>
+</Relationships>"#;
+
+    const NOTES_1: &str = r#"<p:notes xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+<p:cSld><p:spTree>
+<p:sp><p:nvSpPr><p:nvPr><p:ph type="sldImg"/></p:nvPr></p:nvSpPr></p:sp>
+<p:sp><p:nvSpPr><p:nvPr><p:ph type="body" idx="1"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>Remember the demo</a:t></a:r></a:p></p:txBody></p:sp>
+<p:sp><p:nvSpPr><p:nvPr><p:ph type="sldNum" idx="5"/></p:nvPr></p:nvSpPr><p:txBody><a:p><a:r><a:t>2</a:t></a:r></a:p></p:txBody></p:sp>
+</p:spTree></p:cSld></p:notes>"#;
+
+    #[tokio::test]
+    async fn test_extract_text() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.pptx");
+
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in [
+            ("ppt/presentation.xml", PRESENTATION),
+            ("ppt/_rels/presentation.xml.rels", PRESENTATION_RELS),
+            ("ppt/slides/slide1.xml", SLIDE_1),
+            ("ppt/slides/slide2.xml", SLIDE_2),
+            ("ppt/slides/_rels/slide2.xml.rels", SLIDE_2_RELS),
+            ("ppt/notesSlides/notesSlide1.xml", NOTES_1),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let text = PptxProcessor::extract_text(&path).await.unwrap();
+        assert_eq!(
+            text,
+            "## Slide 1: Overview\nFirst point\nSecond point\nNotes: Remember the demo\n\n## Slide 2: 参数\n| 名称 | 值 |\n| 温度 | 450 |\n\n"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! 按文件类型的切分配置：文本块大小、重叠比例、切分策略和计算 token 数的分词器
+
+use crate::embed::text_loader::{BERT_BASE_CHINESE_TOKENIZER, TOKENIZER};
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::FileType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use tokenizers::Tokenizer;
+
+/// 当前生效的切分配置，启动时从设置表加载
+static PROFILES: LazyLock<RwLock<ChunkingProfiles>> = LazyLock::new(Default::default);
+
+/// 可以单独配置的文件类型，压缩包中的文件按其自身的类型切分
+const FILE_TYPES: &[FileType] = &[
+    FileType::Pdf,
+    FileType::Markdown,
+    FileType::Text,
+    FileType::Docx,
+    FileType::Doc,
+    FileType::Rtf,
+    FileType::OpenDocument,
+    FileType::Pptx,
+    FileType::Html,
+    FileType::Epub,
+    FileType::Email,
+    FileType::Spreadsheet,
+    FileType::Csv,
+    FileType::Code,
+    FileType::Image,
+    FileType::Notebook,
+    FileType::Latex,
+    FileType::Subtitle,
+    FileType::Structured,
+];
+
+/// 文本块大小的范围（token）
+const CHUNK_SIZE_RANGE: std::ops::RangeInclusive<usize> = 32..=4096;
+/// 重叠比例的上限，重叠不能达到整个文本块
+const MAX_OVERLAP_RATIO: f32 = 0.5;
+
+/// 切分策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// 按句子切分
+    Sentence,
+    /// 按相邻句子的语义相似度切分
+    Semantic,
+    /// 按文件的结构切分：表格按行、源代码按定义、markdown 按段落和代码块，没有结构的格式按句子切分
+    Structural,
+}
+
+/// 计算 token 数的分词器，均随应用打包
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChunkTokenizer {
+    ChineseRobertaWwmExt,
+    BertBaseChinese,
+}
+
+impl ChunkTokenizer {
+    pub fn tokenizer(&self) -> &'static Tokenizer {
+        match self {
+            ChunkTokenizer::ChineseRobertaWwmExt => &TOKENIZER,
+            ChunkTokenizer::BertBaseChinese => &BERT_BASE_CHINESE_TOKENIZER,
+        }
+    }
+}
+
+/// 一种文件类型的切分配置
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChunkingProfile {
+    /// 文本块大小（token）
+    pub chunk_size: usize,
+    /// 相邻文本块重叠的比例，语义切分不使用，界面上不可编辑
+    pub overlap_ratio: f32,
+    pub strategy: ChunkStrategy,
+    pub tokenizer: ChunkTokenizer,
+}
+
+impl Default for ChunkingProfile {
+    fn default() -> Self {
+        Self {
+            chunk_size: 256,
+            overlap_ratio: 0.3,
+            strategy: ChunkStrategy::Semantic,
+            tokenizer: ChunkTokenizer::ChineseRobertaWwmExt,
+        }
+    }
+}
+
+impl ChunkingProfile {
+    /// 内置的配置：有结构的格式按结构切分，其余按语义切分
+    pub fn builtin(file_type: FileType) -> Self {
+        let strategy = match file_type {
+            FileType::Spreadsheet | FileType::Csv | FileType::Structured | FileType::Code | FileType::Markdown | FileType::Notebook => {
+                ChunkStrategy::Structural
+            }
+            _ => ChunkStrategy::Semantic,
+        };
+        Self {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    fn check(&self) -> AppResult<()> {
+        if !CHUNK_SIZE_RANGE.contains(&self.chunk_size) {
+            return Err(AidenErrors::String(format!(
+                "文本块大小 {} 超出范围 {}-{}",
+                self.chunk_size,
+                CHUNK_SIZE_RANGE.start(),
+                CHUNK_SIZE_RANGE.end()
+            )));
+        }
+        if !(0.0..=MAX_OVERLAP_RATIO).contains(&self.overlap_ratio) {
+            return Err(AidenErrors::String(format!(
+                "重叠比例 {} 超出范围 0-{}",
+                self.overlap_ratio, MAX_OVERLAP_RATIO
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 全部文件类型的切分配置
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChunkingProfiles {
+    /// 无法识别类型的文件（如注册的自定义格式）使用的配置
+    pub default: ChunkingProfile,
+    /// 按文件类型的配置，没有配置的类型使用内置配置
+    pub file_types: HashMap<FileType, ChunkingProfile>,
+}
+
+impl Default for ChunkingProfiles {
+    fn default() -> Self {
+        Self {
+            default: ChunkingProfile::default(),
+            file_types: FILE_TYPES.iter().map(|t| (*t, ChunkingProfile::builtin(*t))).collect(),
+        }
+    }
+}
+
+impl ChunkingProfiles {
+    /// 在设置表中的键
+    pub const SETTINGS_KEY: &'static str = "chunking_profiles";
+
+    /// 当前生效的切分配置
+    pub fn current() -> Self {
+        PROFILES.read().unwrap().clone()
+    }
+
+    /// 替换当前生效的切分配置，之后开始向量化的文件使用新的配置
+    pub fn apply(self) {
+        *PROFILES.write().unwrap() = self;
+    }
+
+    /// 文件类型的切分配置
+    pub fn profile(&self, file_type: Option<FileType>) -> ChunkingProfile {
+        match file_type {
+            Some(file_type) => self
+                .file_types
+                .get(&file_type)
+                .copied()
+                .unwrap_or_else(|| ChunkingProfile::builtin(file_type)),
+            None => self.default,
+        }
+    }
+
+    /// 检查文本块大小和重叠比例，保存前调用
+    pub fn check(&self) -> AppResult<()> {
+        self.default.check()?;
+        self.file_types.values().try_for_each(ChunkingProfile::check)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let json = r#"{"file_types": {"pdf": {"chunk_size": 512, "overlap_ratio": 0.1, "strategy": "sentence"},
+                                      "code": {"chunk_size": 256, "overlap_ratio": 0.0, "strategy": "structural", "tokenizer": "bert-base-chinese"}}}"#;
+        let profiles: ChunkingProfiles = serde_json::from_str(json).unwrap();
+        profiles.check().unwrap();
+
+        let pdf = profiles.profile(Some(FileType::Pdf));
+        assert_eq!(
+            (pdf.chunk_size, pdf.strategy, pdf.tokenizer),
+            (512, ChunkStrategy::Sentence, ChunkTokenizer::ChineseRobertaWwmExt)
+        );
+        assert_eq!(profiles.profile(Some(FileType::Code)).tokenizer, ChunkTokenizer::BertBaseChinese);
+        // 保存的配置中没有的类型使用内置配置
+        assert_eq!(profiles.profile(Some(FileType::Csv)).strategy, ChunkStrategy::Structural);
+        assert_eq!(profiles.profile(None), ChunkingProfile::default());
+        assert_eq!(ChunkingProfiles::default().file_types.len(), FILE_TYPES.len());
+
+        let mut invalid = profiles.clone();
+        invalid.file_types.get_mut(&FileType::Pdf).unwrap().overlap_ratio = 0.8;
+        assert!(invalid.check().is_err());
+        invalid.default.chunk_size = 0;
+        assert!(invalid.check().is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! 提取器注册表：按扩展名或识别出的文件类型选择提取器，下游 crate 可以注册自定义格式
+
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::code::CodeProcessor;
+use crate::extract::doc::DocProcessor;
+use crate::extract::docx::DocxProcessor;
+use crate::extract::email::EmailProcessor;
+use crate::extract::epub::EpubProcessor;
+use crate::extract::html::HtmlProcessor;
+use crate::extract::latex::LatexProcessor;
+use crate::extract::limits::{Deadline, ExtractLimits};
+use crate::extract::lopdf::LoPdfProcessor;
+use crate::extract::markdown::MarkdownProcessor;
+use crate::extract::notebook::NotebookProcessor;
+use crate::extract::ocr::OcrProcessor;
+use crate::extract::odf::OdfProcessor;
+use crate::extract::pptx::PptxProcessor;
+use crate::extract::rtf::RtfProcessor;
+use crate::extract::spreadsheet::SpreadsheetProcessor;
+use crate::extract::structured::StructuredProcessor;
+use crate::extract::subtitle::SubtitleProcessor;
+use crate::extract::text::TextProcessor;
+use crate::extract::{detect, extract_archive_sections, FileType, Section};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// 已注册的提取器，后注册的优先
+static EXTRACTORS: LazyLock<RwLock<Vec<Arc<dyn Extractor>>>> = LazyLock::new(|| RwLock::new(builtin_extractors()));
+
+/// 提取结果
+#[derive(Debug, Default, Clone)]
+pub struct Document {
+    /// 全文
+    pub text: String,
+    /// 章节，切分文本块时不会跨越章节边界
+    pub sections: Vec<Section>,
+    /// 文档级元数据，保存到每个文本块中
+    pub metadata: HashMap<String, String>,
+}
+
+impl Document {
+    /// 不区分章节的格式：全文作为一个无标题的章节
+    pub fn from_text(text: String) -> Self {
+        Self {
+            sections: vec![Section {
+                text: text.clone(),
+                ..Default::default()
+            }],
+            text,
+            ..Default::default()
+        }
+    }
+
+    /// 全文由各章节以空行连接
+    pub fn from_sections(sections: Vec<Section>) -> Self {
+        Self {
+            text: sections.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join("\n\n"),
+            sections,
+            ..Default::default()
+        }
+    }
+}
+
+/// A text extractor for one or more file formats.
+///
+/// Built-in formats are matched by the detected `FileType`. Formats unknown to `detect`, e.g. in-house
+/// formats of a downstream crate, are matched by `extensions` and take precedence over detection.
+pub trait Extractor: Send + Sync {
+    /// The built-in file types handled by this extractor.
+    fn file_types(&self) -> &[FileType] {
+        &[]
+    }
+
+    /// Lowercase file extensions without the dot, for formats that `detect` does not know.
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Extracts the text, sections and metadata of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted `Document` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>>;
+}
+
+/// 注册提取器，覆盖已注册的同类型或同扩展名的提取器
+pub fn register_extractor<E: Extractor + 'static>(extractor: E) {
+    EXTRACTORS.write().unwrap().push(Arc::new(extractor));
+}
+
+/// 查找文件的提取器：按扩展名注册的优先，其次按识别出的文件类型。
+/// 没有提取器时返回 `AidenErrors::UnsupportedFileType`
+pub fn find_extractor<T: AsRef<Path>>(file: &T) -> AppResult<Arc<dyn Extractor>> {
+    if let Some(extractor) = find_by_extension(file.as_ref()) {
+        return Ok(extractor);
+    }
+    let file_type = detect(file)?;
+    EXTRACTORS
+        .read()
+        .unwrap()
+        .iter()
+        .rev()
+        .find(|extractor| extractor.file_types().contains(&file_type))
+        .cloned()
+        .ok_or_else(|| AidenErrors::UnsupportedFileType(file.as_ref().display().to_string()))
+}
+
+/// 是否有按扩展名注册的提取器，用于判断压缩包中的自定义格式是否需要解压
+pub(crate) fn has_extension_extractor(path: &Path) -> bool {
+    find_by_extension(path).is_some()
+}
+
+fn find_by_extension(path: &Path) -> Option<Arc<dyn Extractor>> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    EXTRACTORS
+        .read()
+        .unwrap()
+        .iter()
+        .rev()
+        .find(|extractor| extractor.extensions().contains(&ext.as_str()))
+        .cloned()
+}
+
+/// 使用注册的提取器提取文件。超出当前提取限制（文件大小、字符数、超时）的文件返回
+/// `AidenErrors::LimitExceeded`，超时只计算提取本身，不包括调用方之后的切分和向量化
+pub async fn extract<T: AsRef<Path>>(file: &T) -> AppResult<Document> {
+    extract_with_limits(file.as_ref(), &ExtractLimits::current()).await
+}
+
+pub(crate) async fn extract_with_limits(file: &Path, limits: &ExtractLimits) -> AppResult<Document> {
+    limits.check_file_size(file)?;
+    let extractor = find_extractor(&file)?;
+    let path = file.to_path_buf();
+    let deadline = limits.deadline();
+    let runtime = tokio::runtime::Handle::current();
+    // 内置提取器大多是同步解析，长时间不让出执行权，在阻塞线程中运行才不会占住异步运行时，并能按时结束等待。
+    // 线程无法强制结束：超时后异步部分在下一次让出执行权时取消，同步解析在检查 `Deadline` 的地方结束，
+    // 没有检查点的解析会继续运行到完成，结果被丢弃
+    let task = tokio::task::spawn_blocking(move || runtime.block_on(Deadline::run(deadline, &path, extractor.extract(&path))));
+    let document = match limits.timeout() {
+        Some(duration) => match tokio::time::timeout(duration, task).await {
+            Ok(result) => result??,
+            Err(_) => return Err(limits.timed_out(file)),
+        },
+        None => task.await??,
+    };
+    limits.check_chars(file, &document.text)?;
+    Ok(document)
+}
+
+/// 只提供全文的内置格式
+struct TextExtractor<F> {
+    file_types: &'static [FileType],
+    extract_text: F,
+}
+
+impl<F> Extractor for TextExtractor<F>
+where
+    F: Fn(&Path) -> BoxFuture<'_, AppResult<String>> + Send + Sync,
+{
+    fn file_types(&self) -> &[FileType] {
+        self.file_types
+    }
+
+    fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+        Box::pin(async move { Ok(Document::from_text((self.extract_text)(path).await?)) })
+    }
+}
+
+/// 按章节提取的内置格式
+struct SectionExtractor<F> {
+    file_types: &'static [FileType],
+    extract_sections: F,
+}
+
+impl<F> Extractor for SectionExtractor<F>
+where
+    F: Fn(&Path) -> BoxFuture<'_, AppResult<Vec<Section>>> + Send + Sync,
+{
+    fn file_types(&self) -> &[FileType] {
+        self.file_types
+    }
+
+    fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+        Box::pin(async move { Ok(Document::from_sections((self.extract_sections)(path).await?)) })
+    }
+}
+
+/// 源代码：全文保持原样，章节按定义切分
+struct CodeExtractor;
+
+impl Extractor for CodeExtractor {
+    fn file_types(&self) -> &[FileType] {
+        &[FileType::Code]
+    }
+
+    fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+        Box::pin(async move {
+            Ok(Document {
+                text: CodeProcessor::extract_text(path).await?,
+                sections: CodeProcessor::extract_sections(path).await?,
+                ..Default::default()
+            })
+        })
+    }
+}
+
+/// 压缩包：全文中每个成员前加上其路径
+struct ArchiveExtractor;
+
+impl Extractor for ArchiveExtractor {
+    fn file_types(&self) -> &[FileType] {
+        &[FileType::Archive]
+    }
+
+    fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+        Box::pin(async move {
+            let sections = extract_archive_sections(&path).await?;
+            Ok(Document {
+                text: sections.iter().map(|s| s.label(s.text.clone())).collect::<Vec<_>>().join("\n\n"),
+                sections,
+                ..Default::default()
+            })
+        })
+    }
+}
+
+fn builtin_extractors() -> Vec<Arc<dyn Extractor>> {
+    fn text<F>(file_types: &'static [FileType], extract_text: F) -> Arc<dyn Extractor>
+    where
+        F: Fn(&Path) -> BoxFuture<'_, AppResult<String>> + Send + Sync + 'static,
+    {
+        Arc::new(TextExtractor { file_types, extract_text })
+    }
+    fn sections<F>(file_types: &'static [FileType], extract_sections: F) -> Arc<dyn Extractor>
+    where
+        F: Fn(&Path) -> BoxFuture<'_, AppResult<Vec<Section>>> + Send + Sync + 'static,
+    {
+        Arc::new(SectionExtractor {
+            file_types,
+            extract_sections,
+        })
+    }
+
+    vec![
+        sections(&[FileType::Pdf], |p| Box::pin(LoPdfProcessor::extract_sections(p))),
+        sections(&[FileType::Markdown], |p| Box::pin(MarkdownProcessor::extract_sections(p))),
+        text(&[FileType::Text], |p| Box::pin(TextProcessor::extract_text(p))),
+        text(&[FileType::Docx], |p| Box::pin(DocxProcessor::extract_text(p))),
+        text(&[FileType::Doc], |p| Box::pin(DocProcessor::extract_text(p))),
+        text(&[FileType::Rtf], |p| Box::pin(RtfProcessor::extract_text(p))),
+        text(&[FileType::OpenDocument], |p| Box::pin(OdfProcessor::extract_text(p))),
+        text(&[FileType::Pptx], |p| Box::pin(PptxProcessor::extract_text(p))),
+        text(&[FileType::Html], |p| Box::pin(HtmlProcessor::extract_text(p))),
+        sections(&[FileType::Epub], |p| Box::pin(EpubProcessor::extract_sections(p))),
+        sections(&[FileType::Email], |p| Box::pin(EmailProcessor::extract_sections(p))),
+        text(&[FileType::Spreadsheet, FileType::Csv], |p| {
+            Box::pin(SpreadsheetProcessor::extract_text(p))
+        }),
+        sections(&[FileType::Image], |p| Box::pin(OcrProcessor::extract_sections(p))),
+        sections(&[FileType::Notebook], |p| Box::pin(NotebookProcessor::extract_sections(p))),
+        sections(&[FileType::Latex], |p| Box::pin(LatexProcessor::extract_sections(p))),
+        sections(&[FileType::Subtitle], |p| Box::pin(SubtitleProcessor::extract_sections(p))),
+        sections(&[FileType::Structured], |p| Box::pin(StructuredProcessor::extract_sections(p))),
+        Arc::new(CodeExtractor),
+        Arc::new(ArchiveExtractor),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// 自定义格式：每行 `key=value`，每个 key 一个章节
+    struct KeyValueExtractor;
+
+    impl Extractor for KeyValueExtractor {
+        fn extensions(&self) -> &[&str] {
+            &["kvtest"]
+        }
+
+        fn extract<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+            Box::pin(async move {
+                let text = std::fs::read_to_string(path)?;
+                let sections = text
+                    .lines()
+                    .filter_map(|line| line.split_once('='))
+                    .map(|(key, value)| Section {
+                        title: Some(key.to_string()),
+                        text: value.to_string(),
+                        ..Default::default()
+                    })
+                    .collect();
+                let mut document = Document::from_sections(sections);
+                document.metadata.insert("format".to_string(), "kv".to_string());
+                Ok(document)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_extractor() {
+        let dir = tempdir().unwrap();
+        let custom = dir.path().join("settings.KVTEST");
+        std::fs::write(&custom, "port=8080\nhost=localhost\n").unwrap();
+        let markdown = dir.path().join("notes.md");
+        std::fs::write(&markdown, "# Notes").unwrap();
+
+        assert!(matches!(extract(&custom).await, Err(AidenErrors::UnsupportedFileType(_))));
+
+        register_extractor(KeyValueExtractor);
+        let document = extract(&custom).await.unwrap();
+        assert_eq!(document.text, "8080\n\nlocalhost");
+        assert_eq!(document.sections[1].title.as_deref(), Some("host"));
+        assert_eq!(document.metadata.get("format").map(String::as_str), Some("kv"));
+        assert!(has_extension_extractor(&custom));
+
+        // 内置格式不受影响
+        let document = extract(&markdown).await.unwrap();
+        assert_eq!(document.text, "# Notes");
+        assert_eq!(document.sections.len(), 1);
+    }
+
+    /// 一直读不完的格式
+    struct SlowExtractor;
+
+    impl Extractor for SlowExtractor {
+        fn extensions(&self) -> &[&str] {
+            &["slowtest"]
+        }
+
+        fn extract<'a>(&'a self, _path: &'a Path) -> BoxFuture<'a, AppResult<Document>> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                Ok(Document::default())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_limits() {
+        let dir = tempdir().unwrap();
+        let markdown = dir.path().join("notes.md");
+        std::fs::write(&markdown, "# Notes\n\nSome text.").unwrap();
+        let slow = dir.path().join("data.slowtest");
+        std::fs::write(&slow, "").unwrap();
+        register_extractor(SlowExtractor);
+
+        let limits = ExtractLimits {
+            max_file_size: 10,
+            ..Default::default()
+        };
+        let result = extract_with_limits(&markdown, &limits).await;
+        assert!(matches!(result, Err(AidenErrors::LimitExceeded(file, _)) if file == markdown.display().to_string()));
+
+        let limits = ExtractLimits {
+            max_chars: 10,
+            ..Default::default()
+        };
+        let result = extract_with_limits(&markdown, &limits).await;
+        assert!(matches!(result, Err(AidenErrors::LimitExceeded(_, reason)) if reason == "字符数 19 超过上限 10"));
+
+        let limits = ExtractLimits {
+            timeout_secs: 1,
+            ..Default::default()
+        };
+        let result = extract_with_limits(&slow, &limits).await;
+        assert!(matches!(result, Err(AidenErrors::LimitExceeded(_, reason)) if reason == "提取超过 1 秒"));
+
+        assert_eq!(extract_with_limits(&markdown, &limits).await.unwrap().text, "# Notes\n\nSome text.");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::errors::{AidenErrors, AppResult};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use std::collections::HashMap;
+
+/// 不包含正文的目标组，整组跳过
+static SKIP_DESTINATIONS: &[&str] = &[
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "themedata",
+    "colorschememapping",
+    "datastore",
+    "latentstyles",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "generator",
+    "xmlnstbl",
+    "mmathPr",
+    "fldinst",
+    "header",
+    "headerl",
+    "headerr",
+    "headerf",
+    "footer",
+    "footerl",
+    "footerr",
+    "footerf",
+    "bkmkstart",
+    "bkmkend",
+];
+
+pub struct RtfProcessor;
+
+impl RtfProcessor {
+    /// Extracts text from an RTF file.
+    ///
+    /// `\'hh` escapes are decoded with the code page of the current font (`\fcharset`) or the
+    /// document (`\ansicpg`), so GBK/Big5 documents come out correctly.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the RTF file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<std::path::Path>>(path: T) -> AppResult<String> {
+        let bytes = std::fs::read(path)?;
+        if !bytes.starts_with(b"{\\rtf") {
+            return Err(AidenErrors::Str("不是有效的 RTF 文件"));
+        }
+        Ok(rtf_to_text(&bytes))
+    }
+}
+
+#[derive(Clone, Default)]
+struct GroupState {
+    skip: bool,
+    in_font_table: bool,
+    font: Option<i32>,
+    /// `\uN` 之后需要跳过的替代字符数
+    uc: usize,
+}
+
+#[derive(Default)]
+struct RtfParser {
+    text: String,
+    pending: Vec<u8>,
+    codepage: u32,
+    font_charsets: HashMap<i32, u32>,
+    defining_font: Option<i32>,
+    /// 还需要跳过的 `\uN` 替代字符数
+    unicode_skip: usize,
+}
+
+fn rtf_to_text(bytes: &[u8]) -> String {
+    let mut parser = RtfParser {
+        codepage: 1252,
+        ..Default::default()
+    };
+    let mut stack = vec![GroupState { uc: 1, ..Default::default() }];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let state = stack.last().cloned().unwrap_or_default();
+        match bytes[i] {
+            b'{' => {
+                parser.flush(&state);
+                stack.push(state);
+                i += 1;
+            }
+            b'}' => {
+                parser.flush(&state);
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&c) = bytes.get(i) else { break };
+                match c {
+                    b'\\' | b'{' | b'}' => {
+                        parser.push_byte(&state, c);
+                        i += 1;
+                    }
+                    b'\'' => {
+                        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+                        if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                            parser.push_byte(&state, b);
+                        }
+                        i += 3;
+                    }
+                    b'*' => {
+                        if let Some(top) = stack.last_mut() {
+                            top.skip = true;
+                        }
+                        i += 1;
+                    }
+                    b'~' => {
+                        parser.push_str(&state, " ");
+                        i += 1;
+                    }
+                    b'_' => {
+                        parser.push_str(&state, "-");
+                        i += 1;
+                    }
+                    b'\r' | b'\n' => {
+                        parser.push_str(&state, "\n");
+                        i += 1;
+                    }
+                    c if c.is_ascii_alphabetic() => {
+                        let start = i;
+                        while bytes.get(i).is_some_and(u8::is_ascii_alphabetic) {
+                            i += 1;
+                        }
+                        let word = std::str::from_utf8(&bytes[start..i]).unwrap_or_default();
+                        let param_start = i;
+                        if bytes.get(i) == Some(&b'-') {
+                            i += 1;
+                        }
+                        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                            i += 1;
+                        }
+                        let param = std::str::from_utf8(&bytes[param_start..i]).ok().and_then(|p| p.parse::<i32>().ok());
+                        if bytes.get(i) == Some(&b' ') {
+                            i += 1;
+                        }
+                        if let Some(top) = stack.last_mut() {
+                            parser.control_word(top, word, param);
+                        }
+                    }
+                    _ => i += 1,
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            c => {
+                parser.push_byte(&state, c);
+                i += 1;
+            }
+        }
+    }
+    if let Some(state) = stack.last() {
+        parser.flush(state);
+    }
+
+    parser
+        .text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl RtfParser {
+    fn control_word(&mut self, state: &mut GroupState, word: &str, param: Option<i32>) {
+        match word {
+            "fonttbl" => {
+                state.in_font_table = true;
+                state.skip = true;
+            }
+            w if SKIP_DESTINATIONS.contains(&w) => state.skip = true,
+            "ansicpg" => self.codepage = param.map(|p| p as u32).unwrap_or(1252),
+            "f" if state.in_font_table => self.defining_font = param,
+            "f" => {
+                self.flush(state);
+                state.font = param;
+            }
+            "fcharset" if state.in_font_table => {
+                if let (Some(font), Some(charset)) = (self.defining_font, param) {
+                    self.font_charsets.insert(font, charset as u32);
+                }
+            }
+            "uc" => state.uc = param.unwrap_or(1).max(0) as usize,
+            "u" => {
+                if let Some(code) = param {
+                    let code = if code < 0 { code + 65536 } else { code } as u32;
+                    if let Some(c) = char::from_u32(code) {
+                        self.push_str(state, &c.to_string());
+                    }
+                    self.unicode_skip = state.uc;
+                }
+            }
+            "par" | "line" | "sect" | "page" | "row" => self.push_str(state, "\n"),
+            "cell" => self.push_str(state, " | "),
+            "tab" => self.push_str(state, "\t"),
+            "emdash" => self.push_str(state, "—"),
+            "endash" => self.push_str(state, "–"),
+            "bullet" => self.push_str(state, "•"),
+            "lquote" => self.push_str(state, "‘"),
+            "rquote" => self.push_str(state, "’"),
+            "ldblquote" => self.push_str(state, "“"),
+            "rdblquote" => self.push_str(state, "”"),
+            _ => {}
+        }
+    }
+
+    fn push_byte(&mut self, state: &GroupState, b: u8) {
+        if self.unicode_skip > 0 {
+            self.unicode_skip -= 1;
+            return;
+        }
+        if !state.skip {
+            self.pending.push(b);
+        }
+    }
+
+    fn push_str(&mut self, state: &GroupState, s: &str) {
+        self.flush(state);
+        if !state.skip {
+            self.text.push_str(s);
+        }
+    }
+
+    /// 按当前字体的代码页解码缓存的字节
+    fn flush(&mut self, state: &GroupState) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let codepage = state
+            .font
+            .and_then(|f| self.font_charsets.get(&f))
+            .and_then(|charset| charset_codepage(*charset))
+            .unwrap_or(self.codepage);
+        let (text, _) = codepage_encoding(codepage).decode_without_bom_handling(&self.pending);
+        self.text.push_str(&text);
+        self.pending.clear();
+    }
+}
+
+/// `\fcharset` 对应的 Windows 代码页
+fn charset_codepage(charset: u32) -> Option<u32> {
+    match charset {
+        128 => Some(932),
+        129 => Some(949),
+        134 => Some(936),
+        136 => Some(950),
+        161 => Some(1253),
+        162 => Some(1254),
+        163 => Some(1258),
+        177 => Some(1255),
+        178 => Some(1256),
+        186 => Some(1257),
+        204 => Some(1251),
+        222 => Some(874),
+        238 => Some(1250),
+        _ => None,
+    }
+}
+
+pub(crate) fn codepage_encoding(codepage: u32) -> &'static Encoding {
+    let label = match codepage {
+        936 => "gbk".to_string(),
+        54936 => "gb18030".to_string(),
+        950 => "big5".to_string(),
+        932 => "shift_jis".to_string(),
+        949 => "euc-kr".to_string(),
+        65001 => "utf-8".to_string(),
+        cp => format!("windows-{}", cp),
+    };
+    Encoding::for_label(label.as_bytes()).unwrap_or(WINDOWS_1252)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtf_to_text() {
+        let rtf = br#"{\rtf1\ansi\ansicpg1252\deff0{\fonttbl{\f0\fnil\fcharset0 Calibri;}{\f1\fnil\fcharset134 SimSun;}}
+{\colortbl ;\red255\green0\blue0;}
+{\*\generator Riched20}\viewkind4\uc1\pard\f0\fs22 Hello \b World\b0 !\par
+\f1\'d6\'d0\'ce\'c4\f0  and \u8364?uro\par
+{\field{\*\fldinst HYPERLINK ""http://example.com""}{\fldrslt link}}\par
+}"#;
+        assert_eq!(rtf_to_text(rtf), "Hello World!\n中文 and €uro\nlink");
+    }
+}
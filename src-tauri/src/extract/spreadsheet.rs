@@ -0,0 +1,140 @@
+use crate::errors::AppResult;
+use crate::extract::text::read_text;
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use std::path::Path;
+
+pub struct SpreadsheetProcessor;
+
+impl SpreadsheetProcessor {
+    /// Extracts text from a spreadsheet (xlsx/xls/ods/csv).
+    ///
+    /// Every sheet is read, the first non-empty row is treated as the header, and every data row
+    /// becomes exactly one line in which each cell is qualified by its sheet and column, e.g.
+    /// `Sheet: Params | Row: 3 | Name = Furnace | Max Temp = 450`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the spreadsheet file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<Path>>(path: T) -> AppResult<String> {
+        let path = path.as_ref();
+        let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let sheets = if is_csv { read_csv(path)? } else { read_workbook(path)? };
+
+        let mut text = String::new();
+        for sheet in sheets {
+            for line in sheet.to_lines() {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// 一个工作表的单元格内容，每行带有其在原表中的行号（从 1 开始）
+struct Sheet {
+    name: String,
+    rows: Vec<(usize, Vec<String>)>,
+}
+
+impl Sheet {
+    /// 每个数据行转换为一行文本，单元格带上表头，空单元格忽略
+    fn to_lines(&self) -> Vec<String> {
+        let mut rows = self.rows.iter().filter(|(_, row)| row.iter().any(|cell| !cell.is_empty()));
+
+        let Some((_, header)) = rows.next() else {
+            return vec![];
+        };
+        let data = rows.collect::<Vec<_>>();
+        if data.is_empty() {
+            return vec![format!("Sheet: {} | {}", self.name, header.join(" | "))];
+        }
+
+        data.into_iter()
+            .map(|(row_num, row)| {
+                let mut line = format!("Sheet: {} | Row: {}", self.name, row_num);
+                for (col, cell) in row.iter().enumerate().filter(|(_, cell)| !cell.is_empty()) {
+                    match header.get(col).filter(|h| !h.is_empty()) {
+                        Some(column) => line.push_str(&format!(" | {} = {}", column, cell)),
+                        None => line.push_str(&format!(" | Column {} = {}", col + 1, cell)),
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+}
+
+fn read_workbook(path: &Path) -> AppResult<Vec<Sheet>> {
+    let mut workbook = open_workbook_auto(path)?;
+    let mut sheets = Vec::new();
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name)?;
+        let first_row = range.start().map(|(row, _)| row as usize + 1).unwrap_or(1);
+        let rows = range
+            .rows()
+            .enumerate()
+            .map(|(idx, row)| (first_row + idx, row.iter().map(cell_to_string).collect()))
+            .collect();
+        sheets.push(Sheet { name, rows });
+    }
+    Ok(sheets)
+}
+
+fn read_csv(path: &Path) -> AppResult<Vec<Sheet>> {
+    // csv 可能是 GBK 等非 UTF-8 编码，先转换为 UTF-8
+    let text = read_text(path)?;
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(text.as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let row_num = record.position().map(|p| p.line() as usize).unwrap_or(rows.len() + 1);
+        rows.push((row_num, record.iter().map(clean_cell).collect()));
+    }
+
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    Ok(vec![Sheet { name, rows }])
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::DateTime(_) => cell.as_datetime().map(|d| d.to_string()).unwrap_or_else(|| cell.to_string()),
+        _ => clean_cell(&cell.to_string()),
+    }
+}
+
+/// 单元格中的换行替换为空格，保证一行数据输出为一行文本
+fn clean_cell(cell: &str) -> String {
+    cell.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_extract_csv() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Params.csv");
+        std::fs::write(&path, "\nName,Max Temp,\nFurnace,450,\"hot\nzone\"\n,,\nCooler,,12\n").unwrap();
+
+        let text = SpreadsheetProcessor::extract_text(&path).await.unwrap();
+        assert_eq!(
+            text,
+            "Sheet: Params | Row: 3 | Name = Furnace | Max Temp = 450 | Column 3 = hot zone\n\
+             Sheet: Params | Row: 6 | Name = Cooler | Column 3 = 12\n"
+        );
+
+        let path = dir.path().join("参数.csv");
+        let (gbk, _, _) = encoding_rs::GBK.encode("名称,最高温度\n加热炉,450\n冷却器,12\n");
+        std::fs::write(&path, gbk).unwrap();
+        let text = SpreadsheetProcessor::extract_text(&path).await.unwrap();
+        assert!(text.starts_with("Sheet: 参数 | Row: 2 | 名称 = 加热炉 | 最高温度 = 450\n"));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::extract::{read_text, Section};
+use serde::de::{Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// 语句数不超过这个值的对象或数组作为一个章节，更大的按子节点拆分
+const MAX_SECTION_STATEMENTS: usize = 30;
+/// toml 的日期时间反序列化为只有这个键的表
+const TOML_DATETIME_KEY: &str = "$__toml_private_datetime";
+/// 章节元数据中的对象路径
+const KEY_PATH_KEY: &str = "key_path";
+
+/// 结构化文档的节点，对象保留原始的键顺序
+#[derive(Debug, PartialEq)]
+enum Node {
+    Scalar(String),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+pub struct StructuredProcessor;
+
+impl StructuredProcessor {
+    /// Flattens a JSON, YAML, TOML or XML document into path-qualified statements.
+    ///
+    /// Every scalar becomes a line like `servers[0].url = https://api.example.com` or
+    /// `paths./users.get.summary = List users`. Objects and arrays with at most 30 statements form one
+    /// section titled with their path (also kept as `key_path` metadata), larger ones are split along
+    /// their children, so chunks follow object boundaries. Multi-document YAML files are indexed as a
+    /// top-level list. In XML, attributes are written as `@name` and repeated elements as a list.
+    /// Files that fail to parse (JSON with comments, templated YAML, XML with undeclared entities, ...)
+    /// are indexed as plain text.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the json, yaml, toml or xml file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        let path = path.as_ref();
+        let text = read_text(path)?;
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if !matches!(ext.as_str(), "json" | "yaml" | "yml" | "toml" | "xml") {
+            return Err(AidenErrors::UnsupportedFileType(path.display().to_string()));
+        }
+        let root = match parse(&ext, &text) {
+            Ok(root) => root,
+            Err(e) => {
+                // 解析失败时按纯文本索引，内容仍然可以检索
+                log::warn!("Failed to parse {}, indexing it as plain text: {}", path.display(), e);
+                if text.trim().is_empty() {
+                    return Ok(Vec::new());
+                }
+                return Ok(vec![Section { text, ..Default::default() }]);
+            }
+        };
+
+        let mut sections = Vec::new();
+        split_sections(&root, "", &mut sections);
+        Ok(sections)
+    }
+}
+
+fn parse(ext: &str, text: &str) -> AppResult<Node> {
+    Ok(match ext {
+        "json" => serde_json::from_str(text)?,
+        "yaml" | "yml" => {
+            let mut documents = serde_yaml::Deserializer::from_str(text)
+                .map(Node::deserialize)
+                .collect::<Result<Vec<_>, _>>()?;
+            if documents.len() == 1 {
+                documents.remove(0)
+            } else {
+                Node::List(documents)
+            }
+        }
+        "toml" => toml::from_str(text)?,
+        _ => xml_node(text)?,
+    })
+}
+
+/// 展开后每个节点的语句数，结构与 `Node` 相同。节点的语句在展开结果中连续，子节点的语句依次相连
+struct Flattened {
+    len: usize,
+    children: Vec<Flattened>,
+}
+
+/// 整个文档只展开一次，再按对象边界拆分章节
+fn split_sections(node: &Node, path: &str, sections: &mut Vec<Section>) {
+    let mut statements = Vec::new();
+    let flattened = flatten(node, path, &mut statements);
+    group_sections(node, &flattened, path, &statements, sections);
+}
+
+/// 按对象边界拆分章节，`statements` 为节点展开后的语句：足够小的节点整体作为一个章节，
+/// 否则相邻的标量合并为一个章节，对象和数组递归拆分
+fn group_sections(node: &Node, flattened: &Flattened, path: &str, statements: &[String], sections: &mut Vec<Section>) {
+    let children: Vec<(String, &Node)> = match node {
+        Node::Map(entries) => entries.iter().map(|(key, value)| (join_key(path, key), value)).collect(),
+        Node::List(items) => items.iter().enumerate().map(|(idx, item)| (format!("{}[{}]", path, idx), item)).collect(),
+        Node::Scalar(_) => Vec::new(),
+    };
+    if statements.len() <= MAX_SECTION_STATEMENTS || children.is_empty() {
+        push_section(path, statements, sections);
+        return;
+    }
+
+    // 尚未加入章节的标量从 scalars_start 开始，子节点的语句从 offset 开始
+    let mut scalars_start = 0;
+    let mut offset = 0;
+    for ((child_path, child), child_flattened) in children.into_iter().zip(&flattened.children) {
+        let end = offset + child_flattened.len;
+        if !matches!(child, Node::Scalar(_)) {
+            push_section(path, &statements[scalars_start..offset], sections);
+            group_sections(child, child_flattened, &child_path, &statements[offset..end], sections);
+            scalars_start = end;
+        }
+        offset = end;
+    }
+    push_section(path, &statements[scalars_start..], sections);
+}
+
+fn push_section(path: &str, statements: &[String], sections: &mut Vec<Section>) {
+    if statements.is_empty() {
+        return;
+    }
+    let (title, metadata) = if path.is_empty() {
+        (None, HashMap::new())
+    } else {
+        (Some(path.to_string()), HashMap::from([(KEY_PATH_KEY.to_string(), path.to_string())]))
+    };
+    sections.push(Section {
+        title,
+        text: statements.join("\n"),
+        metadata,
+        ..Default::default()
+    });
+}
+
+/// 展开为 `路径 = 值` 的语句，每个标量一行，返回每个节点的语句数
+fn flatten(node: &Node, path: &str, statements: &mut Vec<String>) -> Flattened {
+    let children = match node {
+        Node::Scalar(value) if path.is_empty() => {
+            statements.push(value.replace('\n', "\\n"));
+            Vec::new()
+        }
+        Node::Scalar(value) => {
+            statements.push(format!("{} = {}", path, value.replace('\n', "\\n")));
+            Vec::new()
+        }
+        Node::List(items) if items.is_empty() => {
+            statements.push(format!("{} = []", path));
+            Vec::new()
+        }
+        Node::Map(entries) if entries.is_empty() => {
+            statements.push(format!("{} = {{}}", path));
+            Vec::new()
+        }
+        Node::List(items) => items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| flatten(item, &format!("{}[{}]", path, idx), statements))
+            .collect(),
+        Node::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| flatten(value, &join_key(path, key), statements))
+            .collect(),
+    };
+    let len = if children.is_empty() {
+        1
+    } else {
+        children.iter().map(|child| child.len).sum()
+    };
+    Flattened { len, children }
+}
+
+fn join_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// XML 转为节点：属性为 `@name`，重复的子元素合并为数组，只有文本的元素为标量，
+/// 同时有子元素和文本时文本为 `#text`
+fn xml_node(text: &str) -> AppResult<Node> {
+    let doc = roxmltree::Document::parse(text)?;
+    let root = doc.root_element();
+    Ok(Node::Map(vec![(root.tag_name().name().to_string(), element_node(root))]))
+}
+
+fn element_node(element: roxmltree::Node) -> Node {
+    let mut entries: Vec<(String, Node)> = element
+        .attributes()
+        .map(|attr| (format!("@{}", attr.name()), Node::Scalar(attr.value().to_string())))
+        .collect();
+    let text = element
+        .children()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut children: Vec<(String, Vec<Node>)> = Vec::new();
+    for child in element.children().filter(|n| n.is_element()) {
+        let name = child.tag_name().name();
+        let node = element_node(child);
+        match children.iter_mut().find(|(n, _)| n == name) {
+            Some((_, nodes)) => nodes.push(node),
+            None => children.push((name.to_string(), vec![node])),
+        }
+    }
+    if entries.is_empty() && children.is_empty() {
+        return Node::Scalar(text);
+    }
+    if !text.is_empty() {
+        entries.push(("#text".to_string(), Node::Scalar(text)));
+    }
+    for (name, mut nodes) in children {
+        let node = if nodes.len() == 1 { nodes.remove(0) } else { Node::List(nodes) };
+        entries.push((name, node));
+    }
+    Node::Map(entries)
+}
+
+/// 同一个反序列化实现用于 json、yaml 和 toml，对象按原始顺序保存
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a structured document")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Node, E> {
+        Ok(Node::Scalar(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Node, E> {
+        Ok(Node::Scalar(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Node, E> {
+        Ok(Node::Scalar("null".to_string()))
+    }
+
+    fn visit_unit<E>(self) -> Result<Node, E> {
+        Ok(Node::Scalar("null".to_string()))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Node, D::Error> {
+        Node::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Node::List(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut entries = Vec::new();
+        while let Some((key, value)) = map.next_entry::<Node, Node>()? {
+            // yaml 的键可以不是字符串
+            let key = match key {
+                Node::Scalar(key) => key,
+                key => {
+                    let mut statements = Vec::new();
+                    flatten(&key, "", &mut statements);
+                    statements.join(", ")
+                }
+            };
+            entries.push((key, value));
+        }
+        // toml 的日期时间
+        if entries.len() == 1 && entries[0].0 == TOML_DATETIME_KEY {
+            return Ok(entries.remove(0).1);
+        }
+        Ok(Node::Map(entries))
+    }
+
+    /// yaml 中带标签的值（如 `!Ref name`）只保留值
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Node, A::Error> {
+        let (_, variant) = data.variant::<Node>()?;
+        variant.newtype_variant()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    async fn extract(dir: &TempDir, name: &str, content: &str) -> Vec<Section> {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        StructuredProcessor::extract_sections(&path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+
+        let sections = extract(
+            &dir,
+            "openapi.yaml",
+            "openapi: 3.0.0\nservers:\n  - url: https://api.example.com\npaths:\n  /users:\n    get:\n      summary: List users\n      tags: []\n",
+        )
+        .await;
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title, None);
+        assert_eq!(
+            sections[0].text,
+            "openapi = 3.0.0\nservers[0].url = https://api.example.com\npaths./users.get.summary = List users\npaths./users.get.tags = []"
+        );
+
+        let sections = extract(&dir, "k8s.yml", "kind: Service\n---\nkind: Deployment\nspec: {replicas: 3}\n").await;
+        assert_eq!(sections[0].text, "[0].kind = Service\n[1].kind = Deployment\n[1].spec.replicas = 3");
+
+        let sections = extract(
+            &dir,
+            "Cargo.toml",
+            "[package]\nname = \"app\"\nreleased = 2024-05-01T08:00:00Z\n\n[[bin]]\nname = \"cli\"\n",
+        )
+        .await;
+        assert_eq!(
+            sections[0].text,
+            "package.name = app\npackage.released = 2024-05-01T08:00:00Z\nbin[0].name = cli"
+        );
+
+        let sections = extract(
+            &dir,
+            "pom.xml",
+            "<project><groupId>com.example</groupId><dependencies>\
+             <dependency scope=\"test\"><artifactId>junit</artifactId></dependency>\
+             <dependency><artifactId>guava</artifactId></dependency></dependencies></project>",
+        )
+        .await;
+        assert_eq!(
+            sections[0].text,
+            "project.groupId = com.example\nproject.dependencies.dependency[0].@scope = test\n\
+             project.dependencies.dependency[0].artifactId = junit\nproject.dependencies.dependency[1].artifactId = guava"
+        );
+
+        // 大的对象按子对象拆分，相邻的标量合并
+        let settings = (0..40).map(|i| format!("\"key{}\": {}", i, i)).collect::<Vec<_>>().join(", ");
+        let json = format!(
+            "{{\"version\": 2, \"settings\": {{{}}}, \"db\": {{\"host\": \"localhost\", \"port\": 5432}}, \"debug\": false}}",
+            settings
+        );
+        let sections = extract(&dir, "config.json", &json).await;
+        let titles = sections.iter().map(|s| s.title.as_deref()).collect::<Vec<_>>();
+        assert_eq!(titles, vec![None, Some("settings"), Some("db"), None]);
+        assert_eq!(sections[0].text, "version = 2");
+        assert_eq!(sections[1].text.lines().count(), 40);
+        assert_eq!(sections[2].text, "db.host = localhost\ndb.port = 5432");
+        assert_eq!(sections[2].metadata.get(KEY_PATH_KEY).map(String::as_str), Some("db"));
+        assert_eq!(sections[3].text, "debug = false");
+    }
+
+    #[tokio::test]
+    async fn test_extract_unparsable() {
+        let dir = tempdir().unwrap();
+
+        // 带注释的 tsconfig、模板化的 YAML、带未声明实体的 XML 按纯文本索引
+        let files = [
+            ("tsconfig.json", "{\n  // 编译选项\n  \"compilerOptions\": {\"strict\": true}\n}"),
+            ("service.yaml", "{{- if .Values.service.enabled }}\nkind: Service\n{{- end }}"),
+            ("manual.xml", "<manual><title>&product; 使用说明</title></manual>"),
+        ];
+        for (name, content) in files {
+            let sections = extract(&dir, name, content).await;
+            assert_eq!(sections.len(), 1, "{}", name);
+            assert_eq!(sections[0].text, content);
+            assert_eq!(sections[0].title, None);
+        }
+        assert!(extract(&dir, "empty.json", "").await.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::errors::AppResult;
+use crate::extract::{read_text, Section};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 每个章节最多包含的时长（毫秒），章节的起止时间保存到其中每个文本块的元数据
+const WINDOW_MS: u64 = 60_000;
+/// 每个章节最多包含的字符数，不超过一个文本块的大小
+const WINDOW_CHARS: usize = 800;
+/// 章节元数据中的起止时间，如 `12:31`、`1:02:03`
+const START_TIME_KEY: &str = "start_time";
+const END_TIME_KEY: &str = "end_time";
+
+/// 一条字幕
+#[derive(Debug, PartialEq)]
+struct Cue {
+    start: u64,
+    end: u64,
+    text: String,
+}
+
+pub struct SubtitleProcessor;
+
+impl SubtitleProcessor {
+    /// Extracts the cues of an srt or vtt subtitle file.
+    ///
+    /// Consecutive cues are grouped into sections of at most one minute, each titled with its time
+    /// range (e.g. `12:31–13:29`) and carrying `start_time`/`end_time` as metadata, so an answer can
+    /// point to the moment in the video. Formatting tags are removed, `<v Speaker>` voice tags become
+    /// a `Speaker: ` prefix, and lines repeated by rolling captions are kept once.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the srt or vtt file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the sections if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_sections<T: AsRef<Path>>(path: T) -> AppResult<Vec<Section>> {
+        let cues = parse_cues(&read_text(path)?);
+        Ok(group_cues(cues))
+    }
+}
+
+/// 解析 srt 和 vtt：空行分隔的块中，含有 `-->` 的行是时间轴，其后是字幕文本
+fn parse_cues(text: &str) -> Vec<Cue> {
+    let text = text.replace("\r\n", "\n");
+    let mut cues: Vec<Cue> = Vec::new();
+    for block in text.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some((start, end)) = lines.next().and_then(parse_timing) else {
+            // WEBVTT 头、NOTE、STYLE、REGION 以及格式错误的块
+            continue;
+        };
+        let mut cue_lines: Vec<String> = Vec::new();
+        for line in lines.map(clean_line).filter(|line| !line.is_empty()) {
+            // 滚动字幕中上一条的最后一行会在下一条中重复出现
+            let repeated = cues.last().is_some_and(|cue| cue.text.lines().any(|l| l == line));
+            if !repeated && !cue_lines.contains(&line) {
+                cue_lines.push(line);
+            }
+        }
+        if !cue_lines.is_empty() {
+            cues.push(Cue {
+                start,
+                end,
+                text: cue_lines.join("\n"),
+            });
+        }
+    }
+    cues
+}
+
+/// 时间轴行，如 `00:12:31,200 --> 00:12:34,000` 或 `12:31.200 --> 12:34.000 align:start`
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// 解析 `hh:mm:ss,mmm`（srt）或 `[hh:]mm:ss.mmm`（vtt），返回毫秒，数值溢出时按解析失败处理
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (clock, millis) = timestamp.split_once([',', '.']).unwrap_or((timestamp, "0"));
+    let mut seconds = 0u64;
+    for part in clock.split(':') {
+        seconds = seconds.checked_mul(60)?.checked_add(part.trim().parse::<u64>().ok()?)?;
+    }
+    seconds.checked_mul(1000)?.checked_add(millis.trim().parse::<u64>().ok()?)
+}
+
+/// 去掉格式标签（`<i>`、`<c.yellow>`、`{\an8}`）和行内时间戳，`<v 说话人>` 改为 `说话人: `
+fn clean_line(line: &str) -> String {
+    let mut text = String::new();
+    let mut rest = line.trim();
+    while let Some(idx) = rest.find(['<', '{']) {
+        text.push_str(&rest[..idx]);
+        let close = if rest[idx..].starts_with('<') { '>' } else { '}' };
+        let Some(len) = rest[idx..].find(close) else {
+            text.push_str(&rest[idx..]);
+            rest = "";
+            break;
+        };
+        let tag = &rest[idx + 1..idx + len];
+        if let Some(speaker) = tag
+            .strip_prefix("v ")
+            .or_else(|| tag.strip_prefix("v.").and_then(|t| t.split_once(' ').map(|(_, s)| s)))
+        {
+            text.push_str(speaker.trim());
+            text.push_str(": ");
+        }
+        rest = &rest[idx + len + 1..];
+    }
+    text.push_str(rest);
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .trim()
+        .to_string()
+}
+
+/// 按时长和字数把相邻的字幕合并为章节
+fn group_cues(cues: Vec<Cue>) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut group: Vec<Cue> = Vec::new();
+    for cue in cues {
+        let full = group
+            .first()
+            .is_some_and(|first| cue.end.saturating_sub(first.start) > WINDOW_MS || group.iter().map(|c| c.text.len()).sum::<usize>() >= WINDOW_CHARS);
+        if full {
+            sections.push(cue_section(std::mem::take(&mut group)));
+        }
+        group.push(cue);
+    }
+    if !group.is_empty() {
+        sections.push(cue_section(group));
+    }
+    sections
+}
+
+fn cue_section(group: Vec<Cue>) -> Section {
+    let start = format_timestamp(group[0].start);
+    let end = format_timestamp(group.iter().map(|cue| cue.end).max().unwrap_or(group[0].end));
+    Section {
+        title: Some(format!("{}–{}", start, end)),
+        text: group.into_iter().map(|cue| cue.text).collect::<Vec<_>>().join("\n"),
+        metadata: HashMap::from([(START_TIME_KEY.to_string(), start), (END_TIME_KEY.to_string(), end)]),
+        ..Default::default()
+    }
+}
+
+/// 毫秒格式化为 `m:ss`，超过一小时为 `h:mm:ss`
+fn format_timestamp(millis: u64) -> String {
+    let seconds = millis / 1000;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_extract_sections() {
+        let dir = tempdir().unwrap();
+        let srt = dir.path().join("training.srt");
+        std::fs::write(
+            &srt,
+            "1\r\n00:12:31,200 --> 00:12:34,000\r\n<i>打开炉门前</i>先确认温度\r\n\r\n\
+             2\r\n00:12:34,500 --> 00:12:40,000\r\n{\\an8}温度低于 50 度 &amp; 断电\r\n\r\n\
+             3\r\n00:13:40,000 --> 00:13:45,000\r\n下一步：更换滤网\r\n",
+        )
+        .unwrap();
+        let sections = SubtitleProcessor::extract_sections(&srt).await.unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title.as_deref(), Some("12:31–12:40"));
+        assert_eq!(sections[0].text, "打开炉门前先确认温度\n温度低于 50 度 & 断电");
+        assert_eq!(sections[0].metadata.get(START_TIME_KEY).map(String::as_str), Some("12:31"));
+        assert_eq!(sections[0].metadata.get(END_TIME_KEY).map(String::as_str), Some("12:40"));
+        assert_eq!(sections[1].title.as_deref(), Some("13:40–13:45"));
+
+        let vtt = dir.path().join("training.vtt");
+        std::fs::write(
+            &vtt,
+            "WEBVTT\n\nNOTE 自动生成\n\nintro\n01:02:03.000 --> 01:02:05.000 align:start\n<v 张工>大家好\n\n\
+             01:02:05.000 --> 01:02:07.000\n<v 张工>大家好\n<c>今天讲<01:02:06.000>安全规范</c>\n",
+        )
+        .unwrap();
+        let sections = SubtitleProcessor::extract_sections(&vtt).await.unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].title.as_deref(), Some("1:02:03–1:02:07"));
+        assert_eq!(sections[0].text, "张工: 大家好\n今天讲安全规范");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("01:02:03,450"), Some(3_723_450));
+        assert_eq!(parse_timestamp("02:03.450"), Some(123_450));
+        // 数值溢出时解析失败，不 panic
+        assert_eq!(parse_timestamp("99999999999999999:00:00,000"), None);
+        assert_eq!(parse_timestamp("00:00:00,18446744073709551615"), Some(u64::MAX));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::errors::AppResult;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::path::Path;
+
+/// 编码检测最多读取的字节数
+const DETECT_LEN: usize = 1024 * 1024;
+
+pub struct TextProcessor;
+
+impl TextProcessor {
+    /// Extracts text from a plain text file.
+    ///
+    /// The encoding is detected from the BOM, a UTF-16 byte pattern, or the byte statistics of the
+    /// content (GB18030/GBK, Big5, Shift-JIS, ...), and the text is transcoded to UTF-8.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the text file.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the extracted text as a `String` if successful,
+    /// or an `Error` if an error occurred during the extraction process.
+    pub async fn extract_text<T: AsRef<Path>>(path: T) -> AppResult<String> {
+        read_text(path)
+    }
+}
+
+/// 读取文本文件并转换为 UTF-8
+pub fn read_text<T: AsRef<Path>>(path: T) -> AppResult<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(decode_text(&bytes))
+}
+
+/// 检测编码并转换为 UTF-8，BOM 会被去掉
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    let (text, _, _) = detect_encoding(bytes).decode(bytes);
+    text.into_owned()
+}
+
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    let sample = &bytes[..bytes.len().min(DETECT_LEN)];
+    detector.feed(sample, sample.len() == bytes.len());
+    // 检测结果为 GBK 时，encoding_rs 的 GBK 解码器同样能解码 GB18030 的四字节序列
+    detector.guess(None, true)
+}
+
+/// 没有 BOM 的 UTF-16：ASCII 字符的高字节为 0，NUL 集中出现在奇数位（LE）或偶数位（BE）
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(1024) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd * 2 > pairs && even * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even * 2 > pairs && odd * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{BIG5, GB18030, GBK, SHIFT_JIS};
+
+    #[test]
+    fn test_decode_text() {
+        let chinese = "本周完成了数据同步功能的开发，下周计划进行性能测试和问题修复。";
+        let (gbk, _, _) = GBK.encode(chinese);
+        assert_eq!(decode_text(&gbk), chinese);
+        // GB18030 的四字节字符
+        let rare = format!("{}𠀀€", chinese);
+        let (gb18030, _, _) = GB18030.encode(&rare);
+        assert_eq!(decode_text(&gb18030), rare);
+
+        let traditional = "本週完成了資料同步功能的開發，下週計劃進行效能測試與問題修復。";
+        let (big5, _, _) = BIG5.encode(traditional);
+        assert_eq!(decode_text(&big5), traditional);
+
+        let japanese = "今週はデータ同期機能の開発を完了しました。来週は性能テストを行います。";
+        let (sjis, _, _) = SHIFT_JIS.encode(japanese);
+        assert_eq!(decode_text(&sjis), japanese);
+
+        let utf16le = "Hello 世界".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+        assert_eq!(decode_text(&[b"\xFF\xFE".as_slice(), &utf16le].concat()), "Hello 世界");
+        assert_eq!(decode_text(&utf16le), "Hello 世界");
+        let utf16be = "Hello 世界".encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<_>>();
+        assert_eq!(decode_text(&utf16be), "Hello 世界");
+
+        assert_eq!(decode_text("\u{FEFF}说明".as_bytes()), "说明");
+    }
+}
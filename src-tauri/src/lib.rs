@@ -5,20 +5,27 @@ pub mod extract;
 pub mod models;
 pub mod storage;
 
-use crate::agent::OpenAiAgent;
+use crate::agent::{OpenAiAgent, RetrievalOptions};
 use crate::embed::job::EmbedManager;
 use crate::embed::AidenTextEmbedder;
 use crate::errors::AppResult;
+use crate::extract::{ChunkingProfiles, ExtractLimits, LoPdfProcessor, NotebookOptions, OcrProcessor};
 use crate::models::flate::{calculate_md5, decompress_and_merge_files};
 use crate::storage::file_contents::FileContentsRepo;
+use crate::storage::file_parents::FileParentsRepo;
 use crate::storage::files::{FileRecord, FilesRepo};
 use crate::storage::open_ai::OpenAiRepo;
+use crate::storage::passwords::{self, PasswordsRepo};
+use crate::storage::settings::SettingsRepo;
+use crate::storage::skipped::SkippedRepo;
 use crate::storage::DB;
 use embed_anything::embeddings::embed::EmbeddingResult;
 use lancedb::table::OptimizeAction;
-use log::info;
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::path::Path;
 use std::time::Duration;
 use tauri::{App, Manager, State};
 use tauri_plugin_log::{Target, TargetKind};
@@ -42,8 +49,18 @@ pub fn run() {
             get_sync_list,
             add_sync_items,
             delete_sync_item,
+            set_sync_password,
             get_ai_config,
-            save_ai_config
+            save_ai_config,
+            get_extract_limits,
+            save_extract_limits,
+            get_chunking_profiles,
+            save_chunking_profiles,
+            get_notebook_options,
+            save_notebook_options,
+            get_retrieval_options,
+            save_retrieval_options,
+            get_ocr_status
         ]) // 注册命令
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -57,15 +74,18 @@ fn init_setup() -> fn(&mut App) -> Result<(), Box<dyn Error>> {
         let aiden_embedder = app.state::<AidenTextEmbedder>().inner().clone();
         let files = app.state::<FilesRepo>().inner().clone();
         let file_contexts = app.state::<FileContentsRepo>().inner().clone();
+        let file_parents = app.state::<FileParentsRepo>().inner().clone();
+        let skipped = app.state::<SkippedRepo>().inner().clone();
 
         let mut manager = EmbedManager::default();
         manager.start_embedding(files.clone(), aiden_embedder);
-        manager.start_write_embedding(files.clone(), file_contexts.clone());
+        manager.start_write_embedding(files.clone(), file_contexts.clone(), file_parents.clone(), skipped);
 
         tauri::async_runtime::spawn(async move {
             loop {
                 let _ = files.optimize(OptimizeAction::All).await;
                 let _ = file_contexts.optimize(OptimizeAction::All).await;
+                let _ = file_parents.optimize(OptimizeAction::All).await;
                 sleep(Duration::from_secs(3600)).await;
             }
         });
@@ -82,18 +102,71 @@ fn init_lancedb(app: &mut App) -> Result<(), Box<dyn Error>> {
     let files_db = tauri::async_runtime::block_on(async move { FilesRepo::new(&db2).await })?;
 
     let db3 = db.clone();
-    let file_context_db = tauri::async_runtime::block_on(async move { FileContentsRepo::new(&db3).await })?;
+    let (file_context_db, outdated) = tauri::async_runtime::block_on(async move {
+        let outdated = FileContentsRepo::drop_if_outdated(&db3).await?;
+        FileContentsRepo::new(&db3).await.map(|repo| (repo, outdated))
+    })?;
+    if outdated {
+        // 文本块表结构变化后旧数据已删除，全部文件重新同步
+        tauri::async_runtime::block_on(files_db.reset_progress())?;
+    }
 
     let db4 = db.clone();
     let open_ai_db = tauri::async_runtime::block_on(async move { OpenAiRepo::new(&db4).await })?;
 
+    let db5 = db.clone();
+    // 密码用系统钥匙串中的密钥加密保存，钥匙串不可用时不能保存密码
+    let key = passwords::load_key()
+        .inspect_err(|e| error!("Keychain unavailable, passwords will not be saved: {}", e))
+        .ok();
+    let passwords_db = tauri::async_runtime::block_on(async move { PasswordsRepo::new(&db5, key).await })?;
+    for (path, password) in tauri::async_runtime::block_on(passwords_db.query_all())? {
+        LoPdfProcessor::register_password(path, password);
+    }
+
+    let db6 = db.clone();
+    let settings_db = tauri::async_runtime::block_on(async move { SettingsRepo::new(&db6).await })?;
+    if let Some(limits) = load_setting::<ExtractLimits>(&settings_db, ExtractLimits::SETTINGS_KEY) {
+        limits.apply();
+    }
+    if let Some(profiles) = load_setting::<ChunkingProfiles>(&settings_db, ChunkingProfiles::SETTINGS_KEY) {
+        profiles.apply();
+    }
+    if let Some(options) = load_setting::<NotebookOptions>(&settings_db, NotebookOptions::SETTINGS_KEY) {
+        options.apply();
+    }
+    if let Some(options) = load_setting::<RetrievalOptions>(&settings_db, RetrievalOptions::SETTINGS_KEY) {
+        options.apply();
+    }
+
+    let db7 = db.clone();
+    let skipped_db = tauri::async_runtime::block_on(async move { SkippedRepo::new(&db7).await })?;
+
+    let db8 = db.clone();
+    let file_parents_db = tauri::async_runtime::block_on(async move { FileParentsRepo::new(&db8).await })?;
+
     app.manage(file_context_db);
+    app.manage(file_parents_db);
     app.manage(files_db);
     app.manage(open_ai_db);
+    app.manage(passwords_db);
+    app.manage(settings_db);
+    app.manage(skipped_db);
     app.manage(db);
     Ok(())
 }
 
+/// 读取保存的设置。读取失败（如旧版本保存的值无法解析）时记录日志并使用默认值，不影响启动
+fn load_setting<T: DeserializeOwned>(settings: &SettingsRepo, key: &str) -> Option<T> {
+    match tauri::async_runtime::block_on(settings.get::<T>(key)) {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to load setting {}, using defaults: {}", key, e);
+            None
+        }
+    }
+}
+
 fn init_models(app: &mut App) -> Result<(), Box<dyn Error>> {
     let resource_path = app.path().resource_dir().expect("Failed to get resource dir");
     let model_source_path = resource_path.join("assets").join("models").join("all-MiniLM-L6-v2");
@@ -118,6 +191,11 @@ fn init_models(app: &mut App) -> Result<(), Box<dyn Error>> {
     let aiden_embedder = AidenTextEmbedder::from(model_source_path, model_target_path).expect("Failed to create AidenTextEmbedder");
     app.manage(aiden_embedder);
 
+    // 没有 OCR 模型时扫描件和图片跳过，不影响其他文件
+    if let Err(e) = OcrProcessor::init(resource_path.join("assets").join("models").join("ocr")) {
+        warn!("OCR unavailable: {}", e);
+    }
+
     Ok(())
 }
 
@@ -127,6 +205,7 @@ async fn rag_query(
     query: String,
     ai: State<'_, OpenAiRepo>,
     file_context: State<'_, FileContentsRepo>,
+    file_parents: State<'_, FileParentsRepo>,
     emb: State<'_, AidenTextEmbedder>,
 ) -> AppResult<String> {
     let question = query.clone();
@@ -141,8 +220,16 @@ async fn rag_query(
         let records = file_context.find_similar(v, 5).await?;
         let res = if let Some(rt) = ai.query_id().await? {
             if rt.state {
+                // 按文本块检索，交给模型的是相邻文本块组成的段落，或去重后的上级文本（页或章节中相邻的文本块）
+                let options = RetrievalOptions::current();
+                let context = if options.window > 0 {
+                    file_context.expand_windows(&records, options.window, options.context_tokens).await?
+                } else {
+                    let parents = file_parents.find_by_ids(&records.parent_ids()).await?;
+                    records.with_parents(&parents, options.context_tokens)
+                };
                 let agent = OpenAiAgent::new(rt.url.as_ref(), rt.token.as_ref());
-                match agent.query(question.as_str(), &records).await {
+                match agent.query(question.as_str(), &context).await {
                     Ok(r) => r,
                     Err(_) => {
                         let _ = ai.update_state(false).await;
@@ -160,9 +247,16 @@ async fn rag_query(
 }
 
 #[tauri::command]
-async fn get_sync_list(state: State<'_, FilesRepo>) -> AppResult<Vec<FileRecord>> {
+async fn get_sync_list(state: State<'_, FilesRepo>, skipped: State<'_, SkippedRepo>) -> AppResult<Vec<FileRecord>> {
     let state = state.inner().clone();
-    state.query_all().await
+    let mut records = state.query_all().await?;
+    let skipped = skipped.query_all().await?;
+    for record in records.iter_mut() {
+        let skipped = skipped.iter().filter(|s| s.sync_path == record.file_path);
+        record.locked_files = skipped.clone().filter(|s| s.needs_password()).map(|s| s.file_path.clone()).collect();
+        record.skip_reasons = skipped.map(|s| s.describe()).collect();
+    }
+    Ok(records)
 }
 
 #[tauri::command]
@@ -171,11 +265,46 @@ async fn add_sync_items(items: Vec<String>, state: State<'_, FilesRepo>) -> AppR
 }
 
 #[tauri::command]
-async fn delete_sync_item(path: String, files: State<'_, FilesRepo>, contents: State<'_, FileContentsRepo>) -> AppResult<()> {
+async fn delete_sync_item(
+    path: String,
+    files: State<'_, FilesRepo>,
+    contents: State<'_, FileContentsRepo>,
+    parents: State<'_, FileParentsRepo>,
+    passwords: State<'_, PasswordsRepo>,
+    skipped: State<'_, SkippedRepo>,
+) -> AppResult<()> {
     let _ = files.delete_by(&path).await;
+    let _ = skipped.delete_by(&path).await;
+    let _ = parents.delete_by(&path).await;
+    // 密码按加密的文件保存，删除同步项及其中文件的密码
+    for (saved, _) in passwords.query_all().await.unwrap_or_default() {
+        if Path::new(&saved).starts_with(&path) {
+            let _ = passwords.delete_by(&saved).await;
+            LoPdfProcessor::unregister_password(&saved);
+        }
+    }
+    LoPdfProcessor::unregister_password(&path);
     contents.delete_by(&path).await
 }
 
+/// 为同步项中加密的文件设置密码，并重新同步该同步项。`file` 为同步项本身或目录中的文件
+#[tauri::command]
+async fn set_sync_password(
+    path: String,
+    file: String,
+    password: String,
+    files: State<'_, FilesRepo>,
+    contents: State<'_, FileContentsRepo>,
+    parents: State<'_, FileParentsRepo>,
+    passwords: State<'_, PasswordsRepo>,
+) -> AppResult<()> {
+    passwords.save(&file, &password).await?;
+    LoPdfProcessor::register_password(&file, password);
+    contents.delete_by(&path).await?;
+    parents.delete_by(&path).await?;
+    files.update_progress_and_sync_time(&path, 0).await
+}
+
 #[tauri::command]
 async fn get_ai_config(ai: State<'_, OpenAiRepo>) -> AppResult<OpenAiConfig> {
     let res = ai.query_id().await?;
@@ -188,6 +317,88 @@ async fn save_ai_config(config: OpenAiConfig, ai: State<'_, OpenAiRepo>) -> AppR
     Ok(())
 }
 
+#[tauri::command]
+async fn get_extract_limits() -> AppResult<ExtractLimits> {
+    Ok(ExtractLimits::current())
+}
+
+/// 保存提取限制，之前被跳过的同步项按新的限制重新同步
+#[tauri::command]
+async fn save_extract_limits(
+    limits: ExtractLimits,
+    settings: State<'_, SettingsRepo>,
+    files: State<'_, FilesRepo>,
+    contents: State<'_, FileContentsRepo>,
+    parents: State<'_, FileParentsRepo>,
+    skipped: State<'_, SkippedRepo>,
+) -> AppResult<()> {
+    settings.set(ExtractLimits::SETTINGS_KEY, &limits).await?;
+    limits.apply();
+    // 缺少密码的文件与提取限制无关，不重新同步
+    let mut sync_paths = skipped
+        .query_all()
+        .await?
+        .into_iter()
+        .filter(|s| !s.needs_password())
+        .map(|s| s.sync_path)
+        .collect::<Vec<_>>();
+    sync_paths.sort();
+    sync_paths.dedup();
+    // 目录中只有部分文件被跳过时其余文件的文本块已经保存，重新同步前删除
+    for path in &sync_paths {
+        contents.delete_by(path).await?;
+        parents.delete_by(path).await?;
+    }
+    files.retry_skipped(&sync_paths).await
+}
+
+#[tauri::command]
+async fn get_chunking_profiles() -> AppResult<ChunkingProfiles> {
+    Ok(ChunkingProfiles::current())
+}
+
+/// 保存切分配置，之后同步的文件按新的配置切分
+#[tauri::command]
+async fn save_chunking_profiles(profiles: ChunkingProfiles, settings: State<'_, SettingsRepo>) -> AppResult<()> {
+    profiles.check()?;
+    settings.set(ChunkingProfiles::SETTINGS_KEY, &profiles).await?;
+    profiles.apply();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_notebook_options() -> AppResult<NotebookOptions> {
+    Ok(NotebookOptions::current())
+}
+
+/// 保存 Notebook 提取配置，之后同步的 Notebook 按新的配置提取
+#[tauri::command]
+async fn save_notebook_options(options: NotebookOptions, settings: State<'_, SettingsRepo>) -> AppResult<()> {
+    settings.set(NotebookOptions::SETTINGS_KEY, &options).await?;
+    options.apply();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_retrieval_options() -> AppResult<RetrievalOptions> {
+    Ok(RetrievalOptions::current())
+}
+
+/// 保存检索配置，之后的查询按新的配置组织上下文
+#[tauri::command]
+async fn save_retrieval_options(options: RetrievalOptions, settings: State<'_, SettingsRepo>) -> AppResult<()> {
+    options.check()?;
+    settings.set(RetrievalOptions::SETTINGS_KEY, &options).await?;
+    options.apply();
+    Ok(())
+}
+
+/// OCR 模型是否已加载，未加载时界面提示扫描件和图片会被跳过
+#[tauri::command]
+async fn get_ocr_status() -> AppResult<bool> {
+    Ok(OcrProcessor::is_available())
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct OpenAiConfig {
     pub url: String,
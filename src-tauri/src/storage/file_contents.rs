@@ -1,17 +1,21 @@
+use crate::embed::text_loader::TOKENIZER;
 use crate::errors::AppResult;
 use crate::storage::DB;
 use arrow_array::types::Float32Type;
-use arrow_array::{Array, FixedSizeListArray, Int64Array, RecordBatch, RecordBatchIterator, StringArray};
+use arrow_array::{Array, FixedSizeListArray, Float32Array, Int64Array, RecordBatch, RecordBatchIterator, StringArray};
 use arrow_schema::{DataType, Field, Schema};
 use chrono::Local;
 use embed_anything::embeddings::embed::{EmbedData, EmbeddingResult};
 use futures::TryStreamExt;
 use lancedb::query::{ExecutableQuery, QueryBase};
 use lancedb::{DistanceType, Table};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::sync::{Arc, LazyLock};
 
+/// OCR 文本块检索时在余弦距离上加的惩罚
+const OCR_DISTANCE_PENALTY: f32 = 0.1;
+
 static DEFINE_FILE_CONTENT_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
     Arc::new(Schema::new(vec![
         Field::new("file_path", DataType::Utf8, false),
@@ -23,9 +27,16 @@ static DEFINE_FILE_CONTENT_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
             false,
         ),
         Field::new("add_time", DataType::Int64, false),
+        // 文本块的元数据（章节、邮件头等），JSON 格式
+        Field::new("metadata", DataType::Utf8, true),
+        // 文本块在同步项中的序号，同一文件的文本块序号连续
+        Field::new("chunk_index", DataType::Int64, false),
     ]))
 });
 
+/// 相邻文本块重叠部分的最少字符数，更短的相同开头视为巧合
+const MIN_OVERLAP_CHARS: usize = 5;
+
 #[derive(Clone)]
 pub struct FileContentsRepo(Table);
 
@@ -35,6 +46,11 @@ impl FileContentsRepo {
         Ok(Self(table))
     }
 
+    /// 表结构发生变化时删除旧表，返回 true 表示已有的向量数据需要重新生成
+    pub async fn drop_if_outdated(db: &DB) -> AppResult<bool> {
+        db.drop_table_if_outdated("file_contents", DEFINE_FILE_CONTENT_SCHEMA.clone()).await
+    }
+
     /// 插入数据
     pub async fn insert_data(&self, records: FileContentRecordFields) -> AppResult<()> {
         let batches = RecordBatch::try_new(
@@ -47,6 +63,8 @@ impl FileContentsRepo {
                     384,
                 )),
                 Arc::new(Int64Array::from(records.add_times)),
+                Arc::new(StringArray::from(records.metadata)),
+                Arc::new(Int64Array::from(records.chunk_indexes)),
             ],
         );
 
@@ -58,7 +76,9 @@ impl FileContentsRepo {
 
     pub async fn query_all(&self, n: usize) -> AppResult<FileContentRecords> {
         let results = self.query().limit(n).execute().await?.try_collect::<Vec<_>>().await?;
-        let records = results.into_iter().flat_map(|row| FileContentRecords::from(row).0).collect();
+        let mut records = results.into_iter().flat_map(|row| FileContentRecords::from(row).0).collect::<Vec<_>>();
+        // OCR 识别的文本可能有错字，排在文本层提取的结果之后
+        records.sort_by_key(FileContentRecord::is_ocr);
 
         Ok(FileContentRecords(records))
     }
@@ -75,9 +95,53 @@ impl FileContentsRepo {
             .try_collect::<Vec<_>>()
             .await?;
 
-        let records = results.into_iter().flat_map(|row| FileContentRecords::from(row).0).collect();
+        // OCR 识别的文本可能有错字，距离加上惩罚后重新排序，排在同样相似的文本层提取结果之后
+        let mut records = Vec::new();
+        for batch in results {
+            let distances = batch
+                .column_by_name("_distance")
+                .and_then(|c| c.as_any().downcast_ref::<Float32Array>())
+                .map(|d| d.values().to_vec())
+                .unwrap_or_default();
+            for (i, record) in FileContentRecords::from(batch).0.into_iter().enumerate() {
+                let penalty = if record.is_ocr() { OCR_DISTANCE_PENALTY } else { 0.0 };
+                records.push((distances.get(i).copied().unwrap_or_default() + penalty, record));
+            }
+        }
+        records.sort_by(|a, b| a.0.total_cmp(&b.0));
 
-        Ok(FileContentRecords(records))
+        Ok(FileContentRecords(records.into_iter().map(|(_, record)| record).collect()))
+    }
+
+    /// 查询每个检索结果前后各 `window` 个文本块，合并成总 token 数不超过 `max_tokens` 的连续段落，
+    /// 见 `FileContentRecords::merge_windows`
+    pub async fn expand_windows(&self, hits: &FileContentRecords, window: usize, max_tokens: usize) -> AppResult<FileContentRecords> {
+        if hits.is_empty() {
+            return Ok(FileContentRecords(Vec::new()));
+        }
+        let filter = hits
+            .iter()
+            .map(|hit| {
+                format!(
+                    "(file_path = '{}' AND chunk_index >= {} AND chunk_index <= {})",
+                    hit.file_path.replace('\'', "''"),
+                    hit.chunk_index - window as i64,
+                    hit.chunk_index + window as i64
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let results = self
+            .query()
+            // 目录中的每个文件都有相同序号的文本块，不限制行数，合并时按文件筛选
+            .only_if(filter)
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let neighbours = results.into_iter().flat_map(|row| FileContentRecords::from(row).0).collect::<Vec<_>>();
+
+        Ok(hits.merge_windows(&neighbours, window, max_tokens))
     }
 
     /// 删除数据
@@ -113,10 +177,13 @@ impl FileContentRecords {
         let mut file_map: HashMap<String, Vec<String>> = HashMap::new();
 
         for record in &self.0 {
-            file_map
-                .entry(record.file_path.clone())
-                .or_insert_with(Vec::new)
-                .push(record.text.clone());
+            // 压缩包中的文件显示其虚拟路径
+            let source = record.metadata.get("virtual_path").unwrap_or(&record.file_path);
+            let text = match record.location() {
+                Some(location) => format!("{} ({})", record.text, location),
+                None => record.text.clone(),
+            };
+            file_map.entry(source.clone()).or_default().push(text);
         }
 
         let mut markdown = String::new();
@@ -137,6 +204,167 @@ impl FileContentRecords {
 
         markdown
     }
+
+    /// 文本块的上级文本 parent_id，按检索顺序去重
+    pub fn parent_ids(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.0
+            .iter()
+            .filter_map(FileContentRecord::parent_id)
+            .filter(|id| seen.insert(*id))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// 把检索到的文本块换成其上级文本（`parents` 为 parent_id -> 文本），同一上级文本只保留一次。
+    /// 按检索顺序加入，总 token 数不超过 `max_tokens`：放不下的上级文本换回文本块本身，仍放不下的跳过
+    pub fn with_parents(&self, parents: &HashMap<String, String>, max_tokens: usize) -> FileContentRecords {
+        let mut used_parents = HashSet::new();
+        let mut used_tokens = 0;
+        let mut records = Vec::new();
+        for record in &self.0 {
+            // 上级文本已经加入的文本块跳过
+            if record.parent_id().is_some_and(|id| used_parents.contains(id)) {
+                continue;
+            }
+            if let Some((parent_id, text)) = record.parent_id().and_then(|id| Some((id, parents.get(id)?))) {
+                let tokens = count_tokens(text);
+                if used_tokens + tokens <= max_tokens {
+                    used_tokens += tokens;
+                    used_parents.insert(parent_id);
+                    records.push(FileContentRecord {
+                        text: text.clone(),
+                        ..record.clone()
+                    });
+                    continue;
+                }
+            }
+            let tokens = count_tokens(&record.text);
+            if used_tokens + tokens <= max_tokens {
+                used_tokens += tokens;
+                records.push(record.clone());
+            }
+        }
+        FileContentRecords(records)
+    }
+
+    /// 把每个检索结果扩展为前后各 `window` 个文本块组成的段落，`neighbours` 为查询到的相邻文本块。
+    /// 同一文件中重叠或相接的窗口合并为一段，按其中最靠前的检索结果排序；段落的位置取首尾文本块的页码或时间。
+    /// 按顺序加入，总 token 数不超过 `max_tokens`：放不下的段落以检索结果为中心缩小窗口，
+    /// 直到只剩检索结果本身，仍放不下的跳过
+    pub fn merge_windows(&self, neighbours: &[FileContentRecord], window: usize, max_tokens: usize) -> FileContentRecords {
+        let radius = window as i64;
+        // (检索顺序, 检索结果, 起始序号, 结束序号)
+        let mut windows = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(rank, hit)| (rank, hit, hit.chunk_index - radius, hit.chunk_index + radius))
+            .collect::<Vec<_>>();
+        windows.sort_by(|a, b| (&a.1.file_path, a.1.source(), a.2).cmp(&(&b.1.file_path, b.1.source(), b.2)));
+        let mut merged: Vec<(usize, &FileContentRecord, i64, i64)> = Vec::new();
+        for (rank, hit, start, end) in windows {
+            match merged.last_mut() {
+                Some(last) if last.1.file_path == hit.file_path && last.1.source() == hit.source() && start <= last.3 + 1 => {
+                    last.3 = last.3.max(end);
+                    if rank < last.0 {
+                        (last.0, last.1) = (rank, hit);
+                    }
+                }
+                _ => merged.push((rank, hit, start, end)),
+            }
+        }
+        merged.sort_by_key(|(rank, ..)| *rank);
+
+        let mut used_tokens = 0;
+        let mut records = Vec::new();
+        for (_, hit, start, end) in merged {
+            let mut chunks = neighbours
+                .iter()
+                .filter(|n| n.file_path == hit.file_path && n.source() == hit.source() && (start..=end).contains(&n.chunk_index))
+                .collect::<Vec<_>>();
+            chunks.sort_by_key(|n| n.chunk_index);
+            chunks.dedup_by_key(|n| n.chunk_index);
+            if !chunks.iter().any(|n| n.chunk_index == hit.chunk_index) {
+                // 没有查询到检索结果本身时只用检索结果
+                chunks = vec![hit];
+            }
+
+            // 合并后的整个窗口，再以检索结果为中心依次缩小
+            let candidates = std::iter::once(chunks.clone()).chain((0..window).rev().map(|size| {
+                chunks
+                    .iter()
+                    .filter(|n| n.chunk_index.abs_diff(hit.chunk_index) <= size as u64)
+                    .copied()
+                    .collect::<Vec<_>>()
+            }));
+            for candidate in candidates {
+                let record = passage(hit, &candidate);
+                let tokens = count_tokens(&record.text);
+                if used_tokens + tokens <= max_tokens {
+                    used_tokens += tokens;
+                    records.push(record);
+                    break;
+                }
+            }
+        }
+        FileContentRecords(records)
+    }
+}
+
+/// 文本块的 token 数
+fn count_tokens(text: &str) -> usize {
+    TOKENIZER.encode(text, false).map(|e| e.len()).unwrap_or(text.len())
+}
+
+/// 把连续的文本块接成一段，元数据取检索结果的，位置取首尾文本块的页码或时间
+fn passage(hit: &FileContentRecord, chunks: &[&FileContentRecord]) -> FileContentRecord {
+    let (Some(first), Some(last)) = (chunks.first(), chunks.last()) else {
+        return hit.clone();
+    };
+    let mut text = first.text.clone();
+    for pair in chunks.windows(2) {
+        append_chunk(&mut text, &pair[0].text, &pair[1].text);
+    }
+    let mut metadata = hit.metadata.clone();
+    for (key, chunk) in [("page_start", first), ("start_time", first), ("page_end", last), ("end_time", last)] {
+        if let Some(value) = chunk.metadata.get(key) {
+            metadata.insert(key.to_string(), value.clone());
+        }
+    }
+    FileContentRecord {
+        text,
+        metadata,
+        ..hit.clone()
+    }
+}
+
+/// 把文本块接到段落后：去掉与上一块相同的首行（章节标题或表头）和与段落结尾重叠的开头
+fn append_chunk(passage: &mut String, previous: &str, chunk: &str) {
+    let chunk = match (previous.split_once('\n'), chunk.split_once('\n')) {
+        (Some((title, _)), Some((line, rest))) if title == line => rest,
+        _ => chunk,
+    };
+    let overlap = chunk
+        .char_indices()
+        .map(|(idx, c)| idx + c.len_utf8())
+        .skip(MIN_OVERLAP_CHARS - 1)
+        .filter(|end| passage.ends_with(&chunk[..*end]))
+        .last();
+    match overlap {
+        Some(end) => {
+            let rest = chunk[end..].trim_start();
+            // 英文单词之间补上空格
+            if passage.ends_with(|c: char| c.is_ascii_graphic()) && rest.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+                passage.push(' ');
+            }
+            passage.push_str(rest);
+        }
+        None => {
+            passage.push('\n');
+            passage.push_str(chunk);
+        }
+    }
 }
 
 impl From<RecordBatch> for FileContentRecords {
@@ -147,18 +375,27 @@ impl From<RecordBatch> for FileContentRecords {
         let file_path_array = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
         let text_array = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
         let add_time_array = batch.column(3).as_any().downcast_ref::<Int64Array>().unwrap();
+        let metadata_array = batch.column_by_name("metadata").and_then(|c| c.as_any().downcast_ref::<StringArray>());
+        let chunk_index_array = batch.column_by_name("chunk_index").and_then(|c| c.as_any().downcast_ref::<Int64Array>());
 
         // 遍历每一行
         for i in 0..batch.num_rows() {
             let file_path = file_path_array.value(i).to_string();
             let text = text_array.value(i).to_string();
             let add_time = add_time_array.value(i);
+            let metadata = metadata_array
+                .filter(|m| !m.is_null(i))
+                .and_then(|m| serde_json::from_str(m.value(i)).ok())
+                .unwrap_or_default();
+            let chunk_index = chunk_index_array.map(|c| c.value(i)).unwrap_or_default();
 
             records.push(FileContentRecord {
                 file_path,
                 text,
                 embedding: vec![],
                 add_time,
+                metadata,
+                chunk_index,
             });
         }
 
@@ -166,12 +403,55 @@ impl From<RecordBatch> for FileContentRecords {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileContentRecord {
     pub file_path: String,
     pub text: String,
     pub embedding: Vec<f32>,
     pub add_time: i64,
+    pub metadata: HashMap<String, String>,
+    /// 文本块在文件中的序号，目录中的每个文件分别从 0 开始
+    pub chunk_index: i64,
+}
+
+impl FileContentRecord {
+    /// 文本块所在的页码，如 `p. 37–38`，没有页码的返回 None
+    pub fn pages(&self) -> Option<String> {
+        let start = self.metadata.get("page_start")?;
+        match self.metadata.get("page_end") {
+            Some(end) if end != start => Some(format!("p. {}–{}", start, end)),
+            _ => Some(format!("p. {}", start)),
+        }
+    }
+
+    /// 文本块在视频中的时间，如 `12:31–13:29`，没有时间的返回 None
+    pub fn timestamps(&self) -> Option<String> {
+        let start = self.metadata.get("start_time")?;
+        match self.metadata.get("end_time") {
+            Some(end) if end != start => Some(format!("{}–{}", start, end)),
+            _ => Some(start.clone()),
+        }
+    }
+
+    /// 文本块在文件中的位置：页码或时间
+    pub fn location(&self) -> Option<String> {
+        self.pages().or_else(|| self.timestamps())
+    }
+
+    /// 文本块所在的文件，同步项是目录或压缩包时区分其中的文件
+    pub fn source(&self) -> &str {
+        self.metadata.get("file_name").unwrap_or(&self.file_path)
+    }
+
+    /// 文本块的上级文本（页或章节中相邻的文本块，不超过上下文预算），只有一个文本块的组没有上级文本
+    pub fn parent_id(&self) -> Option<&str> {
+        self.metadata.get("parent_id").map(String::as_str)
+    }
+
+    /// 文本块是否来自 OCR
+    pub fn is_ocr(&self) -> bool {
+        self.metadata.get("ocr").is_some_and(|v| v == "true")
+    }
 }
 
 #[derive(Debug, Default)]
@@ -180,19 +460,30 @@ pub struct FileContentRecordFields {
     texts: Vec<String>,
     embeddings: Vec<Vec<f32>>,
     add_times: Vec<i64>,
+    metadata: Vec<Option<String>>,
+    chunk_indexes: Vec<i64>,
 }
 
 impl FileContentRecordFields {
     pub fn new(path: String, data: Vec<EmbedData>) -> Self {
         let mut texts = Vec::with_capacity(data.len());
         let mut embeddings = Vec::with_capacity(data.len());
+        let mut metadata = Vec::with_capacity(data.len());
+        let mut chunk_indexes = Vec::with_capacity(data.len());
+        // 文本块按文件编号，目录中的每个文件分别从 0 开始
+        let mut next_indexes: HashMap<String, i64> = HashMap::new();
         data.into_iter().filter(|f| f.text.is_some()).for_each(|embed| {
             let emb = match embed.embedding {
                 EmbeddingResult::DenseVector(d) => d,
                 EmbeddingResult::MultiVector(mut m) => m.is_empty().then_some(vec![]).unwrap_or(m.remove(0)),
             };
+            let source = embed.metadata.as_ref().and_then(|m| m.get("file_name")).unwrap_or(&path);
+            let next_index = next_indexes.entry(source.clone()).or_default();
+            chunk_indexes.push(*next_index);
+            *next_index += 1;
             texts.push(embed.text.unwrap_or_default());
             embeddings.push(emb);
+            metadata.push(embed.metadata.and_then(|m| serde_json::to_string(&m).ok()));
         });
 
         let file_paths = vec![path; texts.len()];
@@ -202,6 +493,8 @@ impl FileContentRecordFields {
             texts,
             embeddings,
             add_times,
+            metadata,
+            chunk_indexes,
         }
     }
 }
@@ -268,6 +561,221 @@ mod lancedb_file_contents_tests {
         assert_eq!(results[0].text, "哈哈哈哈哈哈哈哈");
     }
 
+    #[tokio::test]
+    async fn test_find_similar_ocr() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        // OCR 文本块与查询向量完全相同，文本层的文本块略有差别
+        let ocr = HashMap::from([("ocr".to_string(), "true".to_string())]);
+        let mut embedding = vec![1.0; 384];
+        embedding[192..].fill(0.8);
+        let data = vec![
+            EmbedData::new(
+                EmbeddingResult::DenseVector(vec![1.0; 384]),
+                Some("炉温上限为 45O 度".to_string()),
+                Some(ocr),
+            ),
+            EmbedData::new(EmbeddingResult::DenseVector(embedding), Some("炉温上限为 450 度".to_string()), None),
+        ];
+        repo.insert_data(FileContentRecordFields::new("manual.pdf".to_string(), data))
+            .await
+            .unwrap();
+
+        let results = repo.find_similar(vec![1.0; 384], 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].text, "炉温上限为 450 度");
+        assert!(results[1].is_ocr());
+    }
+
+    #[tokio::test]
+    async fn test_insert_metadata() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let metadata = HashMap::from([("subject".to_string(), "周报".to_string())]);
+        let data = vec![EmbedData::new(
+            EmbeddingResult::DenseVector(vec![1.0; 384]),
+            Some("本周完成了数据同步".to_string()),
+            Some(metadata),
+        )];
+        repo.insert_data(FileContentRecordFields::new("test_path".to_string(), data))
+            .await
+            .unwrap();
+
+        let results = repo.query_all(10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].metadata.get("subject").map(String::as_str), Some("周报"));
+    }
+
+    #[tokio::test]
+    async fn test_to_markdown_pages() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let metadata = HashMap::from([("page_start".to_string(), "37".to_string()), ("page_end".to_string(), "38".to_string())]);
+        let data = vec![EmbedData::new(
+            EmbeddingResult::DenseVector(vec![1.0; 384]),
+            Some("炉温上限为 450 度".to_string()),
+            Some(metadata),
+        )];
+        repo.insert_data(FileContentRecordFields::new("manual.pdf".to_string(), data))
+            .await
+            .unwrap();
+
+        let results = repo.query_all(10).await.unwrap();
+        assert_eq!(results[0].pages().as_deref(), Some("p. 37–38"));
+        assert!(results.to_markdown().contains("- 炉温上限为 450 度 (p. 37–38)"));
+    }
+
+    #[tokio::test]
+    async fn test_to_markdown_timestamps() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let metadata = HashMap::from([
+            ("start_time".to_string(), "12:31".to_string()),
+            ("end_time".to_string(), "13:29".to_string()),
+        ]);
+        let data = vec![EmbedData::new(
+            EmbeddingResult::DenseVector(vec![1.0; 384]),
+            Some("打开炉门前先确认温度".to_string()),
+            Some(metadata),
+        )];
+        repo.insert_data(FileContentRecordFields::new("training.srt".to_string(), data))
+            .await
+            .unwrap();
+
+        let results = repo.query_all(10).await.unwrap();
+        assert_eq!(results[0].pages(), None);
+        assert_eq!(results[0].location().as_deref(), Some("12:31–13:29"));
+        assert!(results.to_markdown().contains("- 打开炉门前先确认温度 (12:31–13:29)"));
+    }
+
+    #[test]
+    fn test_with_parents() {
+        let record = |text: &str, parent_id: Option<&str>| FileContentRecord {
+            file_path: "manual.pdf".to_string(),
+            text: text.to_string(),
+            embedding: vec![],
+            add_time: 0,
+            metadata: parent_id
+                .map(|id| HashMap::from([("parent_id".to_string(), id.to_string())]))
+                .unwrap_or_default(),
+            chunk_index: 0,
+        };
+        let records = FileContentRecords(vec![
+            record("炉温上限为 450 度", Some("manual.pdf#0")),
+            record("打开炉门前先确认温度", Some("manual.pdf#1")),
+            record("超过后自动断电", Some("manual.pdf#0")),
+            record("每周检查一次密封圈", None),
+        ]);
+        assert_eq!(records.parent_ids(), vec!["manual.pdf#0", "manual.pdf#1"]);
+
+        let parents = HashMap::from([
+            ("manual.pdf#0".to_string(), "温度控制\n炉温上限为 450 度，超过后自动断电".to_string()),
+            (
+                "manual.pdf#1".to_string(),
+                "安全须知\n".to_string() + &"打开炉门前先确认温度。".repeat(50),
+            ),
+        ]);
+        let context = records.with_parents(&parents, 64);
+        let texts = context.iter().map(|r| r.text.as_str()).collect::<Vec<_>>();
+        // 同一章节只保留一次，超出预算的章节换回文本块本身
+        assert_eq!(
+            texts,
+            vec![
+                "温度控制\n炉温上限为 450 度，超过后自动断电",
+                "打开炉门前先确认温度",
+                "每周检查一次密封圈"
+            ]
+        );
+        assert!(records.with_parents(&parents, 4).is_empty());
+    }
+
+    #[test]
+    fn test_merge_windows() {
+        let texts = [
+            "安全须知\n打开炉门前先确认温度。",
+            "安全须知\n打开炉门前先确认温度。佩戴隔热手套。",
+            "安全须知\n炉温上限为 450 度。",
+            "温度控制\n超过后自动断电。",
+            "温度控制\n每周检查一次密封圈。",
+            "温度控制\n记录运行日志。",
+        ];
+        let record = |file_path: &str, idx: usize, text: &str| FileContentRecord {
+            file_path: file_path.to_string(),
+            text: text.to_string(),
+            embedding: vec![],
+            add_time: 0,
+            metadata: HashMap::from([
+                ("page_start".to_string(), (10 + idx).to_string()),
+                ("page_end".to_string(), (10 + idx).to_string()),
+            ]),
+            chunk_index: idx as i64,
+        };
+        let neighbours = texts
+            .iter()
+            .enumerate()
+            .map(|(idx, text)| record("manual.pdf", idx, text))
+            .collect::<Vec<_>>();
+        let hits = FileContentRecords(vec![neighbours[3].clone(), neighbours[1].clone(), record("notes.md", 0, "其他")]);
+
+        // 两个窗口 [2, 4] 和 [0, 2] 重叠，合并为一段
+        let merged = hits.merge_windows(&neighbours, 1, 2048);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged[0].text,
+            "安全须知\n打开炉门前先确认温度。佩戴隔热手套。\n炉温上限为 450 度。\n温度控制\n超过后自动断电。\n每周检查一次密封圈。"
+        );
+        assert_eq!(merged[0].pages().as_deref(), Some("p. 10–14"));
+        // 没有查询到相邻文本块的保持不变
+        assert_eq!(merged[1].text, "其他");
+
+        // 预算不足以放下整个窗口时以检索结果为中心缩小，最后只保留检索结果本身
+        let hit = FileContentRecords(vec![neighbours[3].clone()]);
+        assert_eq!(
+            hit.merge_windows(&neighbours, 2, 40)[0].text,
+            "安全须知\n炉温上限为 450 度。\n温度控制\n超过后自动断电。\n每周检查一次密封圈。"
+        );
+        assert_eq!(hit.merge_windows(&neighbours, 2, 16)[0].text, "温度控制\n超过后自动断电。");
+        assert!(hit.merge_windows(&neighbours, 2, 4).is_empty());
+
+        let mut passage = "The furnace is hot. Wear gloves.".to_string();
+        append_chunk(&mut passage, "", "Wear gloves. Then open the door.");
+        assert_eq!(passage, "The furnace is hot. Wear gloves. Then open the door.");
+    }
+
+    #[test]
+    fn test_chunk_index_per_file() {
+        let chunk = |file: &str| {
+            let metadata = HashMap::from([("file_name".to_string(), file.to_string())]);
+            EmbedData::new(EmbeddingResult::DenseVector(vec![0.0; 4]), Some("text".to_string()), Some(metadata))
+        };
+        let records = FileContentRecordFields::new("/data".to_string(), vec![chunk("/data/a.md"), chunk("/data/a.md"), chunk("/data/b.md")]);
+        assert_eq!(records.chunk_indexes, vec![0, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_expand_windows() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let data = ["第一段", "第二段", "第三段", "第四段", "第五段"]
+            .into_iter()
+            .map(|text| EmbedData::new(EmbeddingResult::DenseVector(vec![1.0; 384]), Some(text.to_string()), None))
+            .collect();
+        repo.insert_data(FileContentRecordFields::new("manual.md".to_string(), data))
+            .await
+            .unwrap();
+
+        let hit = repo.query_all(10).await.unwrap().0.into_iter().find(|r| r.chunk_index == 2).unwrap();
+        assert_eq!(hit.text, "第三段");
+        let expanded = repo.expand_windows(&FileContentRecords(vec![hit]), 1, 2048).await.unwrap();
+        assert_eq!(expanded.len(), 1);
+        assert_eq!(expanded[0].text, "第二段\n第三段\n第四段");
+    }
+
     #[tokio::test]
     async fn test_delete_by() {
         let dir = tempdir().unwrap();
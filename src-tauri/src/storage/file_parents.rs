@@ -0,0 +1,179 @@
+use crate::errors::AppResult;
+use crate::storage::DB;
+use arrow_array::{RecordBatch, RecordBatchIterator, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use embed_anything::embeddings::embed::EmbedData;
+use futures::TryStreamExt;
+use lancedb::query::{ExecutableQuery, QueryBase};
+use lancedb::Table;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock};
+
+static DEFINE_FILE_PARENT_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        // 同步的文件或目录，与 file_contents 中的 file_path 相同
+        Field::new("file_path", DataType::Utf8, false),
+        // 文本块元数据中的 parent_id
+        Field::new("parent_id", DataType::Utf8, false),
+        Field::new("text", DataType::Utf8, false),
+    ]))
+});
+
+/// 文本块的上级文本（页或章节中相邻的文本块），检索时按文本块查找，交给模型的是上级文本
+#[derive(Clone)]
+pub struct FileParentsRepo(Table);
+
+impl FileParentsRepo {
+    pub async fn new(db: &DB) -> AppResult<Self> {
+        let table = db.get_or_crate_table("file_parents", DEFINE_FILE_PARENT_SCHEMA.clone()).await?;
+        Ok(Self(table))
+    }
+
+    /// 保存一个同步项的上级文本，替换上次同步的记录
+    pub async fn save(&self, records: FileParentRecordFields) -> AppResult<()> {
+        self.delete_by(&records.file_path).await?;
+        if records.parent_ids.is_empty() {
+            return Ok(());
+        }
+        let batches = RecordBatch::try_new(
+            DEFINE_FILE_PARENT_SCHEMA.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![records.file_path; records.parent_ids.len()])),
+                Arc::new(StringArray::from(records.parent_ids)),
+                Arc::new(StringArray::from(records.texts)),
+            ],
+        );
+
+        self.add(RecordBatchIterator::new(vec![batches], DEFINE_FILE_PARENT_SCHEMA.clone()))
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 按 parent_id 查询上级文本，返回 parent_id -> 文本
+    pub async fn find_by_ids(&self, parent_ids: &[String]) -> AppResult<HashMap<String, String>> {
+        if parent_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let ids = parent_ids
+            .iter()
+            .map(|id| format!("'{}'", id.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let results = self
+            .query()
+            .only_if(format!("parent_id IN ({})", ids))
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let mut parents = HashMap::new();
+        for batch in results {
+            let parent_id_array = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+            let text_array = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+            for i in 0..batch.num_rows() {
+                parents.insert(parent_id_array.value(i).to_string(), text_array.value(i).to_string());
+            }
+        }
+        Ok(parents)
+    }
+
+    /// 删除数据
+    pub async fn delete_by(&self, path: &str) -> AppResult<()> {
+        self.delete(&format!("file_path = '{}'", path)).await?;
+        Ok(())
+    }
+}
+
+impl Deref for FileParentsRepo {
+    type Target = Table;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FileParentRecordFields {
+    file_path: String,
+    parent_ids: Vec<String>,
+    texts: Vec<String>,
+}
+
+impl FileParentRecordFields {
+    /// 从文本块元数据中取出上级文本。同一上级文本只在第一个文本块的元数据中（`parent_text`），
+    /// 取出后文本块只保留 `parent_id`
+    pub fn new(path: String, data: &mut [EmbedData]) -> Self {
+        let mut parent_ids = Vec::new();
+        let mut texts = Vec::new();
+        for metadata in data.iter_mut().filter_map(|embed| embed.metadata.as_mut()) {
+            let Some(text) = metadata.remove("parent_text") else {
+                continue;
+            };
+            if let Some(parent_id) = metadata.get("parent_id") {
+                parent_ids.push(parent_id.clone());
+                texts.push(text);
+            }
+        }
+        Self {
+            file_path: path,
+            parent_ids,
+            texts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod lancedb_file_parents_tests {
+    use super::*;
+    use embed_anything::embeddings::embed::EmbeddingResult;
+    use tempfile::tempdir;
+
+    fn chunk(text: &str, parent_id: &str, parent_text: Option<&str>) -> EmbedData {
+        let mut metadata = HashMap::from([("parent_id".to_string(), parent_id.to_string())]);
+        if let Some(parent_text) = parent_text {
+            metadata.insert("parent_text".to_string(), parent_text.to_string());
+        }
+        EmbedData::new(EmbeddingResult::DenseVector(vec![1.0; 384]), Some(text.to_string()), Some(metadata))
+    }
+
+    #[tokio::test]
+    async fn test_save_and_find() {
+        let dir = tempdir().unwrap();
+        let db = DB::new(dir.path().join("test_db").to_str().unwrap()).await.unwrap();
+        let repo = FileParentsRepo::new(&db).await.unwrap();
+
+        let mut data = vec![
+            chunk(
+                "炉温上限为 450 度",
+                "manual.pdf#0:37-37",
+                Some("第 37 页：炉温上限为 450 度，超过后自动断电"),
+            ),
+            chunk("超过后自动断电", "manual.pdf#0:37-37", None),
+            chunk("打开炉门前先确认温度", "manual.pdf#1", Some("安全须知\n打开炉门前先确认温度")),
+        ];
+        let records = FileParentRecordFields::new("manual.pdf".to_string(), &mut data);
+        // 上级文本从文本块元数据中移除
+        assert!(data.iter().all(|d| !d.metadata.as_ref().unwrap().contains_key("parent_text")));
+        repo.save(records).await.unwrap();
+
+        let ids = vec!["manual.pdf#0:37-37".to_string(), "it's missing".to_string()];
+        let parents = repo.find_by_ids(&ids).await.unwrap();
+        assert_eq!(parents.len(), 1);
+        assert_eq!(parents["manual.pdf#0:37-37"], "第 37 页：炉温上限为 450 度，超过后自动断电");
+
+        // 重新同步时替换上次的记录
+        let mut data = vec![chunk("打开炉门前先确认温度", "manual.pdf#1", Some("安全须知（修订）"))];
+        repo.save(FileParentRecordFields::new("manual.pdf".to_string(), &mut data)).await.unwrap();
+        let parents = repo
+            .find_by_ids(&["manual.pdf#0:37-37".to_string(), "manual.pdf#1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(parents, HashMap::from([("manual.pdf#1".to_string(), "安全须知（修订）".to_string())]));
+
+        repo.delete_by("manual.pdf").await.unwrap();
+        assert!(repo.find_by_ids(&["manual.pdf#1".to_string()]).await.unwrap().is_empty());
+    }
+}
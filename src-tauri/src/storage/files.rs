@@ -5,6 +5,7 @@ use arrow_schema::{DataType, Field, Schema};
 use chrono::Local;
 use futures::TryStreamExt;
 use lancedb::query::{ExecutableQuery, QueryBase};
+use lancedb::table::NewColumnTransform;
 use lancedb::Table;
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
@@ -18,15 +19,56 @@ static DEFINE_FILES_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
         Field::new("add_time", DataType::Int64, false),
         Field::new("sync_time", DataType::Int64, false),
         Field::new("progress", DataType::UInt32, false),
+        // 同步状态，见 SyncStatus。旧版本的表没有此列，打开时添加，因此可以为空
+        Field::new("status", DataType::Utf8, true),
     ]))
 });
 
+/// 同步项的状态，与同步进度分开保存
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// 等待同步、正在同步或已同步，见 progress
+    #[default]
+    Normal,
+    /// 文件已加密，等待用户提供密码。设置密码后重新同步
+    NeedsPassword,
+    /// 文件超出提取限制被跳过，原因记录在 skipped_files 表中。修改限制后重新同步
+    Skipped,
+}
+
+impl SyncStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SyncStatus::Normal => "normal",
+            SyncStatus::NeedsPassword => "needs_password",
+            SyncStatus::Skipped => "skipped",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "needs_password" => SyncStatus::NeedsPassword,
+            "skipped" => SyncStatus::Skipped,
+            _ => SyncStatus::Normal,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct FilesRepo(Table);
 
 impl FilesRepo {
     pub async fn new(db: &DB) -> AppResult<Self> {
         let table = db.get_or_crate_table("files", DEFINE_FILES_SCHEMA.clone()).await?;
+        if table.schema().await?.column_with_name("status").is_none() {
+            table
+                .add_columns(
+                    NewColumnTransform::SqlExpressions(vec![("status".to_string(), "'normal'".to_string())]),
+                    None,
+                )
+                .await?;
+        }
         Ok(Self(table))
     }
 
@@ -52,6 +94,7 @@ impl FilesRepo {
             records.progresses.push(0);
         }
 
+        let statuses = vec![SyncStatus::Normal.as_str(); records.names.len()];
         let batches = RecordBatch::try_new(
             DEFINE_FILES_SCHEMA.clone(),
             vec![
@@ -61,6 +104,7 @@ impl FilesRepo {
                 Arc::new(Int64Array::from(records.add_times)),
                 Arc::new(Int64Array::from(records.sync_times)),
                 Arc::new(UInt32Array::from(records.progresses)),
+                Arc::new(StringArray::from(statuses)),
             ],
         );
 
@@ -88,7 +132,7 @@ impl FilesRepo {
     pub async fn query_progress_zero(&self, limit: usize) -> AppResult<Vec<FileRecord>> {
         let results = self
             .query()
-            .only_if("progress = 0")
+            .only_if("progress = 0 AND status = 'normal'")
             .limit(limit)
             .execute()
             .await?
@@ -100,6 +144,39 @@ impl FilesRepo {
         Ok(records)
     }
 
+    /// 重置全部数据的 progress，下次同步时重新生成向量
+    pub async fn reset_progress(&self) -> AppResult<()> {
+        self.update()
+            .only_if("progress > 0")
+            .column("progress", "0")
+            .column("status", "'normal'")
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 重置被跳过的数据的 progress，修改提取限制后重新同步。`sync_paths` 为 skipped_files 中有记录的同步项，
+    /// 包括目录中只有部分文件被跳过、其余文件已经同步完成的同步项
+    pub async fn retry_skipped(&self, sync_paths: &[String]) -> AppResult<()> {
+        let mut filter = "status = 'skipped'".to_string();
+        if !sync_paths.is_empty() {
+            let paths = sync_paths
+                .iter()
+                .map(|path| format!("'{}'", path.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ");
+            filter = format!("{} OR file_path IN ({})", filter, paths);
+        }
+        self.update()
+            .only_if(filter)
+            .column("progress", "0")
+            .column("status", "'normal'")
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 更新同步进度，状态恢复为 `SyncStatus::Normal`
     pub async fn update_progress_and_sync_time(&self, file_path: &str, new_progress: u32) -> AppResult<()> {
         let new_sync_time = Local::now().timestamp();
 
@@ -107,6 +184,22 @@ impl FilesRepo {
             .only_if(format!("file_path = '{}'", file_path))
             .column("progress", new_progress.to_string())
             .column("sync_time", new_sync_time.to_string())
+            .column("status", "'normal'")
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    /// 同步结束但需要用户处理（提供密码、修改提取限制）时标记状态，进度记为 100，不再自动同步
+    pub async fn update_status_and_sync_time(&self, file_path: &str, status: SyncStatus) -> AppResult<()> {
+        let new_sync_time = Local::now().timestamp();
+
+        self.update()
+            .only_if(format!("file_path = '{}'", file_path))
+            .column("progress", "100")
+            .column("sync_time", new_sync_time.to_string())
+            .column("status", format!("'{}'", status.as_str()))
             .execute()
             .await?;
 
@@ -142,6 +235,14 @@ pub struct FileRecord {
     pub add_time: i64,
     pub sync_time: i64,
     pub progress: u32,
+    #[serde(default)]
+    pub status: SyncStatus,
+    /// 跳过的文件及原因，目录中可能只有部分文件被跳过
+    #[serde(default)]
+    pub skip_reasons: Vec<String>,
+    /// 因加密缺少密码而未能读取的文件，同步项是文件时为同步项本身
+    #[serde(default)]
+    pub locked_files: Vec<String>,
 }
 
 impl From<RecordBatch> for FileRecords {
@@ -155,6 +256,7 @@ impl From<RecordBatch> for FileRecords {
         let add_time_array = batch.column(3).as_any().downcast_ref::<Int64Array>().unwrap();
         let sync_time_array = batch.column(4).as_any().downcast_ref::<Int64Array>().unwrap();
         let progress_array = batch.column(5).as_any().downcast_ref::<UInt32Array>().unwrap();
+        let status_array = batch.column_by_name("status").and_then(|c| c.as_any().downcast_ref::<StringArray>());
 
         // 遍历每一行
         for i in 0..batch.num_rows() {
@@ -168,6 +270,10 @@ impl From<RecordBatch> for FileRecords {
             let add_time = add_time_array.value(i);
             let sync_time = sync_time_array.value(i);
             let progress = progress_array.value(i);
+            let status = status_array
+                .filter(|s| !s.is_null(i))
+                .map(|s| SyncStatus::parse(s.value(i)))
+                .unwrap_or_default();
 
             records.push(FileRecord {
                 name,
@@ -176,6 +282,9 @@ impl From<RecordBatch> for FileRecords {
                 add_time,
                 sync_time,
                 progress,
+                status,
+                skip_reasons: Vec::new(),
+                locked_files: Vec::new(),
             });
         }
 
@@ -328,4 +437,84 @@ mod lancedb_files_tests {
         assert!(&records[1].sync_time > &0);
         assert_eq!(&records[1].progress, &100);
     }
+
+    // 测试加密文件的需要密码状态
+    #[tokio::test]
+    async fn test_needs_password() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let paths = file_paths();
+        repo.insert_data(paths.clone()).await.unwrap();
+        repo.update_status_and_sync_time(&paths[1], SyncStatus::NeedsPassword).await.unwrap();
+
+        let records = repo.query_all().await.unwrap();
+        assert_eq!(records[0].status, SyncStatus::Normal);
+        assert_eq!(records[1].status, SyncStatus::NeedsPassword);
+        assert_eq!(records[1].progress, 100);
+
+        // 需要密码的文件不会被重复同步
+        let records = repo.query_progress_zero(10).await.unwrap();
+        assert_eq!(records.len(), 1);
+    }
+
+    // 测试旧版本没有 status 列的表在打开时添加该列
+    #[tokio::test]
+    async fn test_add_status_column() {
+        let dir = tempdir().unwrap();
+        let db = DB::new(dir.path().join("test_db").to_str().unwrap()).await.unwrap();
+
+        let schema = Arc::new(Schema::new(DEFINE_FILES_SCHEMA.fields()[..6].to_vec()));
+        let table = db.get_or_crate_table("files", schema.clone()).await.unwrap();
+        let batches = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(StringArray::from(vec!["a.pdf"])),
+                Arc::new(StringArray::from(vec!["/data/a.pdf"])),
+                Arc::new(StringArray::from(vec![Some("pdf")])),
+                Arc::new(Int64Array::from(vec![0])),
+                Arc::new(Int64Array::from(vec![0])),
+                Arc::new(UInt32Array::from(vec![50])),
+            ],
+        );
+        table.add(RecordBatchIterator::new(vec![batches], schema)).execute().await.unwrap();
+
+        let repo = FilesRepo::new(&db).await.unwrap();
+        let records = repo.query_all().await.unwrap();
+        assert_eq!(records[0].status, SyncStatus::Normal);
+        assert_eq!(records[0].progress, 50);
+
+        // 添加列后可以继续插入数据
+        repo.insert_data(vec!["/data/b.pdf".to_string()]).await.unwrap();
+        assert_eq!(repo.query_progress_zero(10).await.unwrap().len(), 1);
+    }
+
+    // 测试被跳过的文件在修改限制后重新同步
+    #[tokio::test]
+    async fn test_retry_skipped() {
+        let dir = tempdir().unwrap();
+        let repo = repo(&dir).await;
+
+        let paths = file_paths();
+        repo.insert_data(paths.clone()).await.unwrap();
+        repo.update_progress_and_sync_time(&paths[0], 100).await.unwrap();
+        repo.update_status_and_sync_time(&paths[1], SyncStatus::Skipped).await.unwrap();
+
+        let records = repo.query_all().await.unwrap();
+        assert_eq!(records[0].status, SyncStatus::Normal);
+        assert_eq!(records[1].status, SyncStatus::Skipped);
+        assert!(repo.query_progress_zero(10).await.unwrap().is_empty());
+
+        repo.retry_skipped(&[]).await.unwrap();
+        let records = repo.query_progress_zero(10).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0].file_path, &paths[1]);
+
+        // 目录中只有部分文件被跳过时同步已完成，按 skipped_files 中的记录重新同步
+        repo.update_progress_and_sync_time(&paths[1], 100).await.unwrap();
+        repo.retry_skipped(&[paths[0].clone()]).await.unwrap();
+        let records = repo.query_progress_zero(10).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0].file_path, &paths[0]);
+    }
 }
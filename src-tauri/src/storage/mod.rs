@@ -1,6 +1,10 @@
 pub mod file_contents;
+pub mod file_parents;
 pub mod files;
 pub mod open_ai;
+pub mod passwords;
+pub mod settings;
+pub mod skipped;
 
 use crate::errors::AppResult;
 use arrow_schema::SchemaRef;
@@ -23,4 +27,19 @@ impl DB {
         let table = self.0.open_table(table_name).execute().await?;
         Ok(table)
     }
+
+    /// 表结构（列名）与定义不一致时删除旧表，返回是否删除
+    pub async fn drop_table_if_outdated<S: Into<SchemaRef>>(&self, table_name: &str, schema: S) -> AppResult<bool> {
+        if !self.0.table_names().execute().await?.contains(&table_name.to_string()) {
+            return Ok(false);
+        }
+        let schema = schema.into();
+        let current = self.0.open_table(table_name).execute().await?.schema().await?;
+        let outdated = current.fields().iter().map(|f| f.name()).ne(schema.fields().iter().map(|f| f.name()));
+        if outdated {
+            log::warn!("Table {} schema changed, dropping it.", table_name);
+            self.0.drop_table(table_name).await?;
+        }
+        Ok(outdated)
+    }
 }
@@ -0,0 +1,154 @@
+use crate::errors::{AidenErrors, AppResult};
+use crate::storage::DB;
+use arrow_array::{BinaryArray, Int64Array, RecordBatch, RecordBatchIterator, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::Local;
+use futures::TryStreamExt;
+use lancedb::query::ExecutableQuery;
+use lancedb::Table;
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock};
+
+/// 密钥在系统钥匙串中的服务名和用户名
+const KEYRING_SERVICE: &str = "aiden";
+const KEYRING_USER: &str = "sync-passwords";
+
+static DEFINE_PASSWORDS_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        // 同步的文件或目录
+        Field::new("file_path", DataType::Utf8, false),
+        Field::new("nonce", DataType::Binary, false),
+        // 加密后的密码
+        Field::new("password", DataType::Binary, false),
+        Field::new("time", DataType::Int64, false),
+    ]))
+});
+
+/// 从系统钥匙串读取加密密码用的密钥，第一次使用时生成并保存。
+/// 已有的密钥长度不正确时返回错误，不重新生成，以免覆盖后已保存的密码都无法解密
+pub fn load_key() -> AppResult<Key> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)?;
+    match entry.get_secret() {
+        Ok(secret) if secret.len() == 32 => Ok(*Key::from_slice(&secret)),
+        Ok(_) => Err(AidenErrors::Str("系统钥匙串中的密钥长度不正确")),
+        Err(keyring::Error::NoEntry) => {
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            entry.set_secret(&key)?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 加密文件的密码，用系统钥匙串中的密钥加密后保存在本地数据库中
+#[derive(Clone)]
+pub struct PasswordsRepo(Table, Option<ChaCha20Poly1305>);
+
+impl PasswordsRepo {
+    /// `key` 为 None（系统钥匙串不可用）时不能保存密码，也读不到已保存的密码
+    pub async fn new(db: &DB, key: Option<Key>) -> AppResult<Self> {
+        let table = db.get_or_crate_table("passwords", DEFINE_PASSWORDS_SCHEMA.clone()).await?;
+        Ok(Self(table, key.map(|key| ChaCha20Poly1305::new(&key))))
+    }
+
+    /// 保存密码，已有的密码会被替换
+    pub async fn save(&self, path: &str, password: &str) -> AppResult<()> {
+        let cipher = self.1.as_ref().ok_or(AidenErrors::Str("系统钥匙串不可用，无法保存密码"))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = cipher
+            .encrypt(&nonce, password.as_bytes())
+            .map_err(|_| AidenErrors::Str("密码加密失败"))?;
+        self.delete_by(path).await?;
+        let batches = RecordBatch::try_new(
+            DEFINE_PASSWORDS_SCHEMA.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![path.to_string()])),
+                Arc::new(BinaryArray::from_vec(vec![nonce.as_slice()])),
+                Arc::new(BinaryArray::from_vec(vec![encrypted.as_slice()])),
+                Arc::new(Int64Array::from(vec![Local::now().timestamp()])),
+            ],
+        );
+
+        self.add(RecordBatchIterator::new(vec![batches], DEFINE_PASSWORDS_SCHEMA.clone()))
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 查询全部数据，返回 (路径, 密码)。无法解密（如钥匙串中的密钥已变化）的密码跳过
+    pub async fn query_all(&self) -> AppResult<Vec<(String, String)>> {
+        let Some(cipher) = &self.1 else {
+            return Ok(Vec::new());
+        };
+        let results = self.query().execute().await?.try_collect::<Vec<_>>().await?;
+        let mut records = Vec::new();
+        for batch in results {
+            let path_array = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+            let nonce_array = batch.column(1).as_any().downcast_ref::<BinaryArray>().unwrap();
+            let password_array = batch.column(2).as_any().downcast_ref::<BinaryArray>().unwrap();
+            for i in 0..batch.num_rows() {
+                let path = path_array.value(i).to_string();
+                let nonce = nonce_array.value(i);
+                if nonce.len() != 12 {
+                    log::warn!("Invalid nonce of the saved password, {}", path);
+                    continue;
+                }
+                match cipher.decrypt(Nonce::from_slice(nonce), password_array.value(i)) {
+                    Ok(password) => records.push((path, String::from_utf8_lossy(&password).to_string())),
+                    Err(_) => log::warn!("Failed to decrypt the saved password, {}", path),
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// 删除数据
+    pub async fn delete_by(&self, path: &str) -> AppResult<()> {
+        self.delete(&format!("file_path = '{}'", path)).await?;
+        Ok(())
+    }
+}
+
+impl Deref for PasswordsRepo {
+    type Target = Table;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod lancedb_passwords_tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_save_and_delete() {
+        let dir = tempdir().unwrap();
+        let db = DB::new(dir.path().join("test_db").to_str().unwrap()).await.unwrap();
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let repo = PasswordsRepo::new(&db, Some(key)).await.unwrap();
+
+        repo.save("/data/manuals", "old").await.unwrap();
+        repo.save("/data/manuals", "secret").await.unwrap();
+        assert_eq!(repo.query_all().await.unwrap(), vec![("/data/manuals".to_string(), "secret".to_string())]);
+
+        // 数据库中保存的是密文
+        let batches = repo.query().execute().await.unwrap().try_collect::<Vec<_>>().await.unwrap();
+        let stored = batches[0].column(2).as_any().downcast_ref::<BinaryArray>().unwrap().value(0);
+        assert!(!stored.windows(6).any(|w| w == b"secret"));
+
+        // 密钥不同时读不到密码
+        let other = PasswordsRepo::new(&db, Some(ChaCha20Poly1305::generate_key(&mut OsRng))).await.unwrap();
+        assert!(other.query_all().await.unwrap().is_empty());
+        // 钥匙串不可用时不能保存密码
+        let unavailable = PasswordsRepo::new(&db, None).await.unwrap();
+        assert!(unavailable.save("/data/manuals", "secret").await.is_err());
+        assert!(unavailable.query_all().await.unwrap().is_empty());
+
+        repo.delete_by("/data/manuals").await.unwrap();
+        assert!(repo.query_all().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::errors::AppResult;
+use crate::storage::DB;
+use arrow_array::{Int64Array, RecordBatch, RecordBatchIterator, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::Local;
+use futures::TryStreamExt;
+use lancedb::query::{ExecutableQuery, QueryBase};
+use lancedb::Table;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock};
+
+static DEFINE_SETTINGS_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        Field::new("key", DataType::Utf8, false),
+        // JSON 格式的设置值
+        Field::new("value", DataType::Utf8, false),
+        Field::new("time", DataType::Int64, false),
+    ]))
+});
+
+/// 应用设置，每个键保存一个 JSON 值
+#[derive(Clone)]
+pub struct SettingsRepo(Table);
+
+impl SettingsRepo {
+    pub async fn new(db: &DB) -> AppResult<Self> {
+        let table = db.get_or_crate_table("settings", DEFINE_SETTINGS_SCHEMA.clone()).await?;
+        Ok(Self(table))
+    }
+
+    /// 读取设置，没有保存过时返回 None
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+        let results = self
+            .query()
+            .only_if(format!("key = '{}'", key))
+            .limit(1)
+            .execute()
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        for batch in results {
+            let value_array = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+            if batch.num_rows() > 0 {
+                return Ok(Some(serde_json::from_str(value_array.value(0))?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 保存设置，已有的值会被替换
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> AppResult<()> {
+        let value = serde_json::to_string(value)?;
+        self.delete(&format!("key = '{}'", key)).await?;
+        let batches = RecordBatch::try_new(
+            DEFINE_SETTINGS_SCHEMA.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![key.to_string()])),
+                Arc::new(StringArray::from(vec![value])),
+                Arc::new(Int64Array::from(vec![Local::now().timestamp()])),
+            ],
+        );
+
+        self.add(RecordBatchIterator::new(vec![batches], DEFINE_SETTINGS_SCHEMA.clone()))
+            .execute()
+            .await?;
+        Ok(())
+    }
+}
+
+impl Deref for SettingsRepo {
+    type Target = Table;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod lancedb_settings_tests {
+    use super::*;
+    use crate::extract::ExtractLimits;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_get_and_set() {
+        let dir = tempdir().unwrap();
+        let db = DB::new(dir.path().join("test_db").to_str().unwrap()).await.unwrap();
+        let repo = SettingsRepo::new(&db).await.unwrap();
+
+        assert_eq!(repo.get::<ExtractLimits>(ExtractLimits::SETTINGS_KEY).await.unwrap(), None);
+
+        let limits = ExtractLimits {
+            max_pages: 100,
+            ..Default::default()
+        };
+        repo.set(ExtractLimits::SETTINGS_KEY, &ExtractLimits::default()).await.unwrap();
+        repo.set(ExtractLimits::SETTINGS_KEY, &limits).await.unwrap();
+        assert_eq!(repo.get(ExtractLimits::SETTINGS_KEY).await.unwrap(), Some(limits));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::errors::AppResult;
+use crate::storage::DB;
+use arrow_array::{Int64Array, RecordBatch, RecordBatchIterator, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::Local;
+use futures::TryStreamExt;
+use lancedb::query::ExecutableQuery;
+use lancedb::Table;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock};
+
+/// 文件因加密缺少密码而未能读取时记录的原因
+pub const PASSWORD_REQUIRED: &str = "需要密码";
+
+/// 扫描件或图片因缺少 OCR 模型而未能识别时记录的原因
+pub const OCR_UNAVAILABLE: &str = "缺少 OCR 模型";
+
+static DEFINE_SKIPPED_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        // 同步的文件或目录
+        Field::new("sync_path", DataType::Utf8, false),
+        // 被跳过的文件，同步项是文件时与 sync_path 相同
+        Field::new("file_path", DataType::Utf8, false),
+        Field::new("reason", DataType::Utf8, false),
+        Field::new("time", DataType::Int64, false),
+    ]))
+});
+
+/// 超出提取限制或缺少密码而跳过的文件及原因
+#[derive(Clone)]
+pub struct SkippedRepo(Table);
+
+impl SkippedRepo {
+    pub async fn new(db: &DB) -> AppResult<Self> {
+        let table = db.get_or_crate_table("skipped_files", DEFINE_SKIPPED_SCHEMA.clone()).await?;
+        Ok(Self(table))
+    }
+
+    /// 保存一个同步项本次同步跳过的文件 (文件路径, 原因)，替换上次同步的记录
+    pub async fn save(&self, sync_path: &str, skipped: &[(String, String)]) -> AppResult<()> {
+        self.delete_by(sync_path).await?;
+        if skipped.is_empty() {
+            return Ok(());
+        }
+        let batches = RecordBatch::try_new(
+            DEFINE_SKIPPED_SCHEMA.clone(),
+            vec![
+                Arc::new(StringArray::from(vec![sync_path.to_string(); skipped.len()])),
+                Arc::new(StringArray::from_iter_values(skipped.iter().map(|(file_path, _)| file_path))),
+                Arc::new(StringArray::from_iter_values(skipped.iter().map(|(_, reason)| reason))),
+                Arc::new(Int64Array::from(vec![Local::now().timestamp(); skipped.len()])),
+            ],
+        );
+
+        self.add(RecordBatchIterator::new(vec![batches], DEFINE_SKIPPED_SCHEMA.clone()))
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    /// 查询全部数据
+    pub async fn query_all(&self) -> AppResult<Vec<SkippedRecord>> {
+        let results = self.query().execute().await?.try_collect::<Vec<_>>().await?;
+        let mut records = Vec::new();
+        for batch in results {
+            let sync_path_array = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+            let file_path_array = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+            let reason_array = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+            for i in 0..batch.num_rows() {
+                records.push(SkippedRecord {
+                    sync_path: sync_path_array.value(i).to_string(),
+                    file_path: file_path_array.value(i).to_string(),
+                    reason: reason_array.value(i).to_string(),
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    /// 删除数据
+    pub async fn delete_by(&self, sync_path: &str) -> AppResult<()> {
+        self.delete(&format!("sync_path = '{}'", sync_path)).await?;
+        Ok(())
+    }
+}
+
+impl Deref for SkippedRepo {
+    type Target = Table;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct SkippedRecord {
+    pub sync_path: String,
+    pub file_path: String,
+    pub reason: String,
+}
+
+impl SkippedRecord {
+    /// 文件因加密缺少密码而未能读取，设置密码后重新同步，修改提取限制时不重新同步
+    pub fn needs_password(&self) -> bool {
+        self.reason == PASSWORD_REQUIRED
+    }
+
+    /// 界面上显示的跳过原因，目录中的文件带上文件路径
+    pub fn describe(&self) -> String {
+        if self.file_path == self.sync_path {
+            self.reason.clone()
+        } else {
+            format!("{}: {}", self.file_path, self.reason)
+        }
+    }
+}
+
+#[cfg(test)]
+mod lancedb_skipped_tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_save_and_delete() {
+        let dir = tempdir().unwrap();
+        let db = DB::new(dir.path().join("test_db").to_str().unwrap()).await.unwrap();
+        let repo = SkippedRepo::new(&db).await.unwrap();
+
+        let skipped = vec![
+            ("/data/scans/a.pdf".to_string(), "页数 3000 超过上限 2000".to_string()),
+            ("/data/scans/b.pdf".to_string(), "提取超过 120 秒".to_string()),
+        ];
+        repo.save("/data/scans", &skipped).await.unwrap();
+        repo.save(
+            "/data/big.pdf",
+            &[("/data/big.pdf".to_string(), "文件大小 812.0 MB 超过上限 200.0 MB".to_string())],
+        )
+        .await
+        .unwrap();
+        let records = repo.query_all().await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].describe(), "/data/scans/a.pdf: 页数 3000 超过上限 2000");
+        assert_eq!(records[2].describe(), "文件大小 812.0 MB 超过上限 200.0 MB");
+
+        // 重新同步后没有跳过的文件，清除旧记录
+        repo.save("/data/scans", &[]).await.unwrap();
+        assert_eq!(repo.query_all().await.unwrap().len(), 1);
+
+        repo.delete_by("/data/big.pdf").await.unwrap();
+        assert!(repo.query_all().await.unwrap().is_empty());
+
+        // 缺少密码的文件单独标记，设置密码后重新同步
+        repo.save("/data/vendor", &[("/data/vendor/a.pdf".to_string(), PASSWORD_REQUIRED.to_string())])
+            .await
+            .unwrap();
+        let records = repo.query_all().await.unwrap();
+        assert!(records[0].needs_password());
+        assert_eq!(records[0].describe(), "/data/vendor/a.pdf: 需要密码");
+    }
+}